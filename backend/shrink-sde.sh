@@ -24,7 +24,11 @@ create table dgmTypeAttributes as select * from ext.dgmTypeAttributes where attr
     633, -- meta level
     984,985,986,987, -- resists
     182,183,184,1285,1289,1290, -- skill req
-    277,278,279,1286,1287,1288 -- skil req level
+    277,278,279,1286,1287,1288, -- skil req level
+    9,263,265,72,1159, -- structure, shield and armor HP, HP bonuses
+    51,64,204,128, -- rate of fire, damage multiplier, speed multiplier, charge size
+    114,116,117,118, -- damage
+    267,268,269,270,271,272,273,274,109,110,111,113 -- armor, shield and structure resonance
 );
 create index dgmTypeAttributes_typeID on dgmTypeAttributes (typeID);
 
@@ -0,0 +1,2 @@
+-- Track when a fleet was registered so the most recent one can be treated as active
+ALTER TABLE fleet ADD COLUMN registered_at BIGINT NOT NULL DEFAULT (EXTRACT(EPOCH FROM NOW()));
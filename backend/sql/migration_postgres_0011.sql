@@ -0,0 +1,2 @@
+-- Silent bans are recorded but never announced to the webhook
+ALTER TABLE ban ADD COLUMN silent BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,8 @@
+-- Pilots that FCs have allowed past the x-up skill gate
+CREATE TABLE skill_gate_override (
+  character_id BIGINT PRIMARY KEY NOT NULL,
+  granted_by_id BIGINT NOT NULL,
+  granted_at BIGINT NOT NULL,
+  CONSTRAINT skill_gate_override_character_id FOREIGN KEY (character_id) REFERENCES character (id),
+  CONSTRAINT skill_gate_override_granted_by_id FOREIGN KEY (granted_by_id) REFERENCES character (id)
+);
@@ -0,0 +1,8 @@
+-- Periodic snapshots of the number of fits in each waitlist category
+CREATE TABLE waitlist_metrics (
+  id BIGINT NOT NULL PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+  recorded_at BIGINT NOT NULL,
+  category VARCHAR(10) NOT NULL,
+  size BIGINT NOT NULL
+);
+CREATE INDEX waitlist_metrics_recorded_at ON waitlist_metrics (recorded_at);
@@ -0,0 +1,10 @@
+-- Responses to POSTs sent with an Idempotency-Key header, so retries can be answered without repeating them
+CREATE TABLE idempotency_key (
+  account_id BIGINT NOT NULL,
+  endpoint VARCHAR(32) NOT NULL,
+  idempotency_key VARCHAR(255) NOT NULL,
+  response TEXT,
+  created_at BIGINT NOT NULL,
+  PRIMARY KEY (account_id, endpoint, idempotency_key),
+  CONSTRAINT idempotency_key_account_id FOREIGN KEY (account_id) REFERENCES character (id)
+);
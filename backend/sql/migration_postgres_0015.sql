@@ -0,0 +1,3 @@
+-- The FC a ban was really issued by, when it was created through an integration account
+ALTER TABLE ban ADD COLUMN on_behalf_of BIGINT;
+ALTER TABLE ban ADD CONSTRAINT on_behalf_of FOREIGN KEY (on_behalf_of) REFERENCES character (id);
@@ -0,0 +1,5 @@
+-- Lets inactive x-ups expire
+ALTER TABLE waitlist_entry ADD COLUMN last_activity_at BIGINT;
+UPDATE waitlist_entry SET last_activity_at=joined_at;
+ALTER TABLE waitlist_entry ALTER COLUMN last_activity_at SET NOT NULL;
+ALTER TABLE waitlist_entry ADD COLUMN notify_on_expiry BOOLEAN NOT NULL DEFAULT FALSE;
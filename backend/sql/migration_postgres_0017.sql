@@ -0,0 +1,4 @@
+-- The day a temporary ban was set to expire on, revoked_at is derived from it.
+-- Existing bans are assumed to have been stored with the current downtime offset (11:00).
+ALTER TABLE ban ADD COLUMN expiry_day BIGINT;
+UPDATE ban SET expiry_day = revoked_at - 60 * 60 * 11 WHERE revoked_at IS NOT NULL AND revoked_by IS NULL;
@@ -0,0 +1,10 @@
+-- Corporations exempt from the corporation and alliance bans their members would inherit
+CREATE TABLE ban_exception (
+  id BIGINT NOT NULL PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+  corporation_id BIGINT NOT NULL UNIQUE,
+  corporation_name VARCHAR(64),
+  reason VARCHAR(512) NOT NULL,
+  created_at BIGINT NOT NULL,
+  created_by BIGINT NOT NULL,
+  CONSTRAINT created_by FOREIGN KEY (created_by) REFERENCES character (id)
+);
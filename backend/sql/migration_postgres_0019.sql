@@ -0,0 +1,2 @@
+-- Structured ban category, e.g. "RMT" or "Scam"
+ALTER TABLE ban ADD COLUMN category VARCHAR(32);
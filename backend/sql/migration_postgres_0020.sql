@@ -0,0 +1,2 @@
+-- Member count reported by ESI, refreshed along with the rest of the corporation
+ALTER TABLE corporation ADD COLUMN member_count BIGINT;
@@ -0,0 +1,4 @@
+-- Bans waiting for a second FC to approve them, and who did
+ALTER TABLE ban ADD COLUMN pending_approval BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE ban ADD COLUMN approved_by BIGINT;
+ALTER TABLE ban ADD CONSTRAINT approved_by FOREIGN KEY (approved_by) REFERENCES character (id);
@@ -0,0 +1,2 @@
+-- Earlier versions of a ban's reason: [{reason, edited_at, edited_by}]
+ALTER TABLE ban ADD COLUMN reason_history JSONB NOT NULL DEFAULT '[]';
@@ -0,0 +1,2 @@
+-- Bans created while ESI was down, entity_name is a placeholder until it's looked up
+ALTER TABLE ban ADD COLUMN name_pending BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,15 @@
+-- Background estimates of how many pilots a corporation or alliance ban would reach
+CREATE TABLE ban_impact_job (
+  id BIGINT NOT NULL PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+  entity_type VARCHAR(16) NOT NULL CHECK (entity_type IN ('Corporation', 'Alliance')),
+  entity_id BIGINT NOT NULL,
+  status VARCHAR(16) NOT NULL CHECK (status IN ('queued', 'running', 'done', 'failed')),
+  progress BIGINT NOT NULL DEFAULT 0,
+  total BIGINT,
+  result TEXT,
+  error TEXT,
+  created_by BIGINT NOT NULL,
+  created_at BIGINT NOT NULL,
+  updated_at BIGINT NOT NULL,
+  CONSTRAINT created_by FOREIGN KEY (created_by) REFERENCES character (id)
+);
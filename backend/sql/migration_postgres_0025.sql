@@ -0,0 +1,3 @@
+-- Name pattern bans, entity_name holds the pattern and entity_id is always 0
+ALTER TABLE ban DROP CONSTRAINT ban_entity_type_check;
+ALTER TABLE ban ADD CONSTRAINT ban_entity_type_check CHECK (entity_type IN ('Account', 'Character', 'Corporation', 'Alliance', 'NamePattern'));
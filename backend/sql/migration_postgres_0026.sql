@@ -0,0 +1,17 @@
+-- Earlier versions of ban reasons move from the reason_history column to their own table
+CREATE TABLE ban_reason_history (
+  id BIGINT NOT NULL PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+  ban_id BIGINT NOT NULL,
+  reason VARCHAR(512) NOT NULL,
+  edited_at BIGINT NOT NULL,
+  edited_by BIGINT NOT NULL,
+  CONSTRAINT ban_id FOREIGN KEY (ban_id) REFERENCES ban (id),
+  CONSTRAINT edited_by FOREIGN KEY (edited_by) REFERENCES character (id)
+);
+CREATE INDEX ban_reason_history_ban_id ON ban_reason_history (ban_id);
+
+INSERT INTO ban_reason_history (ban_id, reason, edited_at, edited_by)
+  SELECT ban.id, edit->>'reason', (edit->>'edited_at')::BIGINT, (edit->>'edited_by')::BIGINT
+  FROM ban, jsonb_array_elements(ban.reason_history) AS edit;
+
+ALTER TABLE ban DROP COLUMN reason_history;
@@ -0,0 +1,2 @@
+-- The category ESI reported when the ban was created, to catch mislabeled entity types
+ALTER TABLE ban ADD COLUMN esi_category VARCHAR(16);
@@ -0,0 +1,10 @@
+-- Unfinished bans saved by FCs, only visible to the account that wrote them
+CREATE TABLE ban_draft (
+  id BIGINT NOT NULL PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+  account_id BIGINT NOT NULL,
+  payload TEXT NOT NULL,
+  created_at BIGINT NOT NULL,
+  updated_at BIGINT NOT NULL,
+  CONSTRAINT ban_draft_account_id FOREIGN KEY (account_id) REFERENCES character (id)
+);
+CREATE INDEX ban_draft_account_id ON ban_draft (account_id);
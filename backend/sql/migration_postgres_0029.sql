@@ -0,0 +1,6 @@
+-- Discord accounts of our pilots, filled in by the Discord bot
+CREATE TABLE character_discord (
+  character_id BIGINT NOT NULL PRIMARY KEY,
+  discord_id VARCHAR(32) NOT NULL,
+  CONSTRAINT character_discord_character_id FOREIGN KEY (character_id) REFERENCES character (id)
+);
@@ -0,0 +1,8 @@
+-- Free-form labels on bans, a ban can have several
+CREATE TABLE ban_tag (
+  ban_id BIGINT NOT NULL,
+  tag VARCHAR(32) NOT NULL,
+  PRIMARY KEY (ban_id, tag),
+  CONSTRAINT ban_tag_ban_id FOREIGN KEY (ban_id) REFERENCES ban (id)
+);
+CREATE INDEX ban_tag_tag ON ban_tag (tag);
@@ -0,0 +1,2 @@
+-- The expiry the last "expiring soon" event was sent for, see core::ban_expiry
+ALTER TABLE ban ADD COLUMN expiry_notified_for BIGINT;
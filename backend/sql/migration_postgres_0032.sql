@@ -0,0 +1,2 @@
+-- When the banned character confirmed seeing the public reason
+ALTER TABLE ban ADD COLUMN acknowledged_at BIGINT;
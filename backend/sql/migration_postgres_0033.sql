@@ -0,0 +1,12 @@
+-- Earlier issuers of bans that were handed to another FC
+CREATE TABLE ban_issuer_history (
+  id BIGINT NOT NULL PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+  ban_id BIGINT NOT NULL,
+  issued_by BIGINT NOT NULL,
+  reassigned_at BIGINT NOT NULL,
+  reassigned_by BIGINT NOT NULL,
+  CONSTRAINT ban_id FOREIGN KEY (ban_id) REFERENCES ban (id),
+  CONSTRAINT issued_by FOREIGN KEY (issued_by) REFERENCES character (id),
+  CONSTRAINT reassigned_by FOREIGN KEY (reassigned_by) REFERENCES character (id)
+);
+CREATE INDEX ban_issuer_history_ban_id ON ban_issuer_history (ban_id);
@@ -0,0 +1,3 @@
+-- The ban a corrected ban replaces
+ALTER TABLE ban ADD COLUMN supersedes_ban_id BIGINT;
+ALTER TABLE ban ADD CONSTRAINT supersedes_ban_id FOREIGN KEY (supersedes_ban_id) REFERENCES ban (id);
@@ -0,0 +1,3 @@
+-- Finds bans with similar reasons, see BanService::similar
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+CREATE INDEX ban_reason_trgm ON ban USING GIN (reason gin_trgm_ops);
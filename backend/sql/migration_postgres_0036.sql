@@ -0,0 +1,6 @@
+-- Bans are looked up by entity, by issuer and by whether they're still active.
+-- To revert:
+--   DROP INDEX ban_entity; DROP INDEX ban_issued_by; DROP INDEX ban_revoked_at;
+CREATE INDEX ban_entity ON ban (entity_id, entity_type);
+CREATE INDEX ban_issued_by ON ban (issued_by);
+CREATE INDEX ban_revoked_at ON ban (revoked_at);
@@ -0,0 +1,2 @@
+-- Operational notes for other FCs, kept apart from the reason used in appeals
+ALTER TABLE ban ADD COLUMN fc_note VARCHAR(512);
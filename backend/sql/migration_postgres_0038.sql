@@ -0,0 +1,2 @@
+-- When a ban was last changed after it was issued, for partners syncing only what changed
+ALTER TABLE ban ADD COLUMN updated_at BIGINT;
@@ -0,0 +1,2 @@
+-- Bans decided on now but only enforced from a later date
+ALTER TABLE ban ADD COLUMN starts_at BIGINT;
@@ -0,0 +1,7 @@
+-- For the token health of FCs: when ESI last gave us a token, and when it last refused to
+ALTER TABLE refresh_token ADD COLUMN refreshed_at BIGINT;
+ALTER TABLE character ADD COLUMN esi_token_failed_at BIGINT;
+-- Access tokens last 20 minutes, so this is when the stored one was issued
+UPDATE refresh_token SET refreshed_at = (
+  SELECT expires - 1200 FROM access_token WHERE access_token.character_id = refresh_token.character_id
+);
@@ -0,0 +1,4 @@
+-- Who last edited a ban. Edits used to overwrite issued_by and issued_at, which have to stay
+-- the FC who issued the ban and when.
+ALTER TABLE ban ADD COLUMN updated_by BIGINT;
+ALTER TABLE ban ADD CONSTRAINT updated_by FOREIGN KEY (updated_by) REFERENCES character (id);
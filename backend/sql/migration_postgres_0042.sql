@@ -0,0 +1,2 @@
+-- The start the roles of a scheduled ban were last removed for, see core::ban_expiry
+ALTER TABLE ban ADD COLUMN start_notified_for BIGINT;
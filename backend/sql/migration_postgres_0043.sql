@@ -0,0 +1,3 @@
+-- The admin who created a ban while the entity already had an active one
+ALTER TABLE ban ADD COLUMN duplicate_override_by BIGINT;
+ALTER TABLE ban ADD CONSTRAINT duplicate_override_by FOREIGN KEY (duplicate_override_by) REFERENCES character (id);
@@ -1,3 +1,5 @@
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
 -- Character & Auth related tables
 CREATE TABLE alliance (
   id BIGINT PRIMARY KEY NOT NULL,
@@ -9,6 +11,7 @@ CREATE TABLE corporation (
   name TEXT NOT NULL,
   alliance_id BIGINT,
   updated_at BIGINT NOT NULL,
+  member_count BIGINT,
   CONSTRAINT alliance_id FOREIGN KEY (alliance_id) REFERENCES alliance (id)
 );
 
@@ -17,9 +20,16 @@ CREATE TABLE character (
   name VARCHAR(255) NOT NULL,
   corporation_id BIGINT,
   last_seen BIGINT NOT NULL DEFAULT (EXTRACT(EPOCH FROM NOW())),
+  esi_token_failed_at BIGINT,
   CONSTRAINT character_corporation FOREIGN KEY (corporation_id) REFERENCES corporation (id)
 );
 
+CREATE TABLE character_discord (
+  character_id BIGINT NOT NULL PRIMARY KEY,
+  discord_id VARCHAR(32) NOT NULL,
+  CONSTRAINT character_discord_character_id FOREIGN KEY (character_id) REFERENCES character (id)
+);
+
 CREATE TABLE access_token (
   character_id BIGINT NOT NULL,
   access_token VARCHAR(2048) NOT NULL,
@@ -33,6 +43,7 @@ CREATE TABLE refresh_token (
   character_id BIGINT NOT NULL,
   refresh_token VARCHAR(255) NOT NULL,
   scopes VARCHAR(1024) NOT NULL,
+  refreshed_at BIGINT,
   PRIMARY KEY (character_id),
   CONSTRAINT refresh_token_character_id FOREIGN KEY (character_id) REFERENCES character (id)
 );
@@ -72,15 +83,107 @@ CREATE TABLE ban (
   id BIGINT NOT NULL PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
   entity_id BIGINT NOT NULL,
   entity_name VARCHAR(64),
-  entity_type VARCHAR(16) NOT NULL CHECK (entity_type IN ('Account', 'Character', 'Corporation', 'Alliance')),
+  entity_type VARCHAR(16) NOT NULL CHECK (entity_type IN ('Account', 'Character', 'Corporation', 'Alliance', 'NamePattern')),
   issued_at BIGINT NOT NULL,
   issued_by BIGINT NOT NULL,
   public_reason VARCHAR(512),
   reason VARCHAR(512) NOT NULL,
   revoked_at BIGINT,
   revoked_by BIGINT,
+  silent BOOLEAN NOT NULL DEFAULT FALSE,
+  on_behalf_of BIGINT,
+  expiry_day BIGINT,
+  category VARCHAR(32),
+  pending_approval BOOLEAN NOT NULL DEFAULT FALSE,
+  approved_by BIGINT,
+  name_pending BOOLEAN NOT NULL DEFAULT FALSE,
+  esi_category VARCHAR(16),
+  expiry_notified_for BIGINT,
+  acknowledged_at BIGINT,
+  supersedes_ban_id BIGINT,
+  fc_note VARCHAR(512),
+  updated_at BIGINT,
+  starts_at BIGINT,
+  updated_by BIGINT,
+  start_notified_for BIGINT,
+  duplicate_override_by BIGINT,
+  CONSTRAINT issued_by FOREIGN KEY (issued_by) REFERENCES character (id),
+  CONSTRAINT revoked_by FOREIGN KEY (revoked_by) REFERENCES character (id),
+  CONSTRAINT on_behalf_of FOREIGN KEY (on_behalf_of) REFERENCES character (id),
+  CONSTRAINT approved_by FOREIGN KEY (approved_by) REFERENCES character (id),
+  CONSTRAINT supersedes_ban_id FOREIGN KEY (supersedes_ban_id) REFERENCES ban (id),
+  CONSTRAINT updated_by FOREIGN KEY (updated_by) REFERENCES character (id),
+  CONSTRAINT duplicate_override_by FOREIGN KEY (duplicate_override_by) REFERENCES character (id)
+);
+
+CREATE TABLE ban_reason_history (
+  id BIGINT NOT NULL PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+  ban_id BIGINT NOT NULL,
+  reason VARCHAR(512) NOT NULL,
+  edited_at BIGINT NOT NULL,
+  edited_by BIGINT NOT NULL,
+  CONSTRAINT ban_id FOREIGN KEY (ban_id) REFERENCES ban (id),
+  CONSTRAINT edited_by FOREIGN KEY (edited_by) REFERENCES character (id)
+);
+CREATE INDEX ban_reason_history_ban_id ON ban_reason_history (ban_id);
+CREATE INDEX ban_reason_trgm ON ban USING GIN (reason gin_trgm_ops);
+CREATE INDEX ban_entity ON ban (entity_id, entity_type);
+CREATE INDEX ban_issued_by ON ban (issued_by);
+CREATE INDEX ban_revoked_at ON ban (revoked_at);
+
+CREATE TABLE ban_issuer_history (
+  id BIGINT NOT NULL PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+  ban_id BIGINT NOT NULL,
+  issued_by BIGINT NOT NULL,
+  reassigned_at BIGINT NOT NULL,
+  reassigned_by BIGINT NOT NULL,
+  CONSTRAINT ban_id FOREIGN KEY (ban_id) REFERENCES ban (id),
   CONSTRAINT issued_by FOREIGN KEY (issued_by) REFERENCES character (id),
-  CONSTRAINT revoked_by FOREIGN KEY (revoked_by) REFERENCES character (id)
+  CONSTRAINT reassigned_by FOREIGN KEY (reassigned_by) REFERENCES character (id)
+);
+CREATE INDEX ban_issuer_history_ban_id ON ban_issuer_history (ban_id);
+
+CREATE TABLE ban_tag (
+  ban_id BIGINT NOT NULL,
+  tag VARCHAR(32) NOT NULL,
+  PRIMARY KEY (ban_id, tag),
+  CONSTRAINT ban_tag_ban_id FOREIGN KEY (ban_id) REFERENCES ban (id)
+);
+CREATE INDEX ban_tag_tag ON ban_tag (tag);
+
+CREATE TABLE ban_draft (
+  id BIGINT NOT NULL PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+  account_id BIGINT NOT NULL,
+  payload TEXT NOT NULL,
+  created_at BIGINT NOT NULL,
+  updated_at BIGINT NOT NULL,
+  CONSTRAINT ban_draft_account_id FOREIGN KEY (account_id) REFERENCES character (id)
+);
+CREATE INDEX ban_draft_account_id ON ban_draft (account_id);
+
+CREATE TABLE ban_impact_job (
+  id BIGINT NOT NULL PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+  entity_type VARCHAR(16) NOT NULL CHECK (entity_type IN ('Corporation', 'Alliance')),
+  entity_id BIGINT NOT NULL,
+  status VARCHAR(16) NOT NULL CHECK (status IN ('queued', 'running', 'done', 'failed')),
+  progress BIGINT NOT NULL DEFAULT 0,
+  total BIGINT,
+  result TEXT,
+  error TEXT,
+  created_by BIGINT NOT NULL,
+  created_at BIGINT NOT NULL,
+  updated_at BIGINT NOT NULL,
+  CONSTRAINT created_by FOREIGN KEY (created_by) REFERENCES character (id)
+);
+
+CREATE TABLE ban_exception (
+  id BIGINT NOT NULL PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+  corporation_id BIGINT NOT NULL UNIQUE,
+  corporation_name VARCHAR(64),
+  reason VARCHAR(512) NOT NULL,
+  created_at BIGINT NOT NULL,
+  created_by BIGINT NOT NULL,
+  CONSTRAINT created_by FOREIGN KEY (created_by) REFERENCES character (id)
 );
 
 CREATE TABLE badge (
@@ -114,6 +217,16 @@ CREATE TABLE fitting (
   UNIQUE (dna)
 );
 
+CREATE TABLE idempotency_key (
+  account_id BIGINT NOT NULL,
+  endpoint VARCHAR(32) NOT NULL,
+  idempotency_key VARCHAR(255) NOT NULL,
+  response TEXT,
+  created_at BIGINT NOT NULL,
+  PRIMARY KEY (account_id, endpoint, idempotency_key),
+  CONSTRAINT idempotency_key_account_id FOREIGN KEY (account_id) REFERENCES character (id)
+);
+
 CREATE TABLE implant_set (
   id BIGINT NOT NULL PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
   implants VARCHAR(255) NOT NULL,
@@ -161,6 +274,14 @@ CREATE TABLE skill_history (
   CONSTRAINT skill_history_character_id FOREIGN KEY (character_id) REFERENCES character (id)
 );
 
+CREATE TABLE skill_gate_override (
+  character_id BIGINT PRIMARY KEY NOT NULL,
+  granted_by_id BIGINT NOT NULL,
+  granted_at BIGINT NOT NULL,
+  CONSTRAINT skill_gate_override_character_id FOREIGN KEY (character_id) REFERENCES character (id),
+  CONSTRAINT skill_gate_override_granted_by_id FOREIGN KEY (granted_by_id) REFERENCES character (id)
+);
+
 CREATE TABLE character_note (
   id BIGINT NOT NULL PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
   character_id BIGINT NOT NULL,
@@ -180,6 +301,7 @@ CREATE TABLE fleet (
   max_size BIGINT NOT NULL,
   visible BOOLEAN NOT NULL DEFAULT FALSE,
   error_count BIGINT NOT NULL DEFAULT 0,
+  registered_at BIGINT NOT NULL DEFAULT (EXTRACT(EPOCH FROM NOW())),
   CONSTRAINT fleet_boss_id FOREIGN KEY (boss_id) REFERENCES character (id)
 );
 
@@ -196,6 +318,8 @@ CREATE TABLE waitlist_entry (
   id BIGINT NOT NULL PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
   account_id BIGINT NOT NULL,
   joined_at BIGINT NOT NULL,
+  last_activity_at BIGINT NOT NULL,
+  notify_on_expiry BOOLEAN NOT NULL DEFAULT FALSE,
   UNIQUE (account_id),
   CONSTRAINT waitlist_entry_account_id FOREIGN KEY (account_id) REFERENCES character (id)
 );
@@ -220,6 +344,14 @@ CREATE TABLE waitlist_entry_fit (
   CONSTRAINT fit_state CHECK (state IN ('pending', 'approved', 'rejected'))
 );
 
+CREATE TABLE waitlist_metrics (
+  id BIGINT NOT NULL PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+  recorded_at BIGINT NOT NULL,
+  category VARCHAR(10) NOT NULL,
+  size BIGINT NOT NULL
+);
+CREATE INDEX waitlist_metrics_recorded_at ON waitlist_metrics (recorded_at);
+
 CREATE TABLE wiki_user (
   character_id BIGINT PRIMARY KEY NOT NULL,
   "user" VARCHAR(255) NOT NULL UNIQUE,
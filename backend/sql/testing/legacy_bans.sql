@@ -0,0 +1,11 @@
+-- Bans as they were stored before migration 0010, for sql/testing/postgres_0009.sql.
+-- They get the IDs 1, 2 and 3.
+INSERT INTO character (id, name) VALUES (1000, 'Legacy FC'), (1001, 'Legacy Pilot'), (1002, 'Other Pilot');
+
+INSERT INTO ban (entity_id, entity_name, entity_type, issued_at, issued_by, public_reason, reason, revoked_at, revoked_by) VALUES
+  -- Permanent
+  (1001, 'Legacy Pilot', 'Character', 1600000000, 1000, 'Public reason', 'Internal reason', NULL, NULL),
+  -- Expires far in the future
+  (1002, 'Other Pilot', 'Character', 1600000000, 1000, NULL, 'Internal reason', 4000000000, NULL),
+  -- Revoked
+  (1001, 'Legacy Pilot', 'Character', 1500000000, 1000, NULL, 'Old reason', 1550000000, 1000);
@@ -1,5 +1,8 @@
 use crate::config::Config;
-use std::sync::Arc;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
 
 pub struct Application {
     pub db: Arc<crate::DB>,
@@ -7,31 +10,59 @@ pub struct Application {
     pub affiliation_service: crate::core::affiliation::AffiliationService,
     pub ban_service: crate::core::ban::BanService,
     pub esi_client: crate::core::esi::ESIClient,
+    // Public lookups, a fake in tests
+    pub esi_lookup: Arc<dyn crate::core::esi::EsiLookup>,
+    pub rate_limiter: crate::core::ratelimit::RateLimiter,
     pub sse_client: crate::core::sse::SSEClient,
+    pub webhook_client: crate::core::webhook::WebhookClient,
+    pub role_removal_client: crate::core::discord_roles::RoleRemovalClient,
     pub token_secret: Vec<u8>,
+    pub clock: Arc<dyn crate::util::clock::Clock>,
+    // See bans_changed
+    pub bans_generation: AtomicU64,
 }
 
 pub fn new(db: Arc<crate::DB>, config: Config) -> Application {
+    let clock: Arc<dyn crate::util::clock::Clock> = Arc::new(crate::util::clock::SystemClock);
+    let esi_lookup: Arc<dyn crate::core::esi::EsiLookup> =
+        Arc::new(crate::core::esi::ESIClient::new(
+            db.clone(),
+            config.esi.client_id.clone(),
+            config.esi.client_secret.clone(),
+        ));
+
     Application {
         affiliation_service: crate::core::affiliation::AffiliationService::new(
             db.clone(),
-            crate::core::esi::ESIClient::new(
-                db.clone(),
-                config.esi.client_id.clone(),
-                config.esi.client_secret.clone(),
-            ),
+            esi_lookup.clone(),
         ),
-        ban_service: crate::core::ban::BanService::new(db.clone()),
+        ban_service: crate::core::ban::BanService::new(db.clone(), clock.clone())
+            .snap_to_downtime(config.bans.snap_expiry_to_downtime),
         esi_client: crate::core::esi::ESIClient::new(
             db.clone(),
             config.esi.client_id.clone(),
             config.esi.client_secret.clone(),
         ),
+        rate_limiter: crate::core::ratelimit::RateLimiter::new(),
         sse_client: crate::core::sse::SSEClient::new(
             config.sse.url.clone(),
             &hex::decode(&config.sse.secret).unwrap(),
         ),
+        webhook_client: crate::core::webhook::WebhookClient::new(
+            config.discord.ban_webhook.clone(),
+            config.discord.ban_template.clone(),
+        )
+        .expect("Invalid discord.ban_template"),
+        role_removal_client: crate::core::discord_roles::RoleRemovalClient::new(
+            config.discord.role_removal_url.clone(),
+            config.discord.role_removal_secret.clone(),
+        )
+        .expect("Invalid discord.role_removal_url"),
         token_secret: hex::decode(&config.app.token_secret).unwrap(),
+        // Starts somewhere new on every restart, so ETags from before it don't match by chance
+        bans_generation: AtomicU64::new(clock.now().timestamp_millis() as u64),
+        esi_lookup,
+        clock,
         db,
         config,
     }
@@ -41,4 +72,18 @@ impl Application {
     pub fn get_db(&self) -> &crate::DB {
         &self.db
     }
+
+    // Every handler that changes bans calls this, it invalidates the ETags of ban lists
+    pub fn bans_changed(&self) {
+        self.bans_generation.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn bans_generation(&self) -> u64 {
+        self.bans_generation.load(Ordering::SeqCst)
+    }
+
+    // See [features] in the config, unknown features are off
+    pub fn feature_enabled(&self, name: &str) -> bool {
+        self.config.feature_enabled(name)
+    }
 }
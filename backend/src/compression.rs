@@ -0,0 +1,138 @@
+use std::io::{Cursor, Write};
+
+use flate2::{
+    write::{DeflateEncoder, GzEncoder},
+    Compression as Level,
+};
+use rocket::fairing::{Fairing, Kind};
+use rocket::http::Header;
+
+// Compresses API responses for clients that accept it, mostly for FCs on slow connections
+pub struct Compression {
+    // Smaller bodies aren't worth the overhead
+    pub min_size: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Encoding {
+    Gzip,
+    Deflate,
+}
+
+impl Encoding {
+    fn name(self) -> &'static str {
+        match self {
+            Encoding::Gzip => "gzip",
+            Encoding::Deflate => "deflate",
+        }
+    }
+
+    fn encode(self, body: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            Encoding::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Level::default());
+                encoder.write_all(body)?;
+                encoder.finish()
+            }
+            Encoding::Deflate => {
+                let mut encoder = DeflateEncoder::new(Vec::new(), Level::default());
+                encoder.write_all(body)?;
+                encoder.finish()
+            }
+        }
+    }
+}
+
+// Gzip is preferred when both are accepted, q=0 rules an encoding out
+fn preferred_encoding(accept_encoding: &str) -> Option<Encoding> {
+    let accepted: Vec<String> = accept_encoding
+        .split(',')
+        .filter_map(|part| {
+            let mut params = part.split(';');
+            let name = params.next()?.trim().to_ascii_lowercase();
+            let refused = params.any(|param| {
+                let param = param.trim();
+                param.starts_with("q=") && param[2..].parse::<f32>().map_or(false, |q| q == 0.0)
+            });
+            match refused {
+                true => None,
+                false => Some(name),
+            }
+        })
+        .collect();
+
+    [Encoding::Gzip, Encoding::Deflate]
+        .iter()
+        .copied()
+        .find(|encoding| accepted.iter().any(|name| name == encoding.name()))
+}
+
+#[rocket::async_trait]
+impl Fairing for Compression {
+    fn info(&self) -> rocket::fairing::Info {
+        rocket::fairing::Info {
+            name: "Compression",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, req: &'r rocket::Request<'_>, res: &mut rocket::Response<'r>) {
+        if !req.uri().path().starts_with("/api/v2/") || res.headers().contains("Content-Encoding") {
+            return;
+        }
+        let encoding = match req
+            .headers()
+            .get_one("Accept-Encoding")
+            .and_then(preferred_encoding)
+        {
+            Some(encoding) => encoding,
+            None => return,
+        };
+        // Streamed bodies have no known size, those are left alone
+        match res.body().preset_size() {
+            Some(size) if size >= self.min_size => (),
+            _ => return,
+        }
+
+        let body = match res.body_mut().to_bytes().await {
+            Ok(body) => body,
+            Err(e) => {
+                error!("Unable to read the response body for compression: {:#?}", e);
+                return;
+            }
+        };
+        let compressed = match encoding.encode(&body) {
+            Ok(compressed) => compressed,
+            Err(e) => {
+                error!("Unable to compress the response: {:#?}", e);
+                res.set_sized_body(body.len(), Cursor::new(body));
+                return;
+            }
+        };
+
+        res.set_sized_body(compressed.len(), Cursor::new(compressed));
+        res.set_header(Header::new("Content-Encoding", encoding.name()));
+        res.set_header(Header::new("Vary", "Accept-Encoding"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{preferred_encoding, Encoding};
+
+    #[test]
+    fn test_preferred_encoding() {
+        assert_eq!(
+            preferred_encoding("gzip, deflate, br"),
+            Some(Encoding::Gzip)
+        );
+        assert_eq!(preferred_encoding("deflate"), Some(Encoding::Deflate));
+        assert_eq!(preferred_encoding("GZIP;q=0.5"), Some(Encoding::Gzip));
+        assert_eq!(
+            preferred_encoding("gzip;q=0, deflate"),
+            Some(Encoding::Deflate)
+        );
+        assert_eq!(preferred_encoding("br, identity"), None);
+        assert_eq!(preferred_encoding(""), None);
+    }
+}
@@ -1,5 +1,17 @@
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+
 use serde::Deserialize;
 
+// Features that can be switched per instance under [features], and whether they're on when
+// the config doesn't mention them
+pub const FEATURES: &[(&str, bool)] = &[
+    // Ban announcements, see discord.ban_webhook
+    ("discord_webhook", true),
+    // See discord.role_removal_url
+    ("discord_role_removal", true),
+];
+
 #[derive(Deserialize, Clone)]
 pub struct DatabaseConfig {
     pub url: String,
@@ -19,6 +31,16 @@ pub struct ESIConfig {
 #[derive(Deserialize, Clone)]
 pub struct AppConfig {
     pub token_secret: String,
+    // Requests that take longer are logged as warnings, see request_logger
+    #[serde(default = "default_slow_request_ms")]
+    pub slow_request_ms: u64,
+    // Proxies whose X-Real-IP header is taken as the client's address, see util::client_ip
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpAddr>,
+}
+
+fn default_slow_request_ms() -> u64 {
+    1000
 }
 
 #[derive(Deserialize, Clone)]
@@ -39,11 +61,156 @@ pub struct SkillUpdaterConfig {
     pub runtime: f64,
 }
 
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct WaitlistMetricsConfig {
+    pub enable: bool,
+    pub interval: u64,
+    pub retention_days: i64,
+}
+
+impl Default for WaitlistMetricsConfig {
+    fn default() -> Self {
+        WaitlistMetricsConfig {
+            enable: false,
+            interval: 300,
+            retention_days: 90,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct WaitlistExpiryConfig {
+    pub enable: bool,
+    pub max_idle: i64,
+}
+
+impl Default for WaitlistExpiryConfig {
+    fn default() -> Self {
+        WaitlistExpiryConfig {
+            enable: false,
+            max_idle: 60 * 60 * 3,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct EsiWarmupConfig {
+    pub enable: bool,
+    // Corporations and alliances to always look up
+    pub ids: Vec<i64>,
+    // Plus this many of the most banned ones
+    pub top_referenced: i64,
+}
+
+impl Default for EsiWarmupConfig {
+    fn default() -> Self {
+        EsiWarmupConfig {
+            enable: false,
+            ids: Vec::new(),
+            top_referenced: 200,
+        }
+    }
+}
+
+// Tags new bans that match every condition that's set, see core::ban::auto_tags
+#[derive(Deserialize, Clone)]
+pub struct AutoTagRule {
+    pub tag: String,
+    // Any of these categories, compared case insensitively
+    #[serde(default)]
+    pub categories: Vec<String>,
+    // Any of these access levels for the FC issuing the ban
+    #[serde(default)]
+    pub issuer_roles: Vec<String>,
+    // Permanent, or temporary
+    #[serde(default)]
+    pub permanent: Option<bool>,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct BansConfig {
+    // Access keys needed to read ban reasons, an empty key lets any logged in account read them
+    pub reason_access: String,
+    pub public_reason_access: String,
+    // Permanent bans only take effect once a second FC approves them
+    pub require_approval: bool,
+    // Create bans with a placeholder name while ESI is down, see core::ban_names
+    pub allow_pending_names: bool,
+    // Minutes after a revoke before the entity can be banned again without bans-admin
+    pub enforce_reban_cooldown: bool,
+    pub reban_cooldown: i64,
+    // Refuse bans without a reason, whitespace doesn't count
+    pub require_reason: bool,
+    pub require_public_reason: bool,
+    // Send an SSE event on the bans topic once a ban is within this many hours of expiring,
+    // see core::ban_expiry
+    pub notify_expiring: bool,
+    pub expiring_window: i64,
+    // Unauthenticated GET /api/v2/public/bans/check, limited to this many lookups per IP per hour
+    pub public_check: bool,
+    pub public_check_limit: usize,
+    // Revoke corporation bans once ESI says the corporation closed, see core::ban_dissolution
+    pub revoke_dissolved_corporations: bool,
+    // End temporary bans at the first downtime at or after the requested expiry, instead of
+    // 11 hours after it
+    pub snap_expiry_to_downtime: bool,
+    // Refuse ban changes from accounts that last logged in more than this many hours ago
+    pub require_recent_login: bool,
+    pub max_login_age: i64,
+    // Hours after a ban is issued that only its FC or bans-admin can edit, extend or revoke it
+    pub enforce_ownership: bool,
+    pub ownership_window: i64,
+    // Hours after expiring that a ban is flagged as recently_expired in a character's history
+    pub recently_expired_grace: i64,
+    // Tags added to new bans on top of the ones the FC picked
+    pub auto_tags: Vec<AutoTagRule>,
+}
+
+impl Default for BansConfig {
+    fn default() -> Self {
+        BansConfig {
+            reason_access: "bans-manage".to_string(),
+            public_reason_access: "bans-manage".to_string(),
+            require_approval: false,
+            allow_pending_names: false,
+            enforce_reban_cooldown: false,
+            reban_cooldown: 30,
+            require_reason: false,
+            require_public_reason: false,
+            notify_expiring: false,
+            expiring_window: 24,
+            public_check: false,
+            public_check_limit: 10,
+            revoke_dissolved_corporations: false,
+            snap_expiry_to_downtime: false,
+            require_recent_login: false,
+            max_login_age: 24,
+            enforce_ownership: false,
+            ownership_window: 24,
+            recently_expired_grace: 72,
+            auto_tags: Vec::new(),
+        }
+    }
+}
+
 #[derive(Deserialize, Clone)]
 pub struct DokuWikiConfig {
     pub mail_domain: String,
 }
 
+#[derive(Deserialize, Clone, Default)]
+pub struct DiscordConfig {
+    pub ban_webhook: Option<String>,
+    pub ban_template: Option<String>,
+    // Optional Discord bot endpoint that strips the roles of banned pilots, see core::discord_roles
+    pub role_removal_url: Option<String>,
+    pub role_removal_secret: Option<String>,
+}
+
 #[derive(Deserialize, Clone)]
 pub struct Config {
     pub database: DatabaseConfig,
@@ -52,5 +219,206 @@ pub struct Config {
     pub sse: SSEConfig,
     pub fleet_updater: FleetUpdaterConfig,
     pub skill_updater: SkillUpdaterConfig,
+    #[serde(default)]
+    pub waitlist_metrics: WaitlistMetricsConfig,
+    #[serde(default)]
+    pub waitlist_expiry: WaitlistExpiryConfig,
+    #[serde(default)]
+    pub esi_warmup: EsiWarmupConfig,
     pub dokuwiki: DokuWikiConfig,
+    #[serde(default)]
+    pub discord: DiscordConfig,
+    #[serde(default)]
+    pub bans: BansConfig,
+    #[serde(default)]
+    pub features: BTreeMap<String, bool>,
+}
+
+impl Config {
+    // See [features], unknown features are off
+    pub fn feature_enabled(&self, name: &str) -> bool {
+        match self.features.get(name) {
+            Some(enabled) => *enabled,
+            None => FEATURES
+                .iter()
+                .find(|(feature, _)| *feature == name)
+                .map_or(false, |(_, default)| *default),
+        }
+    }
+
+    // Catches mistakes at startup instead of when the setting is first used
+    pub fn validate(&self) -> Result<(), String> {
+        let mut errors = Vec::new();
+
+        if self.database.url.is_empty() {
+            errors.push("database.url cannot be empty".to_string());
+        }
+        if self.database.max_connections == 0 {
+            errors.push("database.max_connections must be at least 1".to_string());
+        }
+        if self.database.min_connections > self.database.max_connections {
+            errors.push(
+                "database.min_connections cannot be larger than database.max_connections"
+                    .to_string(),
+            );
+        }
+
+        // Branca tokens need a 32 byte key
+        match hex::decode(&self.app.token_secret) {
+            Ok(secret) if secret.len() == 32 => (),
+            _ => errors.push("app.token_secret must be 64 hex characters".to_string()),
+        }
+        if hex::decode(&self.sse.secret).map_or(true, |secret| secret.is_empty()) {
+            errors.push("sse.secret must be hex encoded".to_string());
+        }
+
+        if self.esi.client_id.is_empty() || self.esi.client_secret.is_empty() {
+            errors.push("esi.client_id and esi.client_secret cannot be empty".to_string());
+        }
+
+        if self.skill_updater.runtime <= 0.0 {
+            errors.push("skill_updater.runtime must be positive".to_string());
+        }
+        if self.waitlist_metrics.interval == 0 || self.waitlist_metrics.retention_days <= 0 {
+            errors.push(
+                "waitlist_metrics.interval and waitlist_metrics.retention_days must be positive"
+                    .to_string(),
+            );
+        }
+        if self.waitlist_expiry.max_idle <= 0 {
+            errors.push("waitlist_expiry.max_idle must be positive".to_string());
+        }
+        if self.esi_warmup.top_referenced < 0 {
+            errors.push("esi_warmup.top_referenced cannot be negative".to_string());
+        }
+
+        for name in self.features.keys() {
+            if !FEATURES.iter().any(|(feature, _)| feature == name) {
+                errors.push(format!("features.{} is not a known feature", name));
+            }
+        }
+
+        if let Some(url) = &self.discord.ban_webhook {
+            if !url.starts_with("https://") {
+                errors.push("discord.ban_webhook must be an https URL".to_string());
+            }
+        }
+        if let Some(url) = &self.discord.role_removal_url {
+            if !url.starts_with("https://") {
+                errors.push("discord.role_removal_url must be an https URL".to_string());
+            }
+            if hex::decode(
+                self.discord
+                    .role_removal_secret
+                    .as_deref()
+                    .unwrap_or_default(),
+            )
+            .map_or(true, |secret| secret.is_empty())
+            {
+                errors.push("discord.role_removal_secret must be set and hex encoded".to_string());
+            }
+        }
+
+        if self.bans.reban_cooldown < 0 {
+            errors.push("bans.reban_cooldown cannot be negative".to_string());
+        }
+        if self.bans.expiring_window <= 0 {
+            errors.push("bans.expiring_window must be positive".to_string());
+        }
+        if self.bans.public_check && self.bans.public_check_limit == 0 {
+            errors.push("bans.public_check_limit must be positive".to_string());
+        }
+        if self.bans.recently_expired_grace < 0 {
+            errors.push("bans.recently_expired_grace cannot be negative".to_string());
+        }
+        if self.bans.require_recent_login && self.bans.max_login_age <= 0 {
+            errors.push("bans.max_login_age must be positive".to_string());
+        }
+        if self.bans.enforce_ownership && self.bans.ownership_window <= 0 {
+            errors.push("bans.ownership_window must be positive".to_string());
+        }
+
+        for (i, rule) in self.bans.auto_tags.iter().enumerate() {
+            let name = format!("bans.auto_tags[{}]", i);
+            match crate::core::ban::normalize_tags(&[rule.tag.clone()]) {
+                Ok(tags) if tags.is_empty() => errors.push(format!("{}.tag cannot be empty", name)),
+                Ok(_) => (),
+                Err(e) => errors.push(format!("{}.tag: {}", name, e)),
+            }
+            if rule.categories.is_empty()
+                && rule.issuer_roles.is_empty()
+                && rule.permanent.is_none()
+            {
+                errors.push(format!("{} needs at least one condition", name));
+            }
+            for role in &rule.issuer_roles {
+                if !crate::core::auth::all_access_levels().contains_key(role) {
+                    errors.push(format!(
+                        "{}.issuer_roles: {} is not a known role",
+                        name, role
+                    ));
+                }
+            }
+        }
+
+        // A typo here would silently hide reasons from everyone
+        for (name, key) in [
+            ("bans.reason_access", &self.bans.reason_access),
+            ("bans.public_reason_access", &self.bans.public_reason_access),
+        ]
+        .iter()
+        {
+            let known = crate::core::auth::all_access_levels()
+                .values()
+                .any(|keys| keys.contains(key.as_str()));
+            if !key.is_empty() && !known {
+                errors.push(format!("{} is not a known access key: {}", name, key));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AutoTagRule, Config};
+
+    fn example() -> Config {
+        toml::from_str(include_str!("../config.example.toml")).unwrap()
+    }
+
+    #[test]
+    fn test_example_config_is_valid() {
+        assert_eq!(example().validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut config = example();
+        config.database.min_connections = 10;
+        config.app.token_secret = "abcd".to_string();
+        config.bans.reason_access = "bans-mange".to_string();
+        config.features.insert("discord_webhok".to_string(), false);
+        config.bans.enforce_ownership = true;
+        config.bans.ownership_window = 0;
+        config.bans.auto_tags = vec![AutoTagRule {
+            tag: "leadership".to_string(),
+            categories: Vec::new(),
+            issuer_roles: vec!["Leadershp".to_string()],
+            permanent: None,
+        }];
+
+        let errors = config.validate().unwrap_err();
+        assert!(errors.contains("database.min_connections"));
+        assert!(errors.contains("app.token_secret"));
+        assert!(errors.contains("bans.reason_access"));
+        assert!(errors.contains("features.discord_webhok"));
+        assert!(errors.contains("bans.ownership_window"));
+        assert!(errors.contains("bans.auto_tags[0].issuer_roles"));
+    }
 }
@@ -13,6 +13,7 @@ struct CharacterResponse {
 struct CorporationResponse {
     name: String,
     alliance_id: Option<i64>,
+    member_count: i64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -20,15 +21,22 @@ struct AllianceResponse {
     name: String,
 }
 
+#[derive(Debug)]
+pub struct KnownMember {
+    pub id: i64,
+    pub name: String,
+    pub last_seen: i64,
+}
+
 pub struct AffiliationService {
     db: Arc<crate::DB>,
-    esi_client: crate::core::esi::ESIClient,
+    esi_client: Arc<dyn crate::core::esi::EsiLookup>,
 }
 
 impl AffiliationService {
     pub fn new(
         database: Arc<crate::DB>,
-        esi_client: crate::core::esi::ESIClient,
+        esi_client: Arc<dyn crate::core::esi::EsiLookup>,
     ) -> AffiliationService {
         AffiliationService {
             db: database,
@@ -82,7 +90,7 @@ impl AffiliationService {
             known = true;
 
             // If the corp was updated in the last 24h, we don't need to fetch it again
-            if corp.updated_at + 60 * 60 * 24 > now {
+            if corp.updated_at + 60 * 60 * 24 > now && corp.member_count.is_some() {
                 return Ok(());
             }
         }
@@ -97,20 +105,22 @@ impl AffiliationService {
 
         if !known {
             sqlx::query!(
-                "INSERT INTO corporation (id, name, alliance_id, updated_at) VALUES ($1, $2, $3, $4)",
+                "INSERT INTO corporation (id, name, alliance_id, updated_at, member_count) VALUES ($1, $2, $3, $4, $5)",
                 id,
                 esi_res.name,
                 esi_res.alliance_id,
-                now
+                now,
+                esi_res.member_count
             )
             .execute(self.db.as_ref())
             .await?;
         } else {
             sqlx::query!(
-                "UPDATE corporation SET name=$1, alliance_id=$2, updated_at=$3 WHERE id=$4",
+                "UPDATE corporation SET name=$1, alliance_id=$2, updated_at=$3, member_count=$4 WHERE id=$5",
                 esi_res.name,
                 esi_res.alliance_id,
                 now,
+                esi_res.member_count,
                 id
             )
             .execute(self.db.as_ref())
@@ -120,6 +130,74 @@ impl AffiliationService {
         Ok(())
     }
 
+    // Known characters in a corporation or alliance, going by the cached affiliations.
+    // Most recently seen first.
+    pub async fn known_members(
+        &self,
+        entity_type: &str,
+        entity_id: i64,
+    ) -> Result<Vec<KnownMember>, Madness> {
+        let rows = sqlx::query!(
+            "SELECT
+                character.id,
+                character.name,
+                character.last_seen
+            FROM
+                character
+            JOIN
+                corporation ON character.corporation_id=corporation.id
+            WHERE
+                ($1 = 'Corporation' AND corporation.id=$2)
+                OR ($1 = 'Alliance' AND corporation.alliance_id=$2)
+            ORDER BY
+                character.last_seen DESC",
+            entity_type,
+            entity_id
+        )
+        .fetch_all(self.db.as_ref())
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| KnownMember {
+                id: row.id,
+                name: row.name,
+                last_seen: row.last_seen,
+            })
+            .collect())
+    }
+
+    // The member count ESI reported for a corporation, refreshed at most once a day
+    pub async fn cached_member_count(&self, id: i64) -> Result<Option<i64>, Madness> {
+        self.update_corp_affiliation(id).await?;
+
+        Ok(
+            sqlx::query!("SELECT member_count FROM corporation WHERE id=$1", id)
+                .fetch_one(self.db.as_ref())
+                .await?
+                .member_count,
+        )
+    }
+
+    // The member count of a corporation, if it can be loaded
+    pub async fn corporation_member_count(&self, id: i64) -> Option<i64> {
+        match self.cached_member_count(id).await {
+            Ok(count) => count,
+            Err(e) => {
+                warn!("Unable to load the member count of {}: {:#?}", id, e);
+                None
+            }
+        }
+    }
+
+    // IDs of the corporations currently in an alliance
+    pub async fn alliance_corporations(&self, id: i64) -> Result<Vec<i64>, Madness> {
+        Ok(self
+            .esi_client
+            .get_unauthenticated(&format!("/latest/alliances/{}/corporations", id))
+            .await?)
+    }
+
     pub async fn update_alliance(&self, id: i64) -> Result<(), Madness> {
         let esi_res: AllianceResponse = self
             .esi_client
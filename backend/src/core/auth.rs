@@ -16,6 +16,8 @@ lazy_static::lazy_static! {
 pub struct AuthenticatedAccount {
     pub id: i64,
     pub access: &'static std::collections::BTreeSet<String>,
+    // When the account last went through SSO, 0 for cookies issued before this was tracked
+    pub logged_in_at: i64,
 }
 
 #[derive(Debug)]
@@ -32,9 +34,12 @@ pub enum AuthorizationError {
 }
 
 #[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 struct AuthToken {
     version: i32,
     account_id: i64,
+    #[serde(default)]
+    logged_in_at: i64,
 }
 
 pub struct CookieSetter(pub String, pub bool);
@@ -74,11 +79,20 @@ fn decode_token(token: &str, secret: &[u8]) -> Result<AuthToken, AuthenticationE
 }
 
 pub fn create_cookie(app: &crate::app::Application, account_id: i64) -> CookieSetter {
+    create_cookie_at(app, account_id, app.clock.now().timestamp())
+}
+
+pub fn create_cookie_at(
+    app: &crate::app::Application,
+    account_id: i64,
+    logged_in_at: i64,
+) -> CookieSetter {
     let mut branca = Branca::new(&app.token_secret).unwrap();
 
     let token = AuthToken {
         version: 1,
         account_id,
+        logged_in_at,
     };
 
     let payload = rmp_serde::to_vec_named(&token).unwrap();
@@ -131,6 +145,7 @@ impl<'r> FromRequest<'r> for AuthenticatedAccount {
         Outcome::Success(AuthenticatedAccount {
             id: token.account_id,
             access: access_keys,
+            logged_in_at: token.logged_in_at,
         })
     }
 }
@@ -235,7 +250,10 @@ fn build_access_levels() -> BTreeMap<String, BTreeSet<String>> {
         vec![
             "commanders-manage:Wiki Team",
             "commanders-manage:Instructor",
-            "commanders-manage:Leadership"
+            "commanders-manage:Leadership",
+            "bans-manage-on-behalf",
+            "bans-admin",
+            "access-levels-view",
         ],
     );
 
@@ -246,6 +264,11 @@ pub fn get_access_keys(level: &str) -> Option<&'static BTreeSet<String>> {
     ACCESS_LEVELS.get(level)
 }
 
+// Every role and the access keys it grants, including the ones inherited from lower roles
+pub fn all_access_levels() -> &'static BTreeMap<String, BTreeSet<String>> {
+    &ACCESS_LEVELS
+}
+
 pub async fn authorize_character(
     db: &crate::DB,
     account: &AuthenticatedAccount,
@@ -274,3 +297,33 @@ pub async fn authorize_character(
         Ok(Some(_)) => Ok(()),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use branca::Branca;
+
+    use super::{decode_token, AuthToken};
+    use crate::core::ban_purge::PurgeToken;
+
+    const SECRET: [u8; 32] = [7; 32];
+
+    #[test]
+    fn test_decode_token() {
+        let encode = |payload: Vec<u8>| Branca::new(&SECRET).unwrap().encode(&payload).unwrap();
+
+        let token = encode(
+            rmp_serde::to_vec_named(&AuthToken {
+                version: 1,
+                account_id: 1001,
+                logged_in_at: 0,
+            })
+            .unwrap(),
+        );
+        assert_eq!(decode_token(&token, &SECRET).unwrap().account_id, 1001);
+
+        // Other tokens sealed with the same secret don't pass as a login
+        let token =
+            encode(rmp_serde::to_vec_named(&PurgeToken::new(1001, 30, 10_000_000)).unwrap());
+        assert!(decode_token(&token, &SECRET).is_err());
+    }
+}
@@ -1,31 +1,437 @@
+use std::collections::{BTreeSet, HashMap};
 use std::sync::Arc;
 
+use serde::Serialize;
+
+use crate::config::AutoTagRule;
+use crate::core::{esi::CharacterAffiliation, name_pattern};
 use crate::util::{
+    clock::Clock,
+    list_params,
     madness::Madness,
-    types::{Ban, Character, Entity},
+    types::{Ban, BulkResult, Character, Entity, ReasonEdit},
 };
 
+// Name pattern bans match characters by name, their entity_name holds a glob pattern.
+// They aren't tied to an entity, so they're all stored with the same placeholder ID.
+pub const NAME_PATTERN: &str = "NamePattern";
+pub const NAME_PATTERN_ENTITY_ID: i64 = 0;
+
+// Tagged onto corporation bans revoked because the corporation closed, see core::ban_dissolution
+pub const DISSOLVED_TAG: &str = "corporation-dissolved";
+
+// Temporary bans end at downtime on the day they were set to expire
+const DOWNTIME_OFFSET: i64 = 60 * 60 * 11;
+
+pub fn compute_expiry(day: i64) -> i64 {
+    day + DOWNTIME_OFFSET
+}
+
+const DAY: i64 = 60 * 60 * 24;
+
+// For callers that change the ban in the same transaction, everyone else uses set_tags
+pub async fn replace_tags(
+    tx: &mut crate::DBTX<'_>,
+    ban_id: i64,
+    tags: &[String],
+) -> Result<(), Madness> {
+    sqlx::query!("DELETE FROM ban_tag WHERE ban_id=$1", ban_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!(
+        "INSERT INTO ban_tag (ban_id, tag) SELECT $1, UNNEST($2::VARCHAR[])",
+        ban_id,
+        tags
+    )
+    .execute(&mut *tx)
+    .await?;
+    Ok(())
+}
+
+// The first downtime at or after the timestamp, for when the requested expiry isn't the start
+// of a day
+pub fn next_downtime(timestamp: i64) -> i64 {
+    let downtime = timestamp - timestamp.rem_euclid(DAY) + DOWNTIME_OFFSET;
+    match downtime >= timestamp {
+        true => downtime,
+        false => downtime + DAY,
+    }
+}
+
+// Longest category we keep, see the ban table
+const CATEGORY_MAX_LENGTH: usize = 32;
+
+// Reads a leading tag like "[RMT] Selling ISK" out of a reason
+pub fn parse_reason_tag(reason: &str) -> Option<String> {
+    let rest = reason.trim_start().strip_prefix('[')?;
+    let tag = rest[..rest.find(']')?].trim();
+
+    if tag.is_empty() || tag.chars().count() > CATEGORY_MAX_LENGTH {
+        return None;
+    }
+    Some(tag.to_string())
+}
+
+const TAG_MAX_LENGTH: usize = 32;
+const TAG_MAX_COUNT: usize = 10;
+
+// The tags of the rules a new ban matches, see bans.auto_tags. issuer_role is the access level
+// of the FC the ban is from, if they have one.
+pub fn auto_tags(rules: &[AutoTagRule], ban: &Ban, issuer_role: Option<&str>) -> Vec<String> {
+    rules
+        .iter()
+        .filter(|rule| {
+            rule.categories.is_empty()
+                || ban.category.as_deref().map_or(false, |category| {
+                    rule.categories
+                        .iter()
+                        .any(|wanted| wanted.eq_ignore_ascii_case(category))
+                })
+        })
+        .filter(|rule| {
+            rule.issuer_roles.is_empty()
+                || issuer_role.map_or(false, |role| rule.issuer_roles.iter().any(|r| r == role))
+        })
+        .filter(|rule| {
+            rule.permanent
+                .map_or(true, |permanent| permanent == ban.revoked_at.is_none())
+        })
+        .map(|rule| rule.tag.clone())
+        .collect()
+}
+
+// Narrows down all_active, active_page and count_active, the default is every active ban
+#[derive(Debug, Default)]
+pub struct ActiveFilter<'a> {
+    // Bans with all of these tags
+    pub tags: &'a [String],
+    // Temporary bans that end within this many seconds
+    pub expiring_within: Option<i64>,
+    // Bans of this FC, whether they issued them or an integration did for them
+    pub issued_by: Option<i64>,
+    // Bans whose entity name contains this, case insensitively
+    pub search: Option<&'a str>,
+    // Scheduled bans that haven't started yet as well
+    pub include_scheduled: bool,
+}
+
+impl ActiveFilter<'_> {
+    fn search_pattern(&self) -> Option<String> {
+        self.search
+            .map(|search| format!("%{}%", list_params::escape_like(search)))
+    }
+}
+
+// The IDs of a bulk request without repeats, in the order they were sent
+pub fn unique_ids(ids: &[i64]) -> Vec<i64> {
+    let mut seen = BTreeSet::new();
+    ids.iter().copied().filter(|id| seen.insert(*id)).collect()
+}
+
+// Lowercases and dedupes ban tags, so "AWOX" and "awox" filter the same
+pub fn normalize_tags(tags: &[String]) -> Result<Vec<String>, String> {
+    let tags: BTreeSet<String> = tags
+        .iter()
+        .map(|tag| tag.trim().to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .collect();
+
+    if tags.len() > TAG_MAX_COUNT {
+        return Err(format!(
+            "A ban cannot have more than {} tags",
+            TAG_MAX_COUNT
+        ));
+    }
+    if let Some(tag) = tags.iter().find(|tag| tag.chars().count() > TAG_MAX_LENGTH) {
+        return Err(format!(
+            "Tag \"{}\" is longer than {} characters",
+            tag, TAG_MAX_LENGTH
+        ));
+    }
+    Ok(tags.into_iter().collect())
+}
+
+// A character from the columns of a LEFT JOIN
+fn joined_character(id: Option<i64>, name: Option<String>) -> Option<Character> {
+    match (id, name) {
+        (Some(id), Some(name)) => Some(Character {
+            id,
+            name,
+            corporation_id: None,
+        }),
+        _ => None,
+    }
+}
+
+// The columns and joins of every query that loads whole bans, the argument adds the WHERE and
+// ORDER BY. The rows are read into a BanRow.
+macro_rules! select_bans {
+    ( $rest:expr ) => {
+        concat!(
+            "SELECT
+                ban.id,
+                entity_id,
+                entity_name,
+                entity_type,
+                issued_at,
+                public_reason,
+                reason,
+                category,
+                revoked_at,
+                silent,
+                pending_approval,
+                name_pending,
+                esi_category,
+                acknowledged_at,
+                supersedes_ban_id,
+                fc_note,
+                starts_at,
+                issuer.id AS issued_by_id,
+                issuer.name AS issued_by_name,
+                principal.id AS on_behalf_of_id,
+                principal.name AS on_behalf_of_name,
+                revoker.id AS revoked_by_id,
+                revoker.name AS revoked_by_name,
+                overrider.id AS duplicate_override_by_id,
+                overrider.name AS duplicate_override_by_name
+            FROM
+                ban
+            JOIN
+                character as issuer ON issued_by=issuer.id
+            LEFT JOIN
+                character as principal ON on_behalf_of=principal.id
+            LEFT JOIN
+                character as revoker ON revoked_by=revoker.id
+            LEFT JOIN
+                character as overrider ON duplicate_override_by=overrider.id
+            ",
+            $rest
+        )
+    };
+}
+
+#[derive(sqlx::FromRow)]
+struct BanRow {
+    id: i64,
+    entity_id: i64,
+    entity_name: Option<String>,
+    entity_type: String,
+    issued_at: i64,
+    public_reason: Option<String>,
+    reason: String,
+    category: Option<String>,
+    revoked_at: Option<i64>,
+    silent: bool,
+    pending_approval: bool,
+    name_pending: bool,
+    esi_category: Option<String>,
+    acknowledged_at: Option<i64>,
+    supersedes_ban_id: Option<i64>,
+    fc_note: Option<String>,
+    starts_at: Option<i64>,
+    issued_by_id: i64,
+    issued_by_name: String,
+    on_behalf_of_id: Option<i64>,
+    on_behalf_of_name: Option<String>,
+    revoked_by_id: Option<i64>,
+    revoked_by_name: Option<String>,
+    duplicate_override_by_id: Option<i64>,
+    duplicate_override_by_name: Option<String>,
+}
+
+// Reason history and tags are left empty, they take queries of their own
+impl From<BanRow> for Ban {
+    fn from(ban: BanRow) -> Ban {
+        Ban {
+            id: Some(ban.id),
+            entity: Some(Entity {
+                id: ban.entity_id,
+                name: ban.entity_name,
+                category: ban.entity_type,
+            }),
+            issued_at: Some(ban.issued_at),
+            issued_by: Some(Character {
+                id: ban.issued_by_id,
+                name: ban.issued_by_name,
+                corporation_id: None,
+            }),
+            on_behalf_of: joined_character(ban.on_behalf_of_id, ban.on_behalf_of_name),
+            reason: ban.reason,
+            public_reason: ban.public_reason,
+            category: ban.category,
+            revoked_at: ban.revoked_at,
+            revoked_by: joined_character(ban.revoked_by_id, ban.revoked_by_name),
+            silent: ban.silent,
+            pending_approval: ban.pending_approval,
+            name_pending: ban.name_pending,
+            esi_category: ban.esi_category,
+            acknowledged_at: ban.acknowledged_at,
+            supersedes_ban_id: ban.supersedes_ban_id,
+            fc_note: ban.fc_note,
+            starts_at: ban.starts_at,
+            duplicate_override_by: joined_character(
+                ban.duplicate_override_by_id,
+                ban.duplicate_override_by_name,
+            ),
+            reason_history: Vec::new(),
+            tags: Vec::new(),
+        }
+    }
+}
+
+// A ban column that points at a character that isn't there. Foreign keys should prevent these,
+// but data that was copied in with them disabled can still have them.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct OrphanedReference {
+    pub ban_id: i64,
+    pub column: String,
+    pub character_id: i64,
+}
+
+// A past ban with a reason like the one being written, for matching precedent
+#[derive(Debug, Serialize)]
+pub struct SimilarBan {
+    pub id: i64,
+    pub entity: Entity,
+    pub issued_at: i64,
+    pub reason: String,
+    pub public_reason: Option<String>,
+    pub category: Option<String>,
+    // How long the ban was set to last, None for permanent bans
+    pub duration: Option<i64>,
+    pub similarity: f32,
+}
+
+// How to push out the expiry of a temporary ban
+#[derive(Clone, Copy, Debug)]
+pub enum Extension {
+    // A new expiry day, like the revoked_at of a new ban
+    Until(i64),
+    Days(i64),
+}
+
+// A ban close to expiring, also the payload of the ban_expiring event
+#[derive(Debug, Serialize)]
+pub struct ExpiringBan {
+    pub id: i64,
+    pub entity: Entity,
+    pub expires_at: i64,
+}
+
+// A scheduled character ban that has started, with the pilot's Discord account if linked
+#[derive(Debug)]
+pub struct StartedBan {
+    pub id: i64,
+    pub character_id: i64,
+    pub discord_id: Option<String>,
+    pub starts_at: i64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeType {
+    Created,
+    Updated,
+    // Revoked by hand or expired
+    Revoked,
+}
+
+// A ban as it is now, with what last happened to it
+#[derive(Debug)]
+pub struct BanChange {
+    pub change: ChangeType,
+    pub changed_at: i64,
+    pub ban: Ban,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BanException {
+    pub id: i64,
+    pub corporation: Entity,
+    pub reason: String,
+    pub created_at: i64,
+    pub created_by: Character,
+}
+
+// Where a page of bans ended, handed to clients as an opaque string
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BanCursor {
+    pub issued_at: i64,
+    pub id: i64,
+}
+
+impl BanCursor {
+    pub fn after(ban: &Ban) -> Option<BanCursor> {
+        Some(BanCursor {
+            issued_at: ban.issued_at?,
+            id: ban.id?,
+        })
+    }
+
+    pub fn encode(&self) -> String {
+        hex::encode(format!("{},{}", self.issued_at, self.id))
+    }
+
+    pub fn decode(cursor: &str) -> Option<BanCursor> {
+        let decoded = String::from_utf8(hex::decode(cursor).ok()?).ok()?;
+        let (issued_at, id) = decoded.split_once(',')?;
+        Some(BanCursor {
+            issued_at: issued_at.parse().ok()?,
+            id: id.parse().ok()?,
+        })
+    }
+}
+
 pub struct BanService {
     db: Arc<crate::DB>,
+    clock: Arc<dyn Clock>,
+    snap_to_downtime: bool,
 }
 
 impl BanService {
-    pub fn new(database: Arc<crate::DB>) -> BanService {
-        BanService { db: database }
+    pub fn new(database: Arc<crate::DB>, clock: Arc<dyn Clock>) -> BanService {
+        BanService {
+            db: database,
+            clock,
+            snap_to_downtime: false,
+        }
+    }
+
+    // See bans.snap_expiry_to_downtime in the config
+    pub fn snap_to_downtime(mut self, snap: bool) -> BanService {
+        self.snap_to_downtime = snap;
+        self
+    }
+
+    // When a ban set to expire on the given day ends
+    pub fn expires_at(&self, day: i64) -> i64 {
+        match self.snap_to_downtime {
+            true => next_downtime(day),
+            false => compute_expiry(day),
+        }
     }
 
+    // Precedence: a direct character ban or a name pattern ban, then an exception for the
+    // character's corporation, then any ban inherited from the corporation or its alliance
     pub async fn character_bans(&self, character_id: i64) -> Result<Option<Vec<Ban>>, Madness> {
         if let Some(bans) = self.active_bans(character_id, "Character").await? {
             Ok(Some(bans))
         } else {
             if let Some(character) = sqlx::query!(
-                "SELECT corporation_id FROM character WHERE id=$1",
+                "SELECT name, corporation_id FROM character WHERE id=$1",
                 character_id
             )
             .fetch_optional(self.db.as_ref())
             .await?
             {
+                // Names are kept up to date from ESI by the affiliation service
+                if let Some(bans) = self.name_pattern_bans(&character.name).await? {
+                    return Ok(Some(bans));
+                }
                 if let Some(corporation_id) = character.corporation_id {
+                    if self.has_exception(corporation_id).await? {
+                        return Ok(None);
+                    }
                     if let Some(bans) = self.corporation_bans(corporation_id).await? {
                         return Ok(Some(bans));
                     }
@@ -35,6 +441,164 @@ impl BanService {
         }
     }
 
+    // character_bans for many characters at once, going by the given affiliations rather than
+    // the cached ones. Returns the bans of each character in the same order.
+    pub async fn check_characters(
+        &self,
+        affiliations: &[CharacterAffiliation],
+    ) -> Result<Vec<(i64, Option<Vec<Ban>>)>, Madness> {
+        let now: i64 = self.clock.now().timestamp();
+
+        let character_ids: Vec<i64> = affiliations.iter().map(|a| a.character_id).collect();
+        let corporation_ids: Vec<i64> = affiliations.iter().map(|a| a.corporation_id).collect();
+        let mut entity_ids = vec![NAME_PATTERN_ENTITY_ID];
+        for affiliation in affiliations {
+            entity_ids.push(affiliation.character_id);
+            entity_ids.push(affiliation.corporation_id);
+            entity_ids.extend(affiliation.alliance_id);
+        }
+
+        let rows: Vec<BanRow> = sqlx::query_as(select_bans!(
+            "WHERE
+                entity_id = ANY($1) AND (revoked_at IS NULL OR revoked_at > $2) AND NOT pending_approval
+                AND (starts_at IS NULL OR starts_at <= $2)"
+        ))
+        .bind(&entity_ids)
+        .bind(now)
+        .fetch_all(self.db.as_ref())
+        .await?;
+
+        let bans = self
+            .with_tags(rows.into_iter().map(Ban::from).collect())
+            .await?;
+
+        // Bans by entity, the IDs of different entity types never overlap but the type is
+        // checked anyway
+        let mut by_entity: HashMap<(String, i64), Vec<Ban>> = HashMap::new();
+        let mut patterns = Vec::new();
+        for ban in bans {
+            let entity = ban.entity.as_ref().unwrap();
+            if entity.category == NAME_PATTERN {
+                patterns.push(ban);
+            } else {
+                by_entity
+                    .entry((entity.category.clone(), entity.id))
+                    .or_default()
+                    .push(ban);
+            }
+        }
+
+        // Names are kept up to date from ESI by the affiliation service, characters that were
+        // never seen here can't match a pattern
+        let names: HashMap<i64, String> = sqlx::query!(
+            "SELECT id, name FROM character WHERE id = ANY($1)",
+            &character_ids
+        )
+        .fetch_all(self.db.as_ref())
+        .await?
+        .into_iter()
+        .map(|row| (row.id, row.name))
+        .collect();
+
+        let exceptions: BTreeSet<i64> = sqlx::query!(
+            "SELECT corporation_id FROM ban_exception WHERE corporation_id = ANY($1)",
+            &corporation_ids
+        )
+        .fetch_all(self.db.as_ref())
+        .await?
+        .into_iter()
+        .map(|row| row.corporation_id)
+        .collect();
+
+        let find = |category: &str, id: i64| by_entity.get(&(category.to_string(), id)).cloned();
+
+        // Same precedence as character_bans
+        Ok(affiliations
+            .iter()
+            .map(|affiliation| {
+                let pattern_bans: Vec<Ban> = match names.get(&affiliation.character_id) {
+                    Some(name) => patterns
+                        .iter()
+                        .filter(
+                            |ban| match ban.entity.as_ref().and_then(|e| e.name.as_deref()) {
+                                Some(pattern) => name_pattern::matches(pattern, name),
+                                None => false,
+                            },
+                        )
+                        .cloned()
+                        .collect(),
+                    None => Vec::new(),
+                };
+
+                let bans = if let Some(bans) = find("Character", affiliation.character_id) {
+                    Some(bans)
+                } else if !pattern_bans.is_empty() {
+                    Some(pattern_bans)
+                } else if exceptions.contains(&affiliation.corporation_id) {
+                    None
+                } else if let Some(bans) = find("Corporation", affiliation.corporation_id) {
+                    Some(bans)
+                } else {
+                    affiliation
+                        .alliance_id
+                        .and_then(|alliance_id| find("Alliance", alliance_id))
+                };
+                (affiliation.character_id, bans)
+            })
+            .collect())
+    }
+
+    // Whether the entity (or for pattern bans, the exact same pattern) is already banned
+    pub async fn has_active(&self, entity: &Entity) -> Result<bool, Madness> {
+        if entity.category == NAME_PATTERN {
+            return Ok(self
+                .active_bans(NAME_PATTERN_ENTITY_ID, NAME_PATTERN)
+                .await?
+                .unwrap_or_default()
+                .iter()
+                .any(|ban| {
+                    ban.entity.as_ref().and_then(|e| e.name.as_ref()) == entity.name.as_ref()
+                }));
+        }
+        Ok(self
+            .active_bans(entity.id, &entity.category)
+            .await?
+            .is_some())
+    }
+
+    // When the most recent ban on the entity was revoked by an FC, expiries don't count
+    pub async fn last_revoked_at(&self, entity: &Entity) -> Result<Option<i64>, Madness> {
+        Ok(sqlx::query!(
+            "SELECT MAX(revoked_at) AS revoked_at FROM ban WHERE entity_type=$1 AND entity_id=$2 AND revoked_by IS NOT NULL",
+            entity.category,
+            entity.id
+        )
+        .fetch_one(self.db.as_ref())
+        .await?
+        .revoked_at)
+    }
+
+    // Active name pattern bans that match the name
+    pub async fn name_pattern_bans(&self, name: &str) -> Result<Option<Vec<Ban>>, Madness> {
+        let bans: Vec<Ban> = self
+            .active_bans(NAME_PATTERN_ENTITY_ID, NAME_PATTERN)
+            .await?
+            .unwrap_or_default()
+            .into_iter()
+            .filter(
+                |ban| match ban.entity.as_ref().and_then(|e| e.name.as_deref()) {
+                    Some(pattern) => name_pattern::matches(pattern, name),
+                    None => false,
+                },
+            )
+            .collect();
+
+        match bans.is_empty() {
+            true => Ok(None),
+            false => Ok(Some(bans)),
+        }
+    }
+
     pub async fn corporation_bans(&self, corporation_id: i64) -> Result<Option<Vec<Ban>>, Madness> {
         if let Some(bans) = self.active_bans(corporation_id, "Corporation").await? {
             Ok(Some(bans))
@@ -65,148 +629,1937 @@ impl BanService {
         }
     }
 
-    pub async fn active_bans(
-        &self,
-        entity_id: i64,
-        entity_type: &str,
-    ) -> Result<Option<Vec<Ban>>, Madness> {
-        let now: i64 = chrono::Utc::now().timestamp();
+    pub async fn has_exception(&self, corporation_id: i64) -> Result<bool, Madness> {
+        Ok(sqlx::query!(
+            "SELECT id FROM ban_exception WHERE corporation_id=$1",
+            corporation_id
+        )
+        .fetch_optional(self.db.as_ref())
+        .await?
+        .is_some())
+    }
 
+    pub async fn exceptions(&self) -> Result<Vec<BanException>, Madness> {
         let rows = sqlx::query!(
-            "SELECT 
-                ban.id,
-                entity_id,
-                entity_name,
-                entity_type,
-                issued_at,
-                public_reason,
-                reason,
-                revoked_at,
-                issuer.id AS \"issued_by_id\",
-                issuer.name AS \"issued_by_name\"
+            "SELECT
+                ban_exception.id,
+                ban_exception.corporation_id,
+                ban_exception.corporation_name,
+                ban_exception.reason,
+                ban_exception.created_at,
+                creator.id AS \"created_by_id\",
+                creator.name AS \"created_by_name\"
             FROM
-                ban
+                ban_exception
             JOIN
-                character as issuer ON issued_by=issuer.id
-            WHERE
-                entity_id=$1 AND entity_type=$2 AND (revoked_at IS NULL OR revoked_at > $3)",
-            entity_id,
-            entity_type,
-            now
+                character as creator ON ban_exception.created_by=creator.id
+            ORDER BY
+                ban_exception.created_at"
         )
         .fetch_all(self.db.as_ref())
         .await?;
 
-        if rows.len() == 0 {
-            return Ok(None);
-        }
-
-        let bans: Vec<Ban> = rows
+        Ok(rows
             .into_iter()
-            .map(|ban| Ban {
-                id: Some(ban.id),
-                entity: Some(Entity {
-                    id: ban.entity_id,
-                    name: ban.entity_name,
-                    category: ban.entity_type,
-                }),
-                issued_at: Some(ban.issued_at),
-                issued_by: Some(Character {
-                    id: ban.issued_by_id,
-                    name: ban.issued_by_name,
+            .map(|row| BanException {
+                id: row.id,
+                corporation: Entity {
+                    id: row.corporation_id,
+                    name: row.corporation_name,
+                    category: "Corporation".to_string(),
+                },
+                reason: row.reason,
+                created_at: row.created_at,
+                created_by: Character {
+                    id: row.created_by_id,
+                    name: row.created_by_name,
                     corporation_id: None,
-                }),
-                reason: ban.reason,
-                public_reason: ban.public_reason,
-                revoked_at: ban.revoked_at,
-                revoked_by: None,
+                },
             })
-            .collect();
+            .collect())
+    }
+
+    pub async fn add_exception(
+        &self,
+        corporation: &Entity,
+        reason: &str,
+        created_by: i64,
+    ) -> Result<i64, Madness> {
+        let row = sqlx::query!(
+            "INSERT INTO ban_exception (corporation_id, corporation_name, reason, created_at, created_by) VALUES ($1, $2, $3, $4, $5) ON CONFLICT (corporation_id) DO NOTHING RETURNING id",
+            corporation.id,
+            corporation.name,
+            reason,
+            self.clock.now().timestamp(),
+            created_by
+        )
+        .fetch_optional(self.db.as_ref())
+        .await?;
 
-        return Ok(Some(bans));
+        match row {
+            Some(row) => Ok(row.id),
+            None => Err(Madness::Conflict(
+                "This corporation already has an exception".to_string(),
+            )),
+        }
     }
 
-    pub async fn all_bans(
+    // Returns false if the corporation had no exception
+    pub async fn remove_exception(&self, corporation_id: i64) -> Result<bool, Madness> {
+        let result = sqlx::query!(
+            "DELETE FROM ban_exception WHERE corporation_id=$1",
+            corporation_id
+        )
+        .execute(self.db.as_ref())
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn active_bans(
         &self,
         entity_id: i64,
         entity_type: &str,
     ) -> Result<Option<Vec<Ban>>, Madness> {
-        let rows = sqlx::query!(
-            "SELECT 
-                ban.id,
-                entity_id,
-                entity_name,
-                entity_type,
-                issued_at,
-                public_reason,
-                reason,
-                revoked_at,
-                issuer.id AS \"issued_by_id\",
-                issuer.name AS \"issued_by_name\",
-                revoked_by
+        let now: i64 = self.clock.now().timestamp();
+
+        let rows: Vec<BanRow> = sqlx::query_as(select_bans!(
+            "WHERE
+                entity_id=$1 AND entity_type=$2 AND (revoked_at IS NULL OR revoked_at > $3) AND NOT pending_approval
+                AND (starts_at IS NULL OR starts_at <= $3)"
+        ))
+        .bind(entity_id)
+        .bind(entity_type)
+        .bind(now)
+        .fetch_all(self.db.as_ref())
+        .await?;
+
+        if rows.len() == 0 {
+            return Ok(None);
+        }
+
+        return Ok(Some(rows.into_iter().map(Ban::from).collect()));
+    }
+
+    // How many bans all_active would return
+    pub async fn count_active(&self, filter: &ActiveFilter<'_>) -> Result<i64, Madness> {
+        let now: i64 = self.clock.now().timestamp();
+        let expiring_before = filter.expiring_within.map(|window| now + window);
+        Ok(sqlx::query!(
+            "SELECT COUNT(*) AS \"count!\" FROM ban
+            WHERE
+                (revoked_at IS NULL OR revoked_at > $1) AND NOT pending_approval
+                AND ($6 OR starts_at IS NULL OR starts_at <= $1)
+                AND (cardinality($2::VARCHAR[]) = 0 OR (SELECT COUNT(*) FROM ban_tag WHERE ban_tag.ban_id=ban.id AND tag = ANY($2)) = cardinality($2))
+                AND ($3::BIGINT IS NULL OR revoked_at <= $3)
+                AND ($4::BIGINT IS NULL OR issued_by=$4 OR on_behalf_of=$4)
+                AND ($5::TEXT IS NULL OR entity_name ILIKE $5)",
+            now,
+            filter.tags,
+            expiring_before,
+            filter.issued_by,
+            filter.search_pattern(),
+            filter.include_scheduled
+        )
+        .fetch_one(self.db.as_ref())
+        .await?
+        .count)
+    }
+
+    // Every ban that hasn't been revoked or expired yet, that matches the filter
+    pub async fn all_active(&self, filter: &ActiveFilter<'_>) -> Result<Vec<Ban>, Madness> {
+        let now: i64 = self.clock.now().timestamp();
+        let expiring_before = filter.expiring_within.map(|window| now + window);
+
+        let rows: Vec<BanRow> = sqlx::query_as(select_bans!(
+            "WHERE
+                (revoked_at IS NULL OR revoked_at > $1) AND NOT pending_approval
+                AND ($6 OR starts_at IS NULL OR starts_at <= $1)
+                AND (cardinality($2::VARCHAR[]) = 0 OR (SELECT COUNT(*) FROM ban_tag WHERE ban_tag.ban_id=ban.id AND tag = ANY($2)) = cardinality($2))
+                AND ($3::BIGINT IS NULL OR revoked_at <= $3)
+                AND ($4::BIGINT IS NULL OR issued_by=$4 OR on_behalf_of=$4)
+                AND ($5::TEXT IS NULL OR entity_name ILIKE $5)"
+        ))
+        .bind(now)
+        .bind(filter.tags)
+        .bind(expiring_before)
+        .bind(filter.issued_by)
+        .bind(filter.search_pattern())
+        .bind(filter.include_scheduled)
+        .fetch_all(self.db.as_ref())
+        .await?;
+
+        self.with_tags(rows.into_iter().map(Ban::from).collect())
+            .await
+    }
+
+    // A page of active bans, newest first unless ascending. With a cursor the page starts right
+    // after the ban it points at, so bans issued while paging don't shift the remaining pages.
+    pub async fn active_page(
+        &self,
+        limit: i64,
+        offset: i64,
+        after: Option<BanCursor>,
+        ascending: bool,
+        filter: &ActiveFilter<'_>,
+    ) -> Result<Vec<Ban>, Madness> {
+        let now: i64 = self.clock.now().timestamp();
+        let expiring_before = filter.expiring_within.map(|window| now + window);
+        let (after_issued_at, after_id) = match after {
+            Some(cursor) => (Some(cursor.issued_at), Some(cursor.id)),
+            None => (None, None),
+        };
+
+        let rows: Vec<BanRow> = sqlx::query_as(select_bans!(
+            "WHERE
+                (revoked_at IS NULL OR revoked_at > $1)
+                AND NOT pending_approval
+                AND ($11 OR starts_at IS NULL OR starts_at <= $1)
+                AND ($2::BIGINT IS NULL
+                    OR ($9 AND (issued_at, ban.id) > ($2, $3))
+                    OR (NOT $9 AND (issued_at, ban.id) < ($2, $3)))
+                AND (cardinality($6::VARCHAR[]) = 0 OR (SELECT COUNT(*) FROM ban_tag WHERE ban_tag.ban_id=ban.id AND tag = ANY($6)) = cardinality($6))
+                AND ($7::BIGINT IS NULL OR revoked_at <= $7)
+                AND ($8::BIGINT IS NULL OR issued_by=$8 OR on_behalf_of=$8)
+                AND ($10::TEXT IS NULL OR entity_name ILIKE $10)
+            ORDER BY
+                CASE WHEN $9 THEN issued_at END ASC,
+                CASE WHEN $9 THEN ban.id END ASC,
+                issued_at DESC,
+                ban.id DESC
+            LIMIT $4 OFFSET $5"
+        ))
+        .bind(now)
+        .bind(after_issued_at)
+        .bind(after_id)
+        .bind(limit)
+        .bind(offset)
+        .bind(filter.tags)
+        .bind(expiring_before)
+        .bind(filter.issued_by)
+        .bind(ascending)
+        .bind(filter.search_pattern())
+        .bind(filter.include_scheduled)
+        .fetch_all(self.db.as_ref())
+        .await?;
+
+        self.with_tags(rows.into_iter().map(Ban::from).collect())
+            .await
+    }
+
+    // Most recent bans issued by the account, including revoked and pending ones
+    pub async fn issued_by(&self, account_id: i64, limit: i64) -> Result<Vec<Ban>, Madness> {
+        let rows: Vec<BanRow> = sqlx::query_as(select_bans!(
+            "WHERE
+                issued_by=$1
+            ORDER BY
+                issued_at DESC, ban.id DESC
+            LIMIT $2"
+        ))
+        .bind(account_id)
+        .bind(limit)
+        .fetch_all(self.db.as_ref())
+        .await?;
+
+        self.with_tags(rows.into_iter().map(Ban::from).collect())
+            .await
+    }
+
+    // Active bans whose reason is too short to explain them, oldest first so the backlog is
+    // worked through in order
+    pub async fn incomplete(&self, min_length: i32, limit: i64) -> Result<Vec<Ban>, Madness> {
+        let now: i64 = self.clock.now().timestamp();
+        let rows: Vec<BanRow> = sqlx::query_as(select_bans!(
+            "WHERE
+                (revoked_at IS NULL OR revoked_at > $1) AND NOT pending_approval
+                AND (starts_at IS NULL OR starts_at <= $1)
+                AND char_length(btrim(reason)) < $2
+            ORDER BY
+                issued_at ASC, ban.id ASC
+            LIMIT $3"
+        ))
+        .bind(now)
+        .bind(min_length)
+        .bind(limit)
+        .fetch_all(self.db.as_ref())
+        .await?;
+
+        self.with_tags(rows.into_iter().map(Ban::from).collect())
+            .await
+    }
+
+    // Bans that changed after the given time, oldest change first. after_id breaks ties with the
+    // last change of the previous page. Pending bans are left out until they're approved, a
+    // scheduled ban shows up again when it starts.
+    pub async fn changes(
+        &self,
+        since: i64,
+        after_id: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<BanChange>, Madness> {
+        let now: i64 = self.clock.now().timestamp();
+        let changed = sqlx::query!(
+            "WITH changed AS (
+                SELECT
+                    id,
+                    GREATEST(
+                        issued_at,
+                        COALESCE(updated_at, issued_at),
+                        CASE WHEN revoked_at <= $1 THEN revoked_at ELSE issued_at END,
+                        CASE WHEN starts_at <= $1 THEN starts_at ELSE issued_at END
+                    ) AS changed_at
+                FROM
+                    ban
+                WHERE
+                    NOT pending_approval
+            )
+            SELECT
+                id AS \"id!\",
+                changed_at AS \"changed_at!\"
             FROM
-                ban
-            JOIN
-                character as issuer ON issued_by=issuer.id
+                changed
             WHERE
-                entity_id=$1 AND entity_type=$2
+                changed_at > $2 OR (changed_at = $2 AND id > $3)
             ORDER BY
-                issued_at",
-            entity_id,
-            entity_type,
+                changed_at ASC, id ASC
+            LIMIT $4",
+            now,
+            since,
+            // Without a tie-breaker the change has to be strictly after since
+            after_id.unwrap_or(i64::MAX),
+            limit
         )
         .fetch_all(self.db.as_ref())
         .await?;
 
-        if rows.len() == 0 {
-            return Ok(None);
-        }
+        let ids: Vec<i64> = changed.iter().map(|row| row.id).collect();
+        let rows: Vec<BanRow> = sqlx::query_as(select_bans!("WHERE ban.id = ANY($1)"))
+            .bind(&ids)
+            .fetch_all(self.db.as_ref())
+            .await?;
+        let mut bans: HashMap<i64, Ban> = self
+            .with_tags(rows.into_iter().map(Ban::from).collect())
+            .await?
+            .into_iter()
+            .filter_map(|ban| Some((ban.id?, ban)))
+            .collect();
 
-        let mut bans: Vec<Ban> = rows
+        // issued_at is when the ban was created, edits only move updated_at
+        Ok(changed
             .into_iter()
-            .map(|ban| Ban {
-                id: Some(ban.id),
-                entity: Some(Entity {
-                    id: ban.entity_id,
-                    name: ban.entity_name,
-                    category: ban.entity_type,
-                }),
-                issued_at: Some(ban.issued_at),
-                issued_by: Some(Character {
-                    id: ban.issued_by_id,
-                    name: ban.issued_by_name,
-                    corporation_id: None,
-                }),
-                reason: ban.reason,
-                public_reason: ban.public_reason,
-                revoked_at: ban.revoked_at,
-                revoked_by: match ban.revoked_by {
-                    Some(id) => Some(Character {
-                        id,
-                        name: "".to_string(),
-                        corporation_id: None,
-                    }),
-                    None => None,
-                },
+            .filter_map(|row| {
+                let ban = bans.remove(&row.id)?;
+                Some(BanChange {
+                    change: match (ban.revoked_at, ban.issued_at) {
+                        (Some(revoked_at), _) if revoked_at <= now => ChangeType::Revoked,
+                        (_, Some(issued_at)) if issued_at > since => ChangeType::Created,
+                        _ => ChangeType::Updated,
+                    },
+                    changed_at: row.changed_at,
+                    ban,
+                })
             })
-            .collect();
+            .collect())
+    }
 
-        // foreach ban, go through and look up name
-        for b in bans.iter_mut() {
-            if let Some(revoked_by) = &b.revoked_by {
-                if let Ok(row) = sqlx::query!("SELECT * FROM character WHERE id=$1", revoked_by.id)
-                    .fetch_optional(self.db.as_ref())
-                    .await
-                {
-                    b.revoked_by = Some(Character {
-                        id: revoked_by.id,
-                        name: row.unwrap().name,
-                        corporation_id: None,
-                    })
-                }
-            }
+    // Stores a new ban, revoked_at is taken as the day it expires on
+    pub async fn insert(
+        &self,
+        ban: &Ban,
+        issued_by: i64,
+        on_behalf_of: Option<i64>,
+    ) -> Result<i64, Madness> {
+        let entity = match &ban.entity {
+            Some(entity) => entity,
+            None => return Err(Madness::BadRequest("A ban needs an entity".to_string())),
+        };
+        let issued_at = ban
+            .issued_at
+            .unwrap_or_else(|| self.clock.now().timestamp());
+
+        let ban_id = sqlx::query!(
+            "INSERT INTO ban (entity_type, entity_id, entity_name, issued_at, issued_by, reason, public_reason, revoked_at, silent, on_behalf_of, expiry_day, category, pending_approval, name_pending, esi_category, supersedes_ban_id, fc_note, starts_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18) RETURNING id",
+            entity.category,
+            entity.id,
+            entity.name,
+            issued_at,
+            issued_by,
+            ban.reason,
+            ban.public_reason,
+            ban.revoked_at.map(|day| self.expires_at(day)),
+            ban.silent,
+            on_behalf_of,
+            ban.revoked_at,
+            ban.category,
+            ban.pending_approval,
+            ban.name_pending,
+            ban.esi_category,
+            ban.supersedes_ban_id,
+            ban.fc_note,
+            ban.starts_at,
+        )
+        .fetch_one(self.db.as_ref())
+        .await?
+        .id;
+
+        if !ban.tags.is_empty() {
+            self.set_tags(ban_id, &ban.tags).await?;
         }
+        Ok(ban_id)
+    }
+
+    // Kept with the ban so it's clear later on that the second active ban wasn't a mistake
+    pub async fn record_duplicate_override(
+        &self,
+        ban_id: i64,
+        overridden_by: i64,
+    ) -> Result<(), Madness> {
+        sqlx::query!(
+            "UPDATE ban SET duplicate_override_by=$1 WHERE id=$2",
+            overridden_by,
+            ban_id
+        )
+        .execute(self.db.as_ref())
+        .await?;
+        Ok(())
+    }
+
+    // Bans created while ESI was down, their entity names still have to be looked up
+    pub async fn pending_names(&self) -> Result<Vec<Entity>, Madness> {
+        Ok(
+            sqlx::query!("SELECT DISTINCT entity_id, entity_type FROM ban WHERE name_pending")
+                .fetch_all(self.db.as_ref())
+                .await?
+                .into_iter()
+                .map(|row| Entity {
+                    id: row.entity_id,
+                    name: None,
+                    category: row.entity_type,
+                })
+                .collect(),
+        )
+    }
+
+    pub async fn set_name(&self, entity: &Entity, name: &str) -> Result<(), Madness> {
+        sqlx::query!(
+            "UPDATE ban SET entity_name=$1, name_pending=FALSE WHERE entity_id=$2 AND entity_type=$3 AND name_pending",
+            name,
+            entity.id,
+            entity.category
+        )
+        .execute(self.db.as_ref())
+        .await?;
+        Ok(())
+    }
+
+    // Bans waiting for a second FC, oldest first
+    pub async fn pending(&self) -> Result<Vec<Ban>, Madness> {
+        let ids = sqlx::query!("SELECT id FROM ban WHERE pending_approval ORDER BY issued_at")
+            .fetch_all(self.db.as_ref())
+            .await?;
+
+        let mut bans = Vec::new();
+        for row in ids {
+            if let Some(ban) = self.find(row.id).await? {
+                bans.push(ban);
+            }
+        }
+        Ok(bans)
+    }
+
+    // Activates a pending ban, the FC it was issued by (or for) can't approve it themselves
+    pub async fn approve(&self, ban_id: i64, approved_by: i64) -> Result<(), Madness> {
+        let ban = match sqlx::query!(
+            "SELECT issued_by, on_behalf_of, pending_approval FROM ban WHERE id=$1",
+            ban_id
+        )
+        .fetch_optional(self.db.as_ref())
+        .await?
+        {
+            Some(ban) => ban,
+            None => return Err(Madness::NotFound("Ban not found")),
+        };
+
+        if !ban.pending_approval {
+            return Err(Madness::BadRequest(
+                "This ban is not waiting for approval".to_string(),
+            ));
+        }
+        if ban.issued_by == approved_by || ban.on_behalf_of == Some(approved_by) {
+            return Err(Madness::Forbidden(
+                "A ban has to be approved by a different FC".to_string(),
+            ));
+        }
+
+        // Guards against two FCs approving at the same time
+        let result = sqlx::query!(
+            "UPDATE ban SET pending_approval=FALSE, approved_by=$1, updated_at=$3 WHERE id=$2 AND pending_approval",
+            approved_by,
+            ban_id,
+            self.clock.now().timestamp()
+        )
+        .execute(self.db.as_ref())
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(Madness::Conflict(
+                "This ban has already been approved".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub async fn revoke(&self, ban_id: i64, revoked_by: i64) -> Result<(), Madness> {
+        let ban = match sqlx::query!("SELECT * FROM ban WHERE id=$1", ban_id)
+            .fetch_optional(self.db.as_ref())
+            .await?
+        {
+            Some(ban) => ban,
+            None => {
+                return Err(Madness::BadRequest(format!(
+                    "Could not find a ban with the ID of {}",
+                    ban_id
+                )))
+            }
+        };
+
+        let now = self.clock.now().timestamp();
+        if let Some(revoked_at) = ban.revoked_at {
+            if revoked_at < now {
+                let fc_id = match ban.revoked_by {
+                    Some(fc_id) => fc_id,
+                    None => {
+                        return Err(Madness::BadRequest(
+                            "Cannot revoke the ban as it has already expired".to_string(),
+                        ))
+                    }
+                };
+
+                // The revoker can be missing, see orphaned_references
+                let fc = sqlx::query!("SELECT name FROM character WHERE id=$1", fc_id)
+                    .fetch_optional(self.db.as_ref())
+                    .await?;
+                return Err(Madness::BadRequest(match fc {
+                    Some(fc) => format!("{} has already revoked this ban", fc.name),
+                    None => "This ban has already been revoked".to_string(),
+                }));
+            }
+        }
+
+        sqlx::query!(
+            "UPDATE ban SET revoked_at=$1, revoked_by=$2 WHERE id=$3",
+            now,
+            revoked_by,
+            ban_id
+        )
+        .execute(self.db.as_ref())
+        .await?;
+
+        Ok(())
+    }
+
+    // Hands the active bans of an FC to another, keeping the original issuer in
+    // ban_issuer_history. Returns the IDs of the bans that moved.
+    pub async fn reassign(
+        &self,
+        from_character_id: i64,
+        to_character_id: i64,
+        reassigned_by: i64,
+    ) -> Result<Vec<i64>, Madness> {
+        let now = self.clock.now().timestamp();
+        let mut tx = self.db.begin().await?;
+        let ban_ids: Vec<i64> = sqlx::query!(
+            "SELECT id FROM ban WHERE issued_by=$1 AND (revoked_at IS NULL OR revoked_at > $2) FOR UPDATE",
+            from_character_id,
+            now
+        )
+        .fetch_all(&mut tx)
+        .await?
+        .into_iter()
+        .map(|row| row.id)
+        .collect();
+
+        sqlx::query!(
+            "INSERT INTO ban_issuer_history (ban_id, issued_by, reassigned_at, reassigned_by)
+            SELECT UNNEST($1::BIGINT[]), $2, $3, $4",
+            &ban_ids,
+            from_character_id,
+            now,
+            reassigned_by
+        )
+        .execute(&mut tx)
+        .await?;
+        sqlx::query!(
+            "UPDATE ban SET issued_by=$1, updated_at=$3 WHERE id = ANY($2)",
+            to_character_id,
+            &ban_ids,
+            now
+        )
+        .execute(&mut tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok(ban_ids)
+    }
+
+    // Bans with reasons close to the given one by trigram similarity, most similar first
+    pub async fn similar(
+        &self,
+        reason: &str,
+        entity_type: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<SimilarBan>, Madness> {
+        Ok(sqlx::query!(
+            "SELECT
+                id,
+                entity_id,
+                entity_name,
+                entity_type,
+                issued_at,
+                reason,
+                public_reason,
+                category,
+                revoked_at,
+                revoked_by,
+                expiry_day,
+                similarity(reason, $1) AS \"similarity!\"
+            FROM
+                ban
+            WHERE
+                reason % $1 AND ($2::VARCHAR IS NULL OR entity_type=$2) AND NOT pending_approval
+            ORDER BY
+                similarity(reason, $1) DESC, issued_at DESC
+            LIMIT $3",
+            reason,
+            entity_type,
+            limit
+        )
+        .fetch_all(self.db.as_ref())
+        .await?
+        .into_iter()
+        .map(|row| SimilarBan {
+            id: row.id,
+            entity: Entity {
+                id: row.entity_id,
+                name: row.entity_name,
+                category: row.entity_type,
+            },
+            issued_at: row.issued_at,
+            reason: row.reason,
+            public_reason: row.public_reason,
+            category: row.category,
+            // A revoke by an FC cut the ban short, older bans only kept the expiry itself
+            duration: match (row.expiry_day, row.revoked_at, row.revoked_by) {
+                (Some(day), _, _) => Some(self.expires_at(day) - row.issued_at),
+                (None, Some(revoked_at), None) => Some(revoked_at - row.issued_at),
+                _ => None,
+            },
+            similarity: row.similarity,
+        })
+        .collect())
+    }
+
+    // Records that the banned character saw the public reason, the first time counts.
+    // Returns false if the ban isn't active.
+    pub async fn acknowledge(&self, ban_id: i64) -> Result<bool, Madness> {
+        let now = self.clock.now().timestamp();
+        let result = sqlx::query!(
+            "UPDATE ban SET acknowledged_at=COALESCE(acknowledged_at, $1)
+            WHERE id=$2 AND (revoked_at IS NULL OR revoked_at > $1) AND NOT pending_approval
+                AND (starts_at IS NULL OR starts_at <= $1)",
+            now,
+            ban_id
+        )
+        .execute(self.db.as_ref())
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    // Ends an active corporation ban because the corporation no longer exists. There's no one
+    // to credit, so revoked_by stays empty and the ban is tagged with DISSOLVED_TAG instead.
+    // The corporation's exception from its alliance ban goes too, and so does the expiry, or
+    // recompute_expiry would bring a temporary ban back. Returns false if the ban wasn't active.
+    pub async fn revoke_dissolved(&self, ban_id: i64) -> Result<bool, Madness> {
+        let now = self.clock.now().timestamp();
+        let mut tx = self.db.begin().await?;
+        let ban = match sqlx::query!(
+            "UPDATE ban SET revoked_at=$1, revoked_by=NULL, expiry_day=NULL
+            WHERE id=$2 AND entity_type='Corporation' AND (revoked_at IS NULL OR revoked_at > $1)
+            RETURNING entity_id",
+            now,
+            ban_id
+        )
+        .fetch_optional(&mut tx)
+        .await?
+        {
+            Some(ban) => ban,
+            None => return Ok(false),
+        };
+
+        sqlx::query!(
+            "INSERT INTO ban_tag (ban_id, tag) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+            ban_id,
+            DISSOLVED_TAG
+        )
+        .execute(&mut tx)
+        .await?;
+        sqlx::query!(
+            "DELETE FROM ban_exception WHERE corporation_id=$1",
+            ban.entity_id
+        )
+        .execute(&mut tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    // Pushes out the expiry of an active temporary ban, leaving everything else alone.
+    // Returns the new expiry.
+    pub async fn extend(&self, ban_id: i64, extension: Extension) -> Result<i64, Madness> {
+        let mut tx = self.db.begin().await?;
+        let ban = match sqlx::query!(
+            "SELECT revoked_at, expiry_day FROM ban WHERE id=$1 FOR UPDATE",
+            ban_id
+        )
+        .fetch_optional(&mut tx)
+        .await?
+        {
+            Some(ban) => ban,
+            None => return Err(Madness::NotFound("Ban not found")),
+        };
+
+        let now = self.clock.now().timestamp();
+        let revoked_at = match ban.revoked_at {
+            Some(revoked_at) if revoked_at <= now => {
+                return Err(Madness::BadRequest(
+                    "Cannot extend a ban that has already ended".to_string(),
+                ))
+            }
+            Some(revoked_at) => revoked_at,
+            None => {
+                return Err(Madness::BadRequest(
+                    "Permanent bans cannot be extended".to_string(),
+                ))
+            }
+        };
+
+        let expiry_day = match extension {
+            Extension::Until(day) => day,
+            // Bans from before expiry_day was stored only have the expiry
+            Extension::Days(days) => {
+                ban.expiry_day.unwrap_or(revoked_at - DOWNTIME_OFFSET) + days * DAY
+            }
+        };
+        let expires_at = self.expires_at(expiry_day);
+        if expires_at <= revoked_at {
+            return Err(Madness::BadRequest(
+                "The new expiry has to be later than the current one".to_string(),
+            ));
+        }
+
+        sqlx::query!(
+            "UPDATE ban SET revoked_at=$1, expiry_day=$2, updated_at=$4 WHERE id=$3",
+            expires_at,
+            expiry_day,
+            ban_id,
+            now
+        )
+        .execute(&mut tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok(expires_at)
+    }
+
+    // Character bans on pilots that never logged in are normal, but account bans should always
+    // point at a known character
+    pub async fn orphaned_references(&self) -> Result<Vec<OrphanedReference>, Madness> {
+        Ok(sqlx::query!(
+            "SELECT ban_id AS \"ban_id!\", ban_column AS \"column!\", character_id AS \"character_id!\"
+            FROM (
+                SELECT id AS ban_id, 'issued_by' AS ban_column, issued_by AS character_id FROM ban
+                UNION ALL
+                SELECT id, 'revoked_by', revoked_by FROM ban
+                UNION ALL
+                SELECT id, 'on_behalf_of', on_behalf_of FROM ban
+                UNION ALL
+                SELECT id, 'approved_by', approved_by FROM ban
+                UNION ALL
+                SELECT id, 'updated_by', updated_by FROM ban
+                UNION ALL
+                SELECT id, 'duplicate_override_by', duplicate_override_by FROM ban
+                UNION ALL
+                SELECT id, 'entity_id', entity_id FROM ban WHERE entity_type='Account'
+            ) AS reference
+            WHERE
+                character_id IS NOT NULL
+                AND NOT EXISTS (SELECT 1 FROM character WHERE character.id=reference.character_id)
+            ORDER BY ban_id, ban_column"
+        )
+        .fetch_all(self.db.as_ref())
+        .await?
+        .into_iter()
+        .map(|row| OrphanedReference {
+            ban_id: row.ban_id,
+            column: row.column,
+            character_id: row.character_id,
+        })
+        .collect())
+    }
+
+    // Re-derives the expiry of every temporary ban from the day it was set to expire on.
+    // Bans revoked by hand are left alone, and so are bans that already ended, moving their
+    // expiry later could bring them back. Returns how many bans changed.
+    pub async fn recompute_expiry(&self) -> Result<u64, Madness> {
+        let now = self.clock.now().timestamp();
+        let mut tx = self.db.begin().await?;
+
+        let bans = sqlx::query!(
+            "SELECT id, revoked_at, expiry_day AS \"expiry_day!\" FROM ban WHERE expiry_day IS NOT NULL AND revoked_by IS NULL AND (revoked_at IS NULL OR revoked_at > $1) FOR UPDATE",
+            now
+        )
+        .fetch_all(&mut tx)
+        .await?;
+
+        let mut updated = 0;
+        for ban in bans {
+            let expires_at = self.expires_at(ban.expiry_day);
+            if ban.revoked_at == Some(expires_at) {
+                continue;
+            }
+
+            sqlx::query!(
+                "UPDATE ban SET revoked_at=$1, updated_at=$3 WHERE id=$2",
+                expires_at,
+                ban.id,
+                now
+            )
+            .execute(&mut tx)
+            .await?;
+            updated += 1;
+        }
+
+        tx.commit().await?;
+
+        Ok(updated)
+    }
+
+    // Bans that ended before the cutoff, what purge would delete
+    pub async fn count_ended_before(&self, cutoff: i64) -> Result<i64, Madness> {
+        Ok(sqlx::query!(
+            "SELECT COUNT(*) AS \"count!\" FROM ban WHERE revoked_at <= $1",
+            cutoff
+        )
+        .fetch_one(self.db.as_ref())
+        .await?
+        .count)
+    }
+
+    // Deletes the bans that ended before the cutoff along with their history and tags. Bans
+    // that superseded them keep existing, without the link. Returns how many were deleted.
+    pub async fn purge(&self, cutoff: i64) -> Result<u64, Madness> {
+        let mut tx = self.db.begin().await?;
+
+        let ban_ids: Vec<i64> = sqlx::query!(
+            "SELECT id FROM ban WHERE revoked_at <= $1 FOR UPDATE",
+            cutoff
+        )
+        .fetch_all(&mut tx)
+        .await?
+        .into_iter()
+        .map(|ban| ban.id)
+        .collect();
+
+        sqlx::query!(
+            "UPDATE ban SET supersedes_ban_id=NULL WHERE supersedes_ban_id = ANY($1)",
+            &ban_ids
+        )
+        .execute(&mut tx)
+        .await?;
+        sqlx::query!(
+            "DELETE FROM ban_reason_history WHERE ban_id = ANY($1)",
+            &ban_ids
+        )
+        .execute(&mut tx)
+        .await?;
+        sqlx::query!(
+            "DELETE FROM ban_issuer_history WHERE ban_id = ANY($1)",
+            &ban_ids
+        )
+        .execute(&mut tx)
+        .await?;
+        sqlx::query!("DELETE FROM ban_tag WHERE ban_id = ANY($1)", &ban_ids)
+            .execute(&mut tx)
+            .await?;
+        let deleted = sqlx::query!("DELETE FROM ban WHERE id = ANY($1)", &ban_ids)
+            .execute(&mut tx)
+            .await?
+            .rows_affected();
+
+        tx.commit().await?;
+        Ok(deleted)
+    }
+
+    // Active bans that expire within the window and haven't been reported at their current
+    // expiry yet. Editing the expiry makes a ban come up again.
+    pub async fn expiring_unnotified(&self, window: i64) -> Result<Vec<ExpiringBan>, Madness> {
+        let now = self.clock.now().timestamp();
+        Ok(sqlx::query!(
+            "SELECT id, entity_id, entity_name, entity_type, revoked_at AS \"revoked_at!\"
+            FROM ban
+            WHERE revoked_at > $1 AND revoked_at <= $2 AND NOT pending_approval
+                AND expiry_notified_for IS DISTINCT FROM revoked_at
+            ORDER BY revoked_at",
+            now,
+            now + window
+        )
+        .fetch_all(self.db.as_ref())
+        .await?
+        .into_iter()
+        .map(|row| ExpiringBan {
+            id: row.id,
+            entity: Entity {
+                id: row.entity_id,
+                name: row.entity_name,
+                category: row.entity_type,
+            },
+            expires_at: row.revoked_at,
+        })
+        .collect())
+    }
+
+    pub async fn mark_expiry_notified(&self, bans: &[ExpiringBan]) -> Result<(), Madness> {
+        let ids: Vec<i64> = bans.iter().map(|ban| ban.id).collect();
+        let expiries: Vec<i64> = bans.iter().map(|ban| ban.expires_at).collect();
+        sqlx::query!(
+            "UPDATE ban SET expiry_notified_for=notified.expires_at
+            FROM UNNEST($1::BIGINT[], $2::BIGINT[]) AS notified (id, expires_at)
+            WHERE ban.id=notified.id",
+            &ids,
+            &expiries
+        )
+        .execute(self.db.as_ref())
+        .await?;
+        Ok(())
+    }
+
+    // Scheduled character bans that have started and haven't been handled at their current
+    // start yet. Moving the start makes a ban come up again.
+    pub async fn started_unnotified(&self) -> Result<Vec<StartedBan>, Madness> {
+        let now = self.clock.now().timestamp();
+        Ok(sqlx::query_as!(
+            StartedBan,
+            "SELECT ban.id, ban.entity_id AS character_id, character_discord.discord_id AS \"discord_id?\",
+                ban.starts_at AS \"starts_at!\"
+            FROM ban LEFT JOIN character_discord ON character_discord.character_id=ban.entity_id
+            WHERE ban.entity_type='Character' AND ban.starts_at <= $1 AND NOT ban.pending_approval
+                AND (ban.revoked_at IS NULL OR ban.revoked_at > $1)
+                AND ban.start_notified_for IS DISTINCT FROM ban.starts_at
+            ORDER BY ban.starts_at",
+            now
+        )
+        .fetch_all(self.db.as_ref())
+        .await?)
+    }
+
+    pub async fn mark_start_notified(&self, bans: &[StartedBan]) -> Result<(), Madness> {
+        let ids: Vec<i64> = bans.iter().map(|ban| ban.id).collect();
+        let starts: Vec<i64> = bans.iter().map(|ban| ban.starts_at).collect();
+        sqlx::query!(
+            "UPDATE ban SET start_notified_for=started.starts_at
+            FROM UNNEST($1::BIGINT[], $2::BIGINT[]) AS started (id, starts_at)
+            WHERE ban.id=started.id",
+            &ids,
+            &starts
+        )
+        .execute(self.db.as_ref())
+        .await?;
+        Ok(())
+    }
+
+    // Checks that ban_id (None for a ban that's yet to be created) can supersede the given ban.
+    // A ban is only superseded once, and following the chain back must never come round to
+    // the same ban again.
+    pub async fn check_supersedes(
+        &self,
+        ban_id: Option<i64>,
+        supersedes_ban_id: i64,
+    ) -> Result<(), Madness> {
+        if ban_id == Some(supersedes_ban_id) {
+            return Err(Madness::UnprocessableEntity(
+                "A ban cannot supersede itself".to_string(),
+            ));
+        }
+
+        if let Some(other) = sqlx::query!(
+            "SELECT id FROM ban WHERE supersedes_ban_id=$1 AND ($2::BIGINT IS NULL OR id <> $2) LIMIT 1",
+            supersedes_ban_id,
+            ban_id
+        )
+        .fetch_optional(self.db.as_ref())
+        .await?
+        {
+            return Err(Madness::UnprocessableEntity(format!(
+                "Ban {} is already superseded by ban {}",
+                supersedes_ban_id, other.id
+            )));
+        }
+
+        let mut seen = BTreeSet::new();
+        let mut next = Some(supersedes_ban_id);
+        while let Some(current) = next {
+            if Some(current) == ban_id || !seen.insert(current) {
+                return Err(Madness::UnprocessableEntity(format!(
+                    "Superseding ban {} would make a circular chain",
+                    supersedes_ban_id
+                )));
+            }
+            next = sqlx::query!("SELECT supersedes_ban_id FROM ban WHERE id=$1", current)
+                .fetch_optional(self.db.as_ref())
+                .await?
+                .and_then(|ban| ban.supersedes_ban_id);
+        }
+        Ok(())
+    }
+
+    pub async fn find(&self, ban_id: i64) -> Result<Option<Ban>, Madness> {
+        let row = match sqlx::query_as::<_, BanRow>(select_bans!("WHERE ban.id=$1"))
+            .bind(ban_id)
+            .fetch_optional(self.db.as_ref())
+            .await?
+        {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+
+        let mut ban = Ban::from(row);
+        ban.reason_history = self.reason_history(ban_id).await?;
+        ban.tags = self.tags(ban_id).await?;
+        Ok(Some(ban))
+    }
+
+    pub async fn tags(&self, ban_id: i64) -> Result<Vec<String>, Madness> {
+        Ok(sqlx::query!(
+            "SELECT tag FROM ban_tag WHERE ban_id=$1 ORDER BY tag",
+            ban_id
+        )
+        .fetch_all(self.db.as_ref())
+        .await?
+        .into_iter()
+        .map(|row| row.tag)
+        .collect())
+    }
+
+    // Loads the tags of many bans in one query
+    async fn with_tags(&self, mut bans: Vec<Ban>) -> Result<Vec<Ban>, Madness> {
+        let ids: Vec<i64> = bans.iter().filter_map(|ban| ban.id).collect();
+        let mut tags: HashMap<i64, Vec<String>> = HashMap::new();
+        for row in sqlx::query!(
+            "SELECT ban_id, tag FROM ban_tag WHERE ban_id = ANY($1) ORDER BY tag",
+            &ids
+        )
+        .fetch_all(self.db.as_ref())
+        .await?
+        {
+            tags.entry(row.ban_id).or_default().push(row.tag);
+        }
+
+        for ban in bans.iter_mut() {
+            if let Some(ban_tags) = ban.id.and_then(|id| tags.remove(&id)) {
+                ban.tags = ban_tags;
+            }
+        }
+        Ok(bans)
+    }
+
+    // Replaces the tags of a ban, they should already be normalized
+    pub async fn set_tags(&self, ban_id: i64, tags: &[String]) -> Result<(), Madness> {
+        let mut tx = self.db.begin().await?;
+        replace_tags(&mut tx, ban_id, tags).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // The given IDs that are bans
+    async fn existing(&self, ban_ids: &[i64]) -> Result<BTreeSet<i64>, Madness> {
+        Ok(
+            sqlx::query!("SELECT id FROM ban WHERE id = ANY($1)", ban_ids)
+                .fetch_all(self.db.as_ref())
+                .await?
+                .into_iter()
+                .map(|row| row.id)
+                .collect(),
+        )
+    }
+
+    // Adds a normalized tag to many bans at once. IDs that aren't bans and bans that already
+    // have as many tags as they can fail, bans that already had the tag succeed.
+    pub async fn add_tag(&self, ban_ids: &[i64], tag: &str) -> Result<BulkResult<i64>, Madness> {
+        let existing = self.existing(ban_ids).await?;
+        let full: BTreeSet<i64> = sqlx::query!(
+            "SELECT ban_id FROM ban_tag WHERE ban_id = ANY($1)
+            GROUP BY ban_id HAVING COUNT(*) >= $2 AND NOT bool_or(tag=$3)",
+            ban_ids,
+            TAG_MAX_COUNT as i64,
+            tag
+        )
+        .fetch_all(self.db.as_ref())
+        .await?
+        .into_iter()
+        .map(|row| row.ban_id)
+        .collect();
+
+        let mut result = BulkResult::default();
+        for ban_id in unique_ids(ban_ids) {
+            if !existing.contains(&ban_id) {
+                result.fail(ban_id, "Ban not found");
+            } else if full.contains(&ban_id) {
+                result.fail(
+                    ban_id,
+                    format!("The ban already has {} tags", TAG_MAX_COUNT),
+                );
+            } else {
+                result.succeed(ban_id);
+            }
+        }
+
+        sqlx::query!(
+            "INSERT INTO ban_tag (ban_id, tag) SELECT UNNEST($1::BIGINT[]), $2
+            ON CONFLICT DO NOTHING",
+            &result.succeeded,
+            tag
+        )
+        .execute(self.db.as_ref())
+        .await?;
+        Ok(result)
+    }
+
+    // Only IDs that aren't bans fail, bans without the tag succeed
+    pub async fn remove_tag(&self, ban_ids: &[i64], tag: &str) -> Result<BulkResult<i64>, Madness> {
+        let existing = self.existing(ban_ids).await?;
+        let mut result = BulkResult::default();
+        for ban_id in unique_ids(ban_ids) {
+            match existing.contains(&ban_id) {
+                true => result.succeed(ban_id),
+                false => result.fail(ban_id, "Ban not found"),
+            }
+        }
+
+        sqlx::query!(
+            "DELETE FROM ban_tag WHERE ban_id = ANY($1) AND tag=$2",
+            &result.succeeded,
+            tag
+        )
+        .execute(self.db.as_ref())
+        .await?;
+        Ok(result)
+    }
+
+    // Earlier versions of a ban's reason, oldest first
+    pub async fn reason_history(&self, ban_id: i64) -> Result<Vec<ReasonEdit>, Madness> {
+        Ok(sqlx::query!(
+            "SELECT
+                reason,
+                edited_at,
+                editor.id AS \"editor_id\",
+                editor.name AS \"editor_name\"
+            FROM
+                ban_reason_history
+            JOIN
+                character as editor ON edited_by=editor.id
+            WHERE
+                ban_id=$1
+            ORDER BY
+                edited_at, ban_reason_history.id",
+            ban_id
+        )
+        .fetch_all(self.db.as_ref())
+        .await?
+        .into_iter()
+        .map(|row| ReasonEdit {
+            reason: row.reason,
+            edited_at: row.edited_at,
+            edited_by: Character {
+                id: row.editor_id,
+                name: row.editor_name,
+                corporation_id: None,
+            },
+        })
+        .collect())
+    }
+
+    pub async fn all_bans(
+        &self,
+        entity_id: i64,
+        entity_type: &str,
+    ) -> Result<Option<Vec<Ban>>, Madness> {
+        let rows: Vec<BanRow> = sqlx::query_as(select_bans!(
+            "WHERE
+                entity_id=$1 AND entity_type=$2
+            ORDER BY
+                issued_at"
+        ))
+        .bind(entity_id)
+        .bind(entity_type)
+        .fetch_all(self.db.as_ref())
+        .await?;
+
+        if rows.len() == 0 {
+            return Ok(None);
+        }
+
+        return Ok(Some(
+            self.with_tags(rows.into_iter().map(Ban::from).collect())
+                .await?,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{
+        auto_tags, compute_expiry, next_downtime, normalize_tags, parse_reason_tag, unique_ids,
+        ActiveFilter, BanCursor, BanService, OrphanedReference,
+    };
+    use crate::config::AutoTagRule;
+    use crate::util::{
+        clock::{FixedClock, SystemClock},
+        testdb::TestDatabase,
+        types::{Ban, Entity},
+    };
+
+    async fn columns(db: &TestDatabase) -> Vec<(String, String, String, String)> {
+        sqlx::query_as(
+            "SELECT table_name::TEXT, column_name::TEXT, data_type::TEXT, is_nullable::TEXT
+            FROM information_schema.columns
+            WHERE table_schema = 'public'
+            ORDER BY table_name, column_name",
+        )
+        .fetch_all(db.pool())
+        .await
+        .unwrap()
+    }
+
+    async fn indexes(db: &TestDatabase) -> Vec<(String, String)> {
+        sqlx::query_as(
+            "SELECT indexname::TEXT, indexdef::TEXT FROM pg_indexes
+            WHERE schemaname = 'public'
+            ORDER BY indexname",
+        )
+        .fetch_all(db.pool())
+        .await
+        .unwrap()
+    }
+
+    async fn plan(conn: &mut sqlx::PgConnection, query: &str) -> String {
+        let rows: Vec<(String,)> = sqlx::query_as(&format!("EXPLAIN {}", query))
+            .fetch_all(conn)
+            .await
+            .unwrap();
+        rows.into_iter()
+            .map(|(line,)| line)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn character_ban(character_id: i64, issued_at: i64) -> Ban {
+        Ban {
+            id: None,
+            entity: Some(Entity {
+                id: character_id,
+                name: None,
+                category: "Character".to_string(),
+            }),
+            issued_at: Some(issued_at),
+            issued_by: None,
+            on_behalf_of: None,
+            public_reason: None,
+            reason: "Reason".to_string(),
+            category: None,
+            revoked_at: None,
+            revoked_by: None,
+            silent: false,
+            pending_approval: false,
+            name_pending: false,
+            esi_category: None,
+            acknowledged_at: None,
+            supersedes_ban_id: None,
+            fc_note: None,
+            starts_at: None,
+            duplicate_override_by: None,
+            reason_history: Vec::new(),
+            tags: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_next_downtime() {
+        // 2021-06-01T00:00:00Z, downtime is at 11:00
+        let midnight = 1622505600;
+        let downtime = midnight + 11 * 3600;
+        assert_eq!(next_downtime(midnight), downtime);
+        assert_eq!(next_downtime(downtime - 1), downtime);
+        assert_eq!(next_downtime(downtime), downtime);
+        assert_eq!(next_downtime(downtime + 1), downtime + 86400);
+        assert_eq!(next_downtime(midnight + 86400 - 1), downtime + 86400);
+
+        // Off by default, the expiry is just moved by the offset
+        assert_eq!(compute_expiry(downtime + 1), downtime + 1 + 11 * 3600);
+    }
+
+    #[test]
+    fn test_ban_cursor() {
+        let cursor = BanCursor {
+            issued_at: 1600000000,
+            id: 42,
+        };
+        assert_eq!(BanCursor::decode(&cursor.encode()), Some(cursor));
+        assert_eq!(BanCursor::decode("not a cursor"), None);
+        assert_eq!(BanCursor::decode(&hex::encode("1600000000")), None);
+    }
+
+    #[rocket::async_test]
+    async fn test_cursor_pages_are_stable() {
+        let db = match TestDatabase::fresh().await {
+            Some(db) => db,
+            None => return,
+        };
+        sqlx::query("INSERT INTO character (id, name) VALUES (1000, 'FC')")
+            .execute(db.pool())
+            .await
+            .unwrap();
+        let service = BanService::new(Arc::new(db.pool().clone()), Arc::new(SystemClock));
+
+        // Two bans share a timestamp, so the ID has to break the tie
+        let mut expected = Vec::new();
+        for (character_id, issued_at) in [(1, 100), (2, 200), (3, 200), (4, 300), (5, 400)] {
+            expected.push(
+                service
+                    .insert(&character_ban(character_id, issued_at), 1000, None)
+                    .await
+                    .unwrap(),
+            );
+        }
+        expected.sort_by_key(|id| std::cmp::Reverse(*id));
+
+        let mut seen = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = service
+                .active_page(2, 0, cursor, false, &ActiveFilter::default())
+                .await
+                .unwrap();
+            seen.extend(page.iter().filter_map(|ban| ban.id));
+            cursor = match page.last() {
+                Some(last) if page.len() == 2 => BanCursor::after(last),
+                _ => break,
+            };
+
+            // Bans issued in the middle of the scan are newer than the cursor
+            service
+                .insert(&character_ban(6, 1000 + seen.len() as i64), 1000, None)
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(seen, expected);
+
+        db.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_supersedes_chains() {
+        let db = match TestDatabase::fresh().await {
+            Some(db) => db,
+            None => return,
+        };
+        sqlx::query("INSERT INTO character (id, name) VALUES (1000, 'FC')")
+            .execute(db.pool())
+            .await
+            .unwrap();
+        let service = BanService::new(Arc::new(db.pool().clone()), Arc::new(SystemClock));
+
+        let first = service
+            .insert(&character_ban(1, 100), 1000, None)
+            .await
+            .unwrap();
+        assert!(service.check_supersedes(None, first).await.is_ok());
+        let second = service
+            .insert(
+                &Ban {
+                    supersedes_ban_id: Some(first),
+                    ..character_ban(1, 200)
+                },
+                1000,
+                None,
+            )
+            .await
+            .unwrap();
+
+        // Already corrected once
+        assert!(service.check_supersedes(None, first).await.is_err());
+        assert!(service.check_supersedes(None, second).await.is_ok());
+        assert!(service
+            .check_supersedes(Some(second), second)
+            .await
+            .is_err());
+        // first superseding second would point them at each other
+        assert!(service.check_supersedes(Some(first), second).await.is_err());
+
+        // A cycle that's already in the data, say from a bad import, isn't followed forever
+        sqlx::query("UPDATE ban SET supersedes_ban_id=$1 WHERE id=$2")
+            .bind(second)
+            .bind(first)
+            .execute(db.pool())
+            .await
+            .unwrap();
+        let third = service
+            .insert(
+                &Ban {
+                    supersedes_ban_id: Some(first),
+                    ..character_ban(1, 300)
+                },
+                1000,
+                None,
+            )
+            .await
+            .unwrap();
+        assert!(service.check_supersedes(None, third).await.is_err());
+
+        db.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_orphaned_references() {
+        let db = match TestDatabase::fresh().await {
+            Some(db) => db,
+            None => return,
+        };
+        sqlx::query("INSERT INTO character (id, name) VALUES (1000, 'FC')")
+            .execute(db.pool())
+            .await
+            .unwrap();
+        let service = BanService::new(Arc::new(db.pool().clone()), Arc::new(SystemClock));
+
+        let fine = service
+            .insert(&character_ban(1, 100), 1000, None)
+            .await
+            .unwrap();
+        let mut account_ban = character_ban(2, 100);
+        account_ban.entity.as_mut().unwrap().category = "Account".to_string();
+        let unknown_account = service.insert(&account_ban, 1000, None).await.unwrap();
+
+        // Like a copy of the data made without the foreign keys
+        sqlx::query("ALTER TABLE ban DROP CONSTRAINT revoked_by")
+            .execute(db.pool())
+            .await
+            .unwrap();
+        sqlx::query("UPDATE ban SET revoked_at=200, revoked_by=3 WHERE id=$1")
+            .bind(fine)
+            .execute(db.pool())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            service.orphaned_references().await.unwrap(),
+            vec![
+                OrphanedReference {
+                    ban_id: fine,
+                    column: "revoked_by".to_string(),
+                    character_id: 3,
+                },
+                OrphanedReference {
+                    ban_id: unknown_account,
+                    column: "entity_id".to_string(),
+                    character_id: 2,
+                },
+            ]
+        );
+
+        // The history still loads, just without the missing revoker
+        let history = service.all_bans(1, "Character").await.unwrap().unwrap();
+        assert_eq!(history[0].revoked_at, Some(200));
+        assert!(history[0].revoked_by.is_none());
+
+        db.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_expiring_bans_are_reported_once() {
+        let db = match TestDatabase::fresh().await {
+            Some(db) => db,
+            None => return,
+        };
+        sqlx::query("INSERT INTO character (id, name) VALUES (1000, 'FC')")
+            .execute(db.pool())
+            .await
+            .unwrap();
+        let pool = Arc::new(db.pool().clone());
+        let service = BanService::new(pool.clone(), Arc::new(FixedClock::at(1000000)));
+
+        let soon = service
+            .insert(&character_ban(1, 100), 1000, None)
+            .await
+            .unwrap();
+        let later = service
+            .insert(&character_ban(2, 100), 1000, None)
+            .await
+            .unwrap();
+        service
+            .insert(&character_ban(3, 100), 1000, None)
+            .await
+            .unwrap();
+        for (ban_id, revoked_at) in [(soon, 1000000i64 + 3600), (later, 1000000i64 + 86400 * 7)] {
+            sqlx::query("UPDATE ban SET revoked_at=$1 WHERE id=$2")
+                .bind(revoked_at)
+                .bind(ban_id)
+                .execute(db.pool())
+                .await
+                .unwrap();
+        }
+
+        let expiring = service.expiring_unnotified(86400).await.unwrap();
+        assert_eq!(
+            expiring.iter().map(|ban| ban.id).collect::<Vec<_>>(),
+            [soon]
+        );
+        assert_eq!(expiring[0].expires_at, 1000000 + 3600);
+
+        service.mark_expiry_notified(&expiring).await.unwrap();
+        assert!(service.expiring_unnotified(86400).await.unwrap().is_empty());
+
+        // A new expiry is reported again
+        sqlx::query("UPDATE ban SET revoked_at=$1 WHERE id=$2")
+            .bind(1000000i64 + 7200)
+            .bind(soon)
+            .execute(db.pool())
+            .await
+            .unwrap();
+        assert_eq!(service.expiring_unnotified(86400).await.unwrap().len(), 1);
+
+        db.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_started_unnotified() {
+        let db = match TestDatabase::fresh().await {
+            Some(db) => db,
+            None => return,
+        };
+        sqlx::query("INSERT INTO character (id, name) VALUES (1000, 'FC'), (1, 'Pilot')")
+            .execute(db.pool())
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO character_discord (character_id, discord_id) VALUES (1, '42')")
+            .execute(db.pool())
+            .await
+            .unwrap();
+        let pool = Arc::new(db.pool().clone());
+        let service = BanService::new(pool.clone(), Arc::new(FixedClock::at(1000000)));
+
+        let started = service
+            .insert(&character_ban(1, 100), 1000, None)
+            .await
+            .unwrap();
+        let upcoming = service
+            .insert(&character_ban(2, 100), 1000, None)
+            .await
+            .unwrap();
+        service
+            .insert(&character_ban(3, 100), 1000, None)
+            .await
+            .unwrap();
+        for (ban_id, starts_at) in [(started, 1000000i64 - 60), (upcoming, 1000000i64 + 3600)] {
+            sqlx::query("UPDATE ban SET starts_at=$1 WHERE id=$2")
+                .bind(starts_at)
+                .bind(ban_id)
+                .execute(db.pool())
+                .await
+                .unwrap();
+        }
+
+        let unnotified = service.started_unnotified().await.unwrap();
+        assert_eq!(
+            unnotified.iter().map(|ban| ban.id).collect::<Vec<_>>(),
+            [started]
+        );
+        assert_eq!(unnotified[0].character_id, 1);
+        assert_eq!(unnotified[0].discord_id.as_deref(), Some("42"));
+
+        service.mark_start_notified(&unnotified).await.unwrap();
+        assert!(service.started_unnotified().await.unwrap().is_empty());
+
+        // Once the other one starts it comes up
+        let later = BanService::new(pool, Arc::new(FixedClock::at(1000000 + 3600)));
+        assert_eq!(
+            later
+                .started_unnotified()
+                .await
+                .unwrap()
+                .iter()
+                .map(|ban| ban.id)
+                .collect::<Vec<_>>(),
+            [upcoming]
+        );
+
+        db.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_expiring_within() {
+        let db = match TestDatabase::fresh().await {
+            Some(db) => db,
+            None => return,
+        };
+        sqlx::query("INSERT INTO character (id, name) VALUES (1000, 'FC')")
+            .execute(db.pool())
+            .await
+            .unwrap();
+        let now = 1000000;
+        let service = BanService::new(Arc::new(db.pool().clone()), Arc::new(FixedClock::at(now)));
+
+        // Permanent, right at the edge of the window, just past it, and already expired
+        let mut ids = Vec::new();
+        for (character_id, revoked_at) in [
+            (1, None),
+            (2, Some(now + 3600)),
+            (3, Some(now + 3601)),
+            (4, Some(now - 1)),
+        ]
+        .iter()
+        {
+            let id = service
+                .insert(&character_ban(*character_id, now - 100), 1000, None)
+                .await
+                .unwrap();
+            sqlx::query("UPDATE ban SET revoked_at=$1 WHERE id=$2")
+                .bind(*revoked_at)
+                .bind(id)
+                .execute(db.pool())
+                .await
+                .unwrap();
+            ids.push(id);
+        }
+
+        let ids_of = |bans: Vec<Ban>| bans.iter().filter_map(|ban| ban.id).collect::<Vec<_>>();
+        let expiring = |window| ActiveFilter {
+            expiring_within: Some(window),
+            ..Default::default()
+        };
+        let edge = vec![ids[1]];
+        assert_eq!(
+            ids_of(service.all_active(&expiring(3600)).await.unwrap()),
+            edge
+        );
+        assert_eq!(
+            ids_of(
+                service
+                    .active_page(10, 0, None, false, &expiring(3600))
+                    .await
+                    .unwrap()
+            ),
+            edge
+        );
+        assert_eq!(service.count_active(&expiring(3600)).await.unwrap(), 1);
+        assert_eq!(service.count_active(&expiring(3601)).await.unwrap(), 2);
+        assert_eq!(
+            service
+                .count_active(&ActiveFilter::default())
+                .await
+                .unwrap(),
+            3
+        );
+
+        db.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_expiry_follows_the_clock() {
+        let db = match TestDatabase::legacy().await {
+            Some(db) => db,
+            None => return,
+        };
+        db.execute_file("./sql/testing/legacy_bans.sql").await;
+        db.migrate().await;
+        let pool = Arc::new(db.pool().clone());
+
+        // Ban 2 expires at 4000000000
+        let before = BanService::new(pool.clone(), Arc::new(FixedClock::at(3999999999)));
+        let after = BanService::new(pool.clone(), Arc::new(FixedClock::at(4000000001)));
+
+        let active = |bans: Vec<Ban>| bans.iter().filter_map(|ban| ban.id).collect::<Vec<_>>();
+        assert!(active(before.all_active(&ActiveFilter::default()).await.unwrap()).contains(&2));
+        assert!(!active(after.all_active(&ActiveFilter::default()).await.unwrap()).contains(&2));
+
+        let expired = after.revoke(2, 1000).await.unwrap_err();
+        assert_eq!(
+            expired.to_string(),
+            "Cannot revoke the ban as it has already expired"
+        );
+
+        before.revoke(2, 1000).await.unwrap();
+        let revoked = before.find(2).await.unwrap().unwrap();
+        assert_eq!(revoked.revoked_at, Some(3999999999));
+
+        db.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_recompute_expiry_leaves_ended_bans() {
+        let db = match TestDatabase::fresh().await {
+            Some(db) => db,
+            None => return,
+        };
+        sqlx::query("INSERT INTO character (id, name) VALUES (1000, 'FC')")
+            .execute(db.pool())
+            .await
+            .unwrap();
+        let pool = Arc::new(db.pool().clone());
+        // 2021-06-01T00:00:00Z, the bans are set to expire at noon
+        let midnight = 1622505600;
+        let service = BanService::new(pool.clone(), Arc::new(FixedClock::at(midnight)));
+
+        let mut ended = character_ban(1, midnight);
+        ended.revoked_at = Some(midnight + 12 * 3600);
+        let ended = service.insert(&ended, 1000, None).await.unwrap();
+        let mut active = character_ban(2, midnight);
+        active.revoked_at = Some(midnight + 2 * 86400 + 12 * 3600);
+        let active = service.insert(&active, 1000, None).await.unwrap();
+
+        // Snapping moves both expiries from 23:00 to the next day's downtime, the first ban
+        // ended in between
+        let snapped = BanService::new(pool, Arc::new(FixedClock::at(midnight + 86400)))
+            .snap_to_downtime(true);
+        assert_eq!(snapped.recompute_expiry().await.unwrap(), 1);
+        let ban = snapped.find(ended).await.unwrap().unwrap();
+        assert_eq!(ban.revoked_at, Some(midnight + 23 * 3600));
+        let ban = snapped.find(active).await.unwrap().unwrap();
+        assert_eq!(ban.revoked_at, Some(midnight + 3 * 86400 + 11 * 3600));
+
+        db.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_dissolved_bans_stay_revoked() {
+        let db = match TestDatabase::fresh().await {
+            Some(db) => db,
+            None => return,
+        };
+        sqlx::query("INSERT INTO character (id, name) VALUES (1000, 'FC')")
+            .execute(db.pool())
+            .await
+            .unwrap();
+        let now = 1600000000;
+        let service = BanService::new(Arc::new(db.pool().clone()), Arc::new(FixedClock::at(now)));
+
+        let mut ban = character_ban(98000001, now);
+        ban.entity.as_mut().unwrap().category = "Corporation".to_string();
+        ban.revoked_at = Some(now + 30 * 86400);
+        let ban_id = service.insert(&ban, 1000, None).await.unwrap();
+
+        assert!(service.revoke_dissolved(ban_id).await.unwrap());
+        assert_eq!(service.recompute_expiry().await.unwrap(), 0);
+        let ban = service.find(ban_id).await.unwrap().unwrap();
+        assert_eq!(ban.revoked_at, Some(now));
+        assert!(ban.revoked_by.is_none());
+
+        db.destroy().await;
+    }
+
+    #[test]
+    fn test_parse_reason_tag() {
+        assert_eq!(
+            parse_reason_tag("[RMT] Selling ISK"),
+            Some("RMT".to_string())
+        );
+        assert_eq!(parse_reason_tag("  [ Scam ]"), Some("Scam".to_string()));
+        assert_eq!(parse_reason_tag("Scammed a pilot [Scam]"), None);
+        assert_eq!(parse_reason_tag("[] Empty"), None);
+        assert_eq!(parse_reason_tag("[Unclosed"), None);
+        assert_eq!(parse_reason_tag(&format!("[{}]", "x".repeat(33))), None);
+    }
+
+    #[test]
+    fn test_normalize_tags() {
+        let tags = |tags: &[&str]| -> Vec<String> { tags.iter().map(|t| t.to_string()).collect() };
+
+        assert_eq!(
+            normalize_tags(&tags(&["AWOX", " api-report ", "awox", ""])),
+            Ok(tags(&["api-report", "awox"]))
+        );
+        assert_eq!(normalize_tags(&[]), Ok(Vec::new()));
+        assert!(normalize_tags(&tags(&[&"x".repeat(33)])).is_err());
+        let too_many: Vec<String> = (0..11).map(|i| format!("tag{}", i)).collect();
+        assert!(normalize_tags(&too_many).is_err());
+    }
+
+    #[test]
+    fn test_unique_ids() {
+        assert_eq!(unique_ids(&[3, 1, 3, 2, 1]), vec![3, 1, 2]);
+        assert_eq!(unique_ids(&[]), Vec::<i64>::new());
+    }
+
+    #[test]
+    fn test_auto_tags() {
+        let rules = vec![
+            AutoTagRule {
+                tag: "permanent-review".to_string(),
+                categories: vec!["rmt".to_string()],
+                issuer_roles: Vec::new(),
+                permanent: Some(true),
+            },
+            AutoTagRule {
+                tag: "leadership".to_string(),
+                categories: Vec::new(),
+                issuer_roles: vec!["Leadership".to_string()],
+                permanent: None,
+            },
+        ];
+
+        let mut ban = character_ban(1, 1600000000);
+        ban.category = Some("RMT".to_string());
+        assert_eq!(
+            auto_tags(&rules, &ban, Some("FC")),
+            vec!["permanent-review"]
+        );
+        assert_eq!(
+            auto_tags(&rules, &ban, Some("Leadership")),
+            vec!["permanent-review", "leadership"]
+        );
+
+        ban.revoked_at = Some(1700000000);
+        assert!(auto_tags(&rules, &ban, None).is_empty());
+    }
+
+    #[rocket::async_test]
+    async fn test_migrations_match_schema() {
+        let fresh = match TestDatabase::fresh().await {
+            Some(db) => db,
+            None => return,
+        };
+        let migrated = TestDatabase::legacy().await.unwrap();
+        migrated.migrate().await;
+
+        assert_eq!(columns(&fresh).await, columns(&migrated).await);
+        assert_eq!(indexes(&fresh).await, indexes(&migrated).await);
+
+        fresh.destroy().await;
+        migrated.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_ban_lookups_use_indexes() {
+        let db = match TestDatabase::fresh().await {
+            Some(db) => db,
+            None => return,
+        };
+        // The table is empty, so the planner has to be talked out of scanning it. The setting
+        // only applies to this connection.
+        let mut conn = db.pool().acquire().await.unwrap();
+        sqlx::query("SET enable_seqscan = off")
+            .execute(&mut conn)
+            .await
+            .unwrap();
+
+        for (filter, index) in [
+            ("entity_id = ANY('{1,2}')", "ban_entity"),
+            ("entity_id=1 AND entity_type='Character'", "ban_entity"),
+            ("issued_by=1", "ban_issued_by"),
+            ("revoked_at > 1600000000", "ban_revoked_at"),
+        ]
+        .iter()
+        {
+            let plan = plan(&mut conn, &format!("SELECT id FROM ban WHERE {}", filter)).await;
+            assert!(
+                plan.contains(index),
+                "{} doesn't use {}:\n{}",
+                filter,
+                index,
+                plan
+            );
+        }
+
+        drop(conn);
+        db.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_legacy_bans_survive_migrations() {
+        let db = match TestDatabase::legacy().await {
+            Some(db) => db,
+            None => return,
+        };
+
+        // Rows as the code before the migrations wrote them
+        db.execute_file("./sql/testing/legacy_bans.sql").await;
+        db.migrate().await;
+
+        let service = BanService::new(Arc::new(db.pool().clone()), Arc::new(SystemClock));
+
+        let active = service.all_active(&ActiveFilter::default()).await.unwrap();
+        let mut active_ids: Vec<i64> = active.iter().filter_map(|ban| ban.id).collect();
+        active_ids.sort();
+        assert_eq!(active_ids, vec![1, 2]);
+        assert!(active
+            .iter()
+            .all(|ban| !ban.silent && ban.on_behalf_of.is_none()));
+
+        let revoked = service.find(3).await.unwrap().expect("Ban 3 should exist");
+        assert_eq!(revoked.revoked_by.unwrap().name, "Legacy FC");
+
+        let history = service.all_bans(1001, "Character").await.unwrap().unwrap();
+        assert_eq!(history.len(), 2);
+
+        let ban_id = service
+            .insert(
+                &Ban {
+                    id: None,
+                    entity: Some(Entity {
+                        id: 2001,
+                        name: Some("Legacy Corp".to_string()),
+                        category: "Corporation".to_string(),
+                    }),
+                    issued_at: None,
+                    issued_by: None,
+                    on_behalf_of: None,
+                    public_reason: Some("Public".to_string()),
+                    reason: "Internal".to_string(),
+                    category: None,
+                    revoked_at: None,
+                    revoked_by: None,
+                    silent: true,
+                    pending_approval: false,
+                    name_pending: false,
+                    esi_category: None,
+                    acknowledged_at: None,
+                    supersedes_ban_id: None,
+                    fc_note: None,
+                    starts_at: None,
+                    duplicate_override_by: None,
+                    reason_history: Vec::new(),
+                    tags: Vec::new(),
+                },
+                1000,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let created = service.find(ban_id).await.unwrap().unwrap();
+        assert!(created.silent);
+        assert_eq!(created.issued_by.unwrap().id, 1000);
+        assert_eq!(
+            service
+                .all_active(&ActiveFilter::default())
+                .await
+                .unwrap()
+                .len(),
+            3
+        );
 
-        return Ok(Some(bans));
+        db.destroy().await;
     }
 }
@@ -0,0 +1,203 @@
+use serde::Deserialize;
+
+use crate::core::{
+    ban::{ActiveFilter, BanService},
+    esi::{ESIClient, EsiLookup},
+};
+use crate::util::{clock::SystemClock, types::EntityType};
+use crate::{config::Config, util::madness::Madness};
+use std::sync::Arc;
+
+// Corporations don't close often, so once every few hours is plenty
+const SWEEP_INTERVAL: u64 = 60 * 60 * 6;
+
+#[derive(Debug, Deserialize)]
+struct CorporationResponse {
+    date_dissolved: Option<String>,
+}
+
+// Revokes the bans of corporations that have closed. ESI either reports a date_dissolved for
+// them or stops knowing about them altogether.
+pub struct DissolutionSweep {
+    esi_client: Arc<dyn EsiLookup>,
+    ban_service: BanService,
+}
+
+impl DissolutionSweep {
+    pub fn new(db: Arc<crate::DB>, config: Config) -> DissolutionSweep {
+        let esi_client = ESIClient::new(
+            db.clone(),
+            config.esi.client_id.clone(),
+            config.esi.client_secret.clone(),
+        );
+        Self::with_esi(
+            Arc::new(esi_client),
+            BanService::new(db, Arc::new(SystemClock)),
+        )
+    }
+
+    pub fn with_esi(esi_client: Arc<dyn EsiLookup>, ban_service: BanService) -> DissolutionSweep {
+        DissolutionSweep {
+            esi_client,
+            ban_service,
+        }
+    }
+
+    pub fn start(self) {
+        tokio::spawn(async move {
+            self.run().await;
+        });
+    }
+
+    async fn run(self) {
+        let interval = tokio::time::Duration::from_secs(SWEEP_INTERVAL);
+        loop {
+            if let Err(e) = self.run_once().await {
+                error!("Error in corporation dissolution sweep: {:#?}", e);
+            };
+
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    // Returns the IDs of the bans that were revoked
+    pub async fn run_once(&self) -> Result<Vec<i64>, Madness> {
+        let mut revoked = Vec::new();
+        for ban in self
+            .ban_service
+            .all_active(&ActiveFilter::default())
+            .await?
+        {
+            let (ban_id, entity) = match (ban.id, ban.entity) {
+                (Some(ban_id), Some(entity)) if entity.category == "Corporation" => {
+                    (ban_id, entity)
+                }
+                _ => continue,
+            };
+
+            let path = EntityType::Corporation.esi_path(entity.id);
+            let dissolved = match self.esi_client.get_unauthenticated(&path).await {
+                Ok(CorporationResponse { date_dissolved }) => date_dissolved,
+                Err(e) if e.is_not_found() => Some("unknown".to_string()),
+                // Try again next time
+                Err(e) if e.is_unavailable() => return Ok(revoked),
+                Err(e) => {
+                    warn!(
+                        "Unable to look up banned corporation {}: {:#?}",
+                        entity.id, e
+                    );
+                    continue;
+                }
+            };
+
+            if let Some(date) = dissolved {
+                if self.ban_service.revoke_dissolved(ban_id).await? {
+                    info!(
+                        "Revoked ban {} as corporation {} was dissolved ({})",
+                        ban_id, entity.id, date
+                    );
+                    revoked.push(ban_id);
+                }
+            }
+        }
+
+        Ok(revoked)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::DissolutionSweep;
+    use crate::core::ban::{BanService, DISSOLVED_TAG};
+    use crate::util::{
+        clock::SystemClock,
+        testapp::FakeEsi,
+        testdb::TestDatabase,
+        types::{Ban, Entity},
+    };
+
+    fn corporation_ban(corporation_id: i64) -> Ban {
+        Ban {
+            id: None,
+            entity: Some(Entity {
+                id: corporation_id,
+                name: None,
+                category: "Corporation".to_string(),
+            }),
+            issued_at: Some(1600000000),
+            issued_by: None,
+            on_behalf_of: None,
+            public_reason: None,
+            reason: "Reason".to_string(),
+            category: None,
+            revoked_at: None,
+            revoked_by: None,
+            silent: false,
+            pending_approval: false,
+            name_pending: false,
+            esi_category: None,
+            acknowledged_at: None,
+            supersedes_ban_id: None,
+            fc_note: None,
+            starts_at: None,
+            duplicate_override_by: None,
+            reason_history: Vec::new(),
+            tags: Vec::new(),
+        }
+    }
+
+    #[rocket::async_test]
+    async fn test_revokes_dissolved_corporations() {
+        let db = match TestDatabase::fresh().await {
+            Some(db) => db,
+            None => return,
+        };
+        sqlx::query("INSERT INTO character (id, name) VALUES (1000, 'FC')")
+            .execute(db.pool())
+            .await
+            .unwrap();
+        let service = BanService::new(Arc::new(db.pool().clone()), Arc::new(SystemClock));
+
+        let open = service
+            .insert(&corporation_ban(98000001), 1000, None)
+            .await
+            .unwrap();
+        let dissolved = service
+            .insert(&corporation_ban(98000002), 1000, None)
+            .await
+            .unwrap();
+        // ESI doesn't know this one at all
+        let gone = service
+            .insert(&corporation_ban(98000003), 1000, None)
+            .await
+            .unwrap();
+
+        let esi = FakeEsi::new(&[
+            (98000001, "Open Corp", "Corporation"),
+            (98000002, "Closed Corp", "Corporation"),
+        ])
+        .with_dissolved(98000002, "2021-06-01T00:00:00Z");
+        let sweep = DissolutionSweep::with_esi(
+            Arc::new(esi),
+            BanService::new(Arc::new(db.pool().clone()), Arc::new(SystemClock)),
+        );
+
+        let mut revoked = sweep.run_once().await.unwrap();
+        revoked.sort();
+        assert_eq!(revoked, vec![dissolved, gone]);
+        assert!(sweep.run_once().await.unwrap().is_empty());
+
+        let ban = service.find(open).await.unwrap().unwrap();
+        assert!(ban.revoked_at.is_none());
+        for ban_id in [dissolved, gone].iter() {
+            let ban = service.find(*ban_id).await.unwrap().unwrap();
+            assert!(ban.revoked_at.is_some());
+            assert!(ban.revoked_by.is_none());
+            assert_eq!(ban.tags, vec![DISSOLVED_TAG.to_string()]);
+        }
+
+        db.destroy().await;
+    }
+}
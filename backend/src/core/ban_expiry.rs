@@ -0,0 +1,117 @@
+use crate::core::{
+    ban::BanService,
+    discord_roles::RoleRemovalClient,
+    sse::{Event, SSEClient},
+};
+use crate::util::clock::{Clock, SystemClock};
+use crate::{config::Config, util::madness::Madness};
+use std::sync::Arc;
+
+// Tells the bans SSE topic about bans that are about to expire, so they can be reviewed for
+// renewal. Each ban is only reported once per expiry. Scheduled bans get their Discord roles
+// removed here once they start, as there's no request to do it in at that point.
+pub struct BanExpiryNotifier {
+    ban_service: BanService,
+    clock: Arc<dyn Clock>,
+    sse_client: SSEClient,
+    role_removal_client: RoleRemovalClient,
+    notify_expiring: bool,
+    remove_roles: bool,
+    window: i64,
+}
+
+impl BanExpiryNotifier {
+    pub fn new(db: Arc<crate::DB>, config: Config) -> BanExpiryNotifier {
+        let role_removal_client = RoleRemovalClient::new(
+            config.discord.role_removal_url.clone(),
+            config.discord.role_removal_secret.clone(),
+        )
+        .expect("Invalid discord.role_removal_url");
+        let clock: Arc<dyn Clock> = Arc::new(SystemClock);
+        BanExpiryNotifier {
+            ban_service: BanService::new(db, clock.clone()),
+            clock,
+            sse_client: SSEClient::new(
+                config.sse.url.clone(),
+                &hex::decode(&config.sse.secret).unwrap(),
+            ),
+            remove_roles: role_removal_client.is_enabled()
+                && config.feature_enabled("discord_role_removal"),
+            role_removal_client,
+            notify_expiring: config.bans.notify_expiring,
+            window: config.bans.expiring_window * 60 * 60,
+        }
+    }
+
+    pub fn start(self) {
+        tokio::spawn(async move {
+            self.run().await;
+        });
+    }
+
+    async fn run(self) {
+        loop {
+            if let Err(e) = self.run_once().await {
+                error!("Error in ban expiry notifier: {:#?}", e);
+            };
+
+            tokio::time::sleep(tokio::time::Duration::from_secs(60)).await;
+        }
+    }
+
+    async fn run_once(&self) -> Result<(), Madness> {
+        if self.remove_roles {
+            self.remove_started_roles().await?;
+        }
+        if !self.notify_expiring {
+            return Ok(());
+        }
+
+        let expiring = self.ban_service.expiring_unnotified(self.window).await?;
+        if expiring.is_empty() {
+            return Ok(());
+        }
+
+        let events = expiring
+            .iter()
+            .map(|ban| Event::new_json("bans", "ban_expiring", ban))
+            .collect();
+        // Only marked once sent, so a failed submission is retried on the next run
+        self.sse_client.submit(events).await?;
+        self.ban_service.mark_expiry_notified(&expiring).await?;
+
+        info!("Sent expiry notices for {} bans", expiring.len());
+        Ok(())
+    }
+
+    async fn remove_started_roles(&self) -> Result<(), Madness> {
+        let started = self.ban_service.started_unnotified().await?;
+        if started.is_empty() {
+            return Ok(());
+        }
+
+        let now = self.clock.now().timestamp();
+        for ban in &started {
+            // Pilots without a linked Discord account have nothing to remove
+            let discord_id = match &ban.discord_id {
+                Some(discord_id) => discord_id,
+                None => continue,
+            };
+            // Like at creation a failure is only logged, retrying could go on forever
+            if let Err(e) = self
+                .role_removal_client
+                .remove_roles(discord_id, ban.character_id, ban.id, now)
+                .await
+            {
+                warn!(
+                    "Unable to remove the Discord roles of {} for ban {}: {:#?}",
+                    ban.character_id, ban.id, e
+                );
+            }
+        }
+        self.ban_service.mark_start_notified(&started).await?;
+
+        info!("Removed Discord roles for {} started bans", started.len());
+        Ok(())
+    }
+}
@@ -0,0 +1,312 @@
+// How many pilots a corporation or alliance ban would reach.
+//
+// The quick preview only looks at pilots we already know. Jobs also add up ESI's member count
+// of every corporation in an alliance, which takes one request per corporation, so they run
+// in the background and store their progress in the ban_impact_job table.
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::core::{
+    affiliation::AffiliationService,
+    esi::{self, EsiLookup},
+};
+use crate::util::{clock::Clock, types::Character};
+use crate::{config::Config, util::madness::Madness};
+
+// Pilots seen in the last 28 days count as active, like the skill updater
+const ACTIVE_WINDOW: i64 = 86400 * 28;
+const SAMPLE_SIZE: usize = 10;
+
+// Jobs that haven't finished yet, across all FCs
+pub const MAX_UNFINISHED_JOBS: i64 = 3;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CascadePreview {
+    // Characters we've seen in the corporation or alliance, and how many of them are active
+    pub known_members: usize,
+    pub active_members: usize,
+    // ESI's member count, the quick preview only loads it for corporations
+    pub member_count: Option<i64>,
+    pub sample: Vec<Character>,
+}
+
+pub fn check_entity_type(entity_type: &str) -> Result<(), Madness> {
+    if entity_type != "Corporation" && entity_type != "Alliance" {
+        return Err(Madness::BadRequest(
+            "Only corporation and alliance bans cascade to their members".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+pub async fn preview(
+    affiliation_service: &AffiliationService,
+    entity_type: &str,
+    entity_id: i64,
+    now: i64,
+) -> Result<CascadePreview, Madness> {
+    let active_since = now - ACTIVE_WINDOW;
+    let members = affiliation_service
+        .known_members(entity_type, entity_id)
+        .await?;
+
+    // Members are sorted by when we last saw them
+    let active: Vec<_> = members
+        .iter()
+        .take_while(|member| member.last_seen > active_since)
+        .collect();
+
+    let member_count = match entity_type {
+        "Corporation" => {
+            affiliation_service
+                .corporation_member_count(entity_id)
+                .await
+        }
+        _ => None,
+    };
+
+    Ok(CascadePreview {
+        known_members: members.len(),
+        active_members: active.len(),
+        member_count,
+        sample: active
+            .iter()
+            .take(SAMPLE_SIZE)
+            .map(|member| Character {
+                id: member.id,
+                name: member.name.clone(),
+                corporation_id: None,
+            })
+            .collect(),
+    })
+}
+
+pub struct ImpactJobRunner {
+    db: Arc<crate::DB>,
+    affiliation_service: AffiliationService,
+    clock: Arc<dyn Clock>,
+}
+
+impl ImpactJobRunner {
+    pub fn new(db: Arc<crate::DB>, config: Config, clock: Arc<dyn Clock>) -> ImpactJobRunner {
+        let esi_client = esi::ESIClient::new(
+            db.clone(),
+            config.esi.client_id.clone(),
+            config.esi.client_secret.clone(),
+        );
+        Self::with_esi(db, Arc::new(esi_client), clock)
+    }
+
+    pub fn with_esi(
+        db: Arc<crate::DB>,
+        esi_client: Arc<dyn EsiLookup>,
+        clock: Arc<dyn Clock>,
+    ) -> ImpactJobRunner {
+        ImpactJobRunner {
+            affiliation_service: AffiliationService::new(db.clone(), esi_client),
+            db,
+            clock,
+        }
+    }
+
+    // Queues a job, or refuses if too many are still running
+    pub async fn create(
+        db: &crate::DB,
+        entity_type: &str,
+        entity_id: i64,
+        created_by: i64,
+        now: i64,
+    ) -> Result<i64, Madness> {
+        check_entity_type(entity_type)?;
+
+        let mut tx = db.begin().await?;
+        // Serializes job creation, so two FCs can't both take the last slot
+        sqlx::query!("LOCK TABLE ban_impact_job IN SHARE ROW EXCLUSIVE MODE")
+            .execute(&mut tx)
+            .await?;
+
+        let unfinished = sqlx::query!(
+            "SELECT COUNT(*) AS \"count!\" FROM ban_impact_job WHERE status IN ('queued', 'running')"
+        )
+        .fetch_one(&mut tx)
+        .await?
+        .count;
+        if unfinished >= MAX_UNFINISHED_JOBS {
+            return Err(Madness::TooManyRequests(
+                "Too many impact estimates are running, try again later".to_string(),
+            ));
+        }
+
+        let id = sqlx::query!(
+            "INSERT INTO ban_impact_job (entity_type, entity_id, status, created_by, created_at, updated_at) VALUES ($1, $2, 'queued', $3, $4, $4) RETURNING id",
+            entity_type,
+            entity_id,
+            created_by,
+            now
+        )
+        .fetch_one(&mut tx)
+        .await?
+        .id;
+
+        tx.commit().await?;
+        Ok(id)
+    }
+
+    pub fn start(self, job_id: i64) {
+        tokio::spawn(async move {
+            if let Err(e) = self.run(job_id).await {
+                error!("Error in ban impact job {}: {:#?}", job_id, e);
+                if let Err(e) = self.fail(job_id, &e.to_string()).await {
+                    error!(
+                        "Unable to mark ban impact job {} as failed: {:#?}",
+                        job_id, e
+                    );
+                }
+            }
+        });
+    }
+
+    // Picks up jobs that were interrupted by a restart
+    pub async fn resume(
+        db: Arc<crate::DB>,
+        config: Config,
+        clock: Arc<dyn Clock>,
+    ) -> Result<(), Madness> {
+        let jobs = sqlx::query!(
+            "SELECT id FROM ban_impact_job WHERE status IN ('queued', 'running') ORDER BY id"
+        )
+        .fetch_all(db.as_ref())
+        .await?;
+
+        for job in jobs {
+            ImpactJobRunner::new(db.clone(), config.clone(), clock.clone()).start(job.id);
+        }
+        Ok(())
+    }
+
+    async fn set_progress(&self, job_id: i64, progress: i64, total: i64) -> Result<(), Madness> {
+        sqlx::query!(
+            "UPDATE ban_impact_job SET progress=$1, total=$2, updated_at=$3 WHERE id=$4",
+            progress,
+            total,
+            self.clock.now().timestamp(),
+            job_id
+        )
+        .execute(self.db.as_ref())
+        .await?;
+        Ok(())
+    }
+
+    async fn fail(&self, job_id: i64, error: &str) -> Result<(), Madness> {
+        sqlx::query!(
+            "UPDATE ban_impact_job SET status='failed', error=$1, updated_at=$2 WHERE id=$3",
+            error,
+            self.clock.now().timestamp(),
+            job_id
+        )
+        .execute(self.db.as_ref())
+        .await?;
+        Ok(())
+    }
+
+    async fn run(&self, job_id: i64) -> Result<(), Madness> {
+        let job = sqlx::query!(
+            "SELECT entity_type, entity_id FROM ban_impact_job WHERE id=$1",
+            job_id
+        )
+        .fetch_one(self.db.as_ref())
+        .await?;
+
+        sqlx::query!(
+            "UPDATE ban_impact_job SET status='running', progress=0, updated_at=$1 WHERE id=$2",
+            self.clock.now().timestamp(),
+            job_id
+        )
+        .execute(self.db.as_ref())
+        .await?;
+
+        let now = self.clock.now().timestamp();
+        let mut result = preview(
+            &self.affiliation_service,
+            &job.entity_type,
+            job.entity_id,
+            now,
+        )
+        .await?;
+
+        if job.entity_type == "Alliance" {
+            let corporations = self
+                .affiliation_service
+                .alliance_corporations(job.entity_id)
+                .await?;
+            let total = corporations.len() as i64;
+            self.set_progress(job_id, 0, total).await?;
+
+            let mut member_count = 0;
+            for (i, corporation_id) in corporations.into_iter().enumerate() {
+                if let Some(count) = self
+                    .affiliation_service
+                    .corporation_member_count(corporation_id)
+                    .await
+                {
+                    member_count += count;
+                }
+                self.set_progress(job_id, i as i64 + 1, total).await?;
+            }
+            result.member_count = Some(member_count);
+        }
+
+        sqlx::query!(
+            "UPDATE ban_impact_job SET status='done', result=$1, updated_at=$2 WHERE id=$3",
+            serde_json::to_string(&result).unwrap(),
+            self.clock.now().timestamp(),
+            job_id
+        )
+        .execute(self.db.as_ref())
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::ImpactJobRunner;
+    use crate::util::{clock::FixedClock, testapp::FakeEsi, testdb::TestDatabase};
+
+    #[rocket::async_test]
+    async fn test_job_timestamps() {
+        let db = match TestDatabase::fresh().await {
+            Some(db) => db,
+            None => return,
+        };
+        sqlx::query("INSERT INTO character (id, name) VALUES (1000, 'FC')")
+            .execute(db.pool())
+            .await
+            .unwrap();
+        let pool = Arc::new(db.pool().clone());
+
+        let job_id = ImpactJobRunner::create(db.pool(), "Corporation", 98000001, 1000, 1000000)
+            .await
+            .unwrap();
+        let runner = ImpactJobRunner::with_esi(
+            pool,
+            Arc::new(FakeEsi::new(&[(98000001, "Some Corp", "Corporation")])),
+            Arc::new(FixedClock::at(1000000 + 60)),
+        );
+        runner.run(job_id).await.unwrap();
+
+        let (status, created_at, updated_at): (String, i64, i64) =
+            sqlx::query_as("SELECT status, created_at, updated_at FROM ban_impact_job WHERE id=$1")
+                .bind(job_id)
+                .fetch_one(db.pool())
+                .await
+                .unwrap();
+        assert_eq!(status, "done");
+        assert_eq!((created_at, updated_at), (1000000, 1000000 + 60));
+
+        db.destroy().await;
+    }
+}
@@ -0,0 +1,179 @@
+// Comparing a partner's ban list with ours before importing it.
+//
+// Lists come as JSON, or as CSV with an entity_type,entity_id,reason header. The reason is the
+// last column, so it may contain commas.
+use serde::{Deserialize, Serialize};
+
+use crate::core::ban::BanService;
+use crate::util::{
+    madness::Madness,
+    types::{Entity, EntityType},
+};
+
+// Any more and the comparison takes too long for a single request
+pub const IMPORT_MAX_COUNT: usize = 5000;
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ImportedBan {
+    pub entity_id: i64,
+    pub entity_type: String,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExistingBan {
+    #[serde(flatten)]
+    pub imported: ImportedBan,
+    pub our_reasons: Vec<String>,
+    // None of our reasons match theirs
+    pub reason_differs: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RevokedBan {
+    #[serde(flatten)]
+    pub imported: ImportedBan,
+    pub revoked_at: i64,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ImportDiff {
+    // Entities we don't ban and never revoked a ban on
+    pub new: Vec<ImportedBan>,
+    pub existing: Vec<ExistingBan>,
+    // Entities an FC deliberately unbanned, bans that simply expired don't count
+    pub revoked: Vec<RevokedBan>,
+}
+
+fn unquote(field: &str) -> &str {
+    let field = field.trim();
+    field
+        .strip_prefix('"')
+        .and_then(|field| field.strip_suffix('"'))
+        .unwrap_or(field)
+}
+
+pub fn parse_csv(input: &str) -> Result<Vec<ImportedBan>, String> {
+    let mut lines = input.lines().filter(|line| !line.trim().is_empty());
+    let header: Vec<&str> = match lines.next() {
+        Some(header) => header.split(',').map(unquote).collect(),
+        None => return Ok(Vec::new()),
+    };
+    if header != ["entity_type", "entity_id", "reason"] {
+        return Err("The CSV header must be entity_type,entity_id,reason".to_string());
+    }
+
+    lines
+        .enumerate()
+        .map(|(i, line)| {
+            let mut fields = line.splitn(3, ',').map(unquote);
+            let (entity_type, entity_id, reason) = (fields.next(), fields.next(), fields.next());
+            let entity_id = entity_id
+                .and_then(|id| id.parse().ok())
+                .ok_or_else(|| format!("Row {} has an invalid entity_id", i + 1))?;
+
+            Ok(ImportedBan {
+                entity_id,
+                entity_type: entity_type.unwrap_or_default().to_string(),
+                reason: reason
+                    .filter(|reason| !reason.is_empty())
+                    .map(str::to_string),
+            })
+        })
+        .collect()
+}
+
+pub fn validate(bans: &[ImportedBan]) -> Result<(), String> {
+    if bans.len() > IMPORT_MAX_COUNT {
+        return Err(format!(
+            "Cannot compare more than {} bans at once",
+            IMPORT_MAX_COUNT
+        ));
+    }
+    if let Some(ban) = bans
+        .iter()
+        .find(|ban| EntityType::parse(&ban.entity_type).is_none())
+    {
+        return Err(format!("Unknown entity type {}", ban.entity_type));
+    }
+    Ok(())
+}
+
+fn same_reason(ours: &str, theirs: &str) -> bool {
+    ours.trim().eq_ignore_ascii_case(theirs.trim())
+}
+
+pub async fn diff(ban_service: &BanService, bans: &[ImportedBan]) -> Result<ImportDiff, Madness> {
+    let mut diff = ImportDiff::default();
+    for imported in bans {
+        if let Some(ours) = ban_service
+            .active_bans(imported.entity_id, &imported.entity_type)
+            .await?
+        {
+            let our_reasons: Vec<String> = ours.into_iter().map(|ban| ban.reason).collect();
+            let reason_differs = match &imported.reason {
+                Some(theirs) => !our_reasons.iter().any(|ours| same_reason(ours, theirs)),
+                None => false,
+            };
+            diff.existing.push(ExistingBan {
+                imported: imported.clone(),
+                our_reasons,
+                reason_differs,
+            });
+            continue;
+        }
+
+        let entity = Entity {
+            id: imported.entity_id,
+            name: None,
+            category: imported.entity_type.clone(),
+        };
+        match ban_service.last_revoked_at(&entity).await? {
+            Some(revoked_at) => diff.revoked.push(RevokedBan {
+                imported: imported.clone(),
+                revoked_at,
+            }),
+            None => diff.new.push(imported.clone()),
+        }
+    }
+    Ok(diff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_csv, validate, ImportedBan};
+
+    #[test]
+    fn test_parse_csv() {
+        let bans = parse_csv(
+            "entity_type,entity_id,reason\n\
+             Character,2001,\"Awoxing, twice\"\n\
+             \n\
+             Corporation,98000001,\n",
+        )
+        .unwrap();
+        assert_eq!(
+            bans,
+            vec![
+                ImportedBan {
+                    entity_id: 2001,
+                    entity_type: "Character".to_string(),
+                    reason: Some("Awoxing, twice".to_string()),
+                },
+                ImportedBan {
+                    entity_id: 98000001,
+                    entity_type: "Corporation".to_string(),
+                    reason: None,
+                },
+            ]
+        );
+
+        assert!(parse_csv("id,type\n1,Character").is_err());
+        assert_eq!(
+            parse_csv("entity_type,entity_id,reason\nCharacter,abc,x"),
+            Err("Row 1 has an invalid entity_id".to_string())
+        );
+        assert!(validate(&parse_csv("entity_type,entity_id,reason\nPlanet,1,x").unwrap()).is_err());
+    }
+}
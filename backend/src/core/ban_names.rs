@@ -0,0 +1,74 @@
+use serde::Deserialize;
+
+use crate::core::{ban::BanService, esi};
+use crate::util::{clock::SystemClock, types::EntityType};
+use crate::{config::Config, util::madness::Madness};
+use std::sync::Arc;
+
+// How often to retry the names of bans created while ESI was down
+const REFRESH_INTERVAL: u64 = 60 * 10;
+
+#[derive(Debug, Deserialize)]
+struct EsiResponse {
+    name: String,
+}
+
+pub struct BanNameRefresher {
+    esi_client: esi::ESIClient,
+    ban_service: BanService,
+}
+
+impl BanNameRefresher {
+    pub fn new(db: Arc<crate::DB>, config: Config) -> BanNameRefresher {
+        BanNameRefresher {
+            esi_client: esi::ESIClient::new(
+                db.clone(),
+                config.esi.client_id.clone(),
+                config.esi.client_secret.clone(),
+            ),
+            ban_service: BanService::new(db, Arc::new(SystemClock)),
+        }
+    }
+
+    pub fn start(self) {
+        tokio::spawn(async move {
+            self.run().await;
+        });
+    }
+
+    async fn run(self) {
+        let interval = tokio::time::Duration::from_secs(REFRESH_INTERVAL);
+        loop {
+            if let Err(e) = self.run_once().await {
+                error!("Error in ban name refresher: {:#?}", e);
+            };
+
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    async fn run_once(&self) -> Result<(), Madness> {
+        for entity in self.ban_service.pending_names().await? {
+            let path = match EntityType::parse(&entity.category) {
+                Some(entity_type) => entity_type.esi_path(entity.id),
+                None => continue,
+            };
+            let res: EsiResponse = match self.esi_client.get_unauthenticated(&path).await {
+                Ok(res) => res,
+                // Still down, try again next time
+                Err(e) if e.is_unavailable() => return Ok(()),
+                Err(e) => {
+                    warn!(
+                        "Unable to look up the name of banned {}: {:#?}",
+                        entity.id, e
+                    );
+                    continue;
+                }
+            };
+
+            self.ban_service.set_name(&entity, &res.name).await?;
+        }
+
+        Ok(())
+    }
+}
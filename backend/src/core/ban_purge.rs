@@ -0,0 +1,91 @@
+// Deleting ended bans for good, behind a preview and a confirmation token.
+//
+// The preview works out the cutoff and hands back a token for it. The delete has to present
+// that token with the same parameters, so it removes exactly what was previewed.
+use branca::Branca;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+
+// Long enough to read the preview, short enough that the count is still right
+pub const PURGE_TOKEN_TTL: u32 = 60 * 5;
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct PurgeToken {
+    version: i32,
+    pub account_id: i64,
+    pub older_than_days: i64,
+    // Bans that ended before this are deleted
+    pub cutoff: i64,
+}
+
+// Purge tokens are sealed with a key of their own, derived from the token secret, so they can
+// never be mistaken for a login cookie or the other way around
+fn purge_key(secret: &[u8]) -> Vec<u8> {
+    let mut mac =
+        Hmac::<sha2::Sha256>::new_from_slice(secret).expect("HMAC can take a key of any size");
+    mac.update(b"ban purge token");
+    mac.finalize().into_bytes().to_vec()
+}
+
+impl PurgeToken {
+    pub fn new(account_id: i64, older_than_days: i64, now: i64) -> PurgeToken {
+        PurgeToken {
+            version: 1,
+            account_id,
+            older_than_days,
+            cutoff: now - older_than_days * 86400,
+        }
+    }
+
+    pub fn encode(&self, secret: &[u8]) -> String {
+        let mut branca = Branca::new(&purge_key(secret)).unwrap();
+        let payload = rmp_serde::to_vec_named(self).unwrap();
+        branca.encode(&payload).unwrap()
+    }
+
+    // None if the token is invalid, has expired or was issued for something else
+    pub fn decode(
+        token: &str,
+        secret: &[u8],
+        account_id: i64,
+        older_than_days: i64,
+    ) -> Option<PurgeToken> {
+        let branca = Branca::new(&purge_key(secret)).unwrap();
+        let payload = branca.decode(token, PURGE_TOKEN_TTL).ok()?;
+        let decoded: PurgeToken = rmp_serde::from_read_ref(&payload).ok()?;
+
+        if decoded.version != 1
+            || decoded.account_id != account_id
+            || decoded.older_than_days != older_than_days
+        {
+            return None;
+        }
+        Some(decoded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use branca::Branca;
+
+    use super::PurgeToken;
+
+    const SECRET: [u8; 32] = [7; 32];
+
+    #[test]
+    fn test_purge_token() {
+        let token = PurgeToken::new(1001, 30, 10_000_000);
+        assert_eq!(token.cutoff, 10_000_000 - 30 * 86400);
+        let encoded = token.encode(&SECRET);
+
+        assert_eq!(PurgeToken::decode(&encoded, &SECRET, 1001, 30), Some(token));
+        // Other parameters, another account, another key or a mangled token
+        assert_eq!(PurgeToken::decode(&encoded, &SECRET, 1001, 0), None);
+        assert_eq!(PurgeToken::decode(&encoded, &SECRET, 1002, 30), None);
+        assert_eq!(PurgeToken::decode(&encoded, &[8; 32], 1001, 30), None);
+        assert_eq!(PurgeToken::decode("nonsense", &SECRET, 1001, 30), None);
+
+        // Not sealed with the token secret itself, which login cookies use
+        assert!(Branca::new(&SECRET).unwrap().decode(&encoded, 0).is_err());
+    }
+}
@@ -0,0 +1,152 @@
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+
+use crate::core::webhook::WebhookError;
+
+pub const SIGNATURE_HEADER: &str = "X-Waitlist-Signature";
+// The bot may be slow or gone, it mustn't keep a task around for long
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+// Asks our Discord bot to strip the roles of a banned pilot. The bot checks the signature
+// against the shared secret, so it can't be triggered by anyone who finds the URL.
+#[derive(Clone)]
+pub struct RoleRemovalClient {
+    http: reqwest::Client,
+    endpoint: Option<(String, Vec<u8>)>,
+}
+
+#[derive(Debug, Serialize)]
+struct RoleRemoval<'a> {
+    discord_id: &'a str,
+    character_id: i64,
+    ban_id: i64,
+    // Lets the bot reject replayed requests
+    timestamp: i64,
+}
+
+// Hex encoded HMAC-SHA256 of the body
+pub fn sign(secret: &[u8], body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<sha2::Sha256>::new_from_slice(secret).expect("HMAC can take a key of any size");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+fn verify(secret: &[u8], body: &[u8], signature: &str) -> bool {
+    let signature = match signature
+        .strip_prefix("sha256=")
+        .and_then(|hex| hex::decode(hex).ok())
+    {
+        Some(signature) => signature,
+        None => return false,
+    };
+    let mut mac =
+        Hmac::<sha2::Sha256>::new_from_slice(secret).expect("HMAC can take a key of any size");
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
+
+impl RoleRemovalClient {
+    pub fn new(url: Option<String>, secret: Option<String>) -> Result<RoleRemovalClient, String> {
+        let endpoint = match (url, secret) {
+            (Some(url), Some(secret)) => Some((
+                url,
+                hex::decode(&secret).map_err(|_| "the secret must be hex encoded".to_string())?,
+            )),
+            (None, _) => None,
+            (Some(_), None) => return Err("a secret is required".to_string()),
+        };
+
+        Ok(RoleRemovalClient {
+            http: reqwest::Client::builder()
+                .timeout(TIMEOUT)
+                .build()
+                .map_err(|e| e.to_string())?,
+            endpoint,
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.endpoint.is_some()
+    }
+
+    // Requests from the bot are signed the same way, with the same secret
+    pub fn verify(&self, body: &[u8], signature: &str) -> bool {
+        match &self.endpoint {
+            Some((_, secret)) => verify(secret, body, signature),
+            None => false,
+        }
+    }
+
+    pub async fn remove_roles(
+        &self,
+        discord_id: &str,
+        character_id: i64,
+        ban_id: i64,
+        now: i64,
+    ) -> Result<(), WebhookError> {
+        let (url, secret) = match &self.endpoint {
+            Some(endpoint) => endpoint,
+            None => return Ok(()),
+        };
+
+        let body = serde_json::to_vec(&RoleRemoval {
+            discord_id,
+            character_id,
+            ban_id,
+            timestamp: now,
+        })
+        .unwrap();
+
+        self.http
+            .post(url)
+            .header("Content-Type", "application/json")
+            .header(SIGNATURE_HEADER, format!("sha256={}", sign(secret, &body)))
+            .body(body)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sign, verify, RoleRemovalClient};
+
+    #[test]
+    fn test_sign() {
+        assert_eq!(
+            sign(b"key", b"The quick brown fox jumps over the lazy dog"),
+            "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+    }
+
+    #[test]
+    fn test_verify() {
+        let signature = format!("sha256={}", sign(b"key", b"body"));
+        assert!(verify(b"key", b"body", &signature));
+        assert!(!verify(b"key", b"other body", &signature));
+        assert!(!verify(b"other key", b"body", &signature));
+        assert!(!verify(b"key", b"body", &signature["sha256=".len()..]));
+        assert!(!verify(b"key", b"body", "sha256=xyz"));
+    }
+
+    #[test]
+    fn test_new() {
+        assert!(!RoleRemovalClient::new(None, None).unwrap().is_enabled());
+        assert!(
+            RoleRemovalClient::new(Some("https://bot".to_string()), Some("00ff".to_string()))
+                .unwrap()
+                .is_enabled()
+        );
+        assert!(RoleRemovalClient::new(Some("https://bot".to_string()), None).is_err());
+        assert!(
+            RoleRemovalClient::new(Some("https://bot".to_string()), Some("xyz".to_string()))
+                .is_err()
+        );
+    }
+}
@@ -12,6 +12,41 @@ pub struct ESIClient {
     raw: ESIRawClient,
 }
 
+// Refresh tokens that haven't been used this long may have been revoked without us noticing
+pub const TOKEN_STALE_AFTER: i64 = 7 * 86400;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TokenStatus {
+    // Refreshed recently
+    Valid,
+    // Stored, but not used for a while, see TOKEN_STALE_AFTER
+    Expiring,
+    // ESI refused the refresh token, so it was deleted and the character has to log in again
+    RefreshFailed,
+    // The character never logged in, or not since it was added
+    Missing,
+}
+
+// The health of a character's stored ESI token, from what access_token_raw records
+pub fn token_status(
+    has_refresh_token: bool,
+    refreshed_at: Option<i64>,
+    refresh_failed_at: Option<i64>,
+    now: i64,
+) -> TokenStatus {
+    if !has_refresh_token {
+        return match refresh_failed_at {
+            Some(_) => TokenStatus::RefreshFailed,
+            None => TokenStatus::Missing,
+        };
+    }
+    match refreshed_at {
+        Some(refreshed_at) if refreshed_at > now - TOKEN_STALE_AFTER => TokenStatus::Valid,
+        _ => TokenStatus::Expiring,
+    }
+}
+
 pub struct EsiErrorReason {
     pub error: String,
     pub details: String,
@@ -45,6 +80,20 @@ impl EsiErrorReason {
     }
 }
 
+#[derive(Debug, Serialize)]
+pub struct ResolvedEntity {
+    pub id: i64,
+    pub name: String,
+    pub category: &'static str,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CharacterAffiliation {
+    pub character_id: i64,
+    pub corporation_id: i64,
+    pub alliance_id: Option<i64>,
+}
+
 #[derive(Debug, Deserialize)]
 struct OAuthTokenResponse {
     access_token: String,
@@ -76,6 +125,36 @@ pub enum ESIError {
     NoToken,
     #[error("missing ESI scope")]
     MissingScope,
+    // ESI answers 420 once we've made too many failed requests, until the window resets
+    #[error("{}", error_limit_message(.0))]
+    ErrorLimited(Option<i64>),
+    #[error("unexpected ESI response")]
+    InvalidResponse(#[from] serde_json::Error),
+}
+
+fn error_limit_message(reset: &Option<i64>) -> String {
+    match reset {
+        Some(seconds) => format!(
+            "ESI is temporarily rate-limiting us, retry in {} seconds",
+            seconds
+        ),
+        None => "ESI is temporarily rate-limiting us, retry shortly".to_string(),
+    }
+}
+
+impl ESIError {
+    // ESI couldn't be reached or failed on its side, e.g. during downtime
+    pub fn is_unavailable(&self) -> bool {
+        match self {
+            ESIError::HTTPError(_) => true,
+            ESIError::Status(code) | ESIError::WithMessage(code, _) => *code >= 500,
+            _ => false,
+        }
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, ESIError::Status(404) | ESIError::WithMessage(404, _))
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -211,6 +290,19 @@ impl ESIRawClient {
     async fn log_response_error(response: reqwest::Response) -> Result<reqwest::Response, ESIError> {
         if let Err(_) = response.error_for_status_ref() {
             let status = response.status();
+            if status.as_u16() == 420 {
+                let reset = response
+                    .headers()
+                    .get("X-ESI-Error-Limit-Reset")
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.parse().ok());
+                warn!(
+                    "ESI is error limiting us, the limit resets in {:?} seconds",
+                    reset
+                );
+                return Err(ESIError::ErrorLimited(reset));
+            }
+
             let headers = format!("{:?}", response.headers());
             let url = response.url().to_owned();
             let response_body = response.text().await?;
@@ -242,6 +334,15 @@ impl ESIRawClient {
         Self::log_response_error(response).await
     }
 
+    pub async fn post_unauthenticated<E: Serialize + ?Sized>(
+        &self,
+        url: &str,
+        input: &E,
+    ) -> Result<reqwest::Response, ESIError> {
+        let response = self.http.post(url).json(input).send().await?;
+        Self::log_response_error(response).await
+    }
+
     pub async fn delete(
         &self,
         url: &str,
@@ -368,12 +469,21 @@ impl ESIClient {
         .await?;
 
         sqlx::query!(
-            "INSERT INTO refresh_token (character_id, refresh_token, scopes) VALUES ($1, $2, $3) ON CONFLICT (character_id) DO UPDATE
+            "INSERT INTO refresh_token (character_id, refresh_token, scopes, refreshed_at) VALUES ($1, $2, $3, $4) ON CONFLICT (character_id) DO UPDATE
             SET refresh_token = excluded.refresh_token, 
-                scopes = excluded.scopes;",
+                scopes = excluded.scopes,
+                refreshed_at = excluded.refreshed_at;",
             auth.character_id,
             auth.refresh_token,
             scopes,
+            chrono::Utc::now().timestamp(),
+        )
+        .execute(&mut tx)
+        .await?;
+
+        sqlx::query!(
+            "UPDATE character SET esi_token_failed_at=NULL WHERE id=$1",
+            auth.character_id
         )
         .execute(&mut tx)
         .await?;
@@ -439,6 +549,14 @@ impl ESIClient {
                 )
                 .execute(&mut tx)
                 .await?;
+                // Shown to leadership, see token_status
+                sqlx::query!(
+                    "UPDATE character SET esi_token_failed_at=$2 WHERE id=$1",
+                    character_id,
+                    chrono::Utc::now().timestamp()
+                )
+                .execute(&mut tx)
+                .await?;
                 tx.commit().await?;
 
                 return Err(ESIError::NoToken);
@@ -479,6 +597,113 @@ impl ESIClient {
         Ok(self.raw.get_unauthenticated(&url).await?.json().await?)
     }
 
+    // Like get_unauthenticated, but also returns when ESI says the response goes stale
+    pub async fn get_unauthenticated_with_expiry<D: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+    ) -> Result<(D, Option<i64>), ESIError> {
+        let url = format!("https://esi.evetech.net{}", path);
+        let response = self.raw.get_unauthenticated(&url).await?;
+        let expires = response
+            .headers()
+            .get(reqwest::header::EXPIRES)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| chrono::DateTime::parse_from_rfc2822(value).ok())
+            .map(|expires| expires.timestamp());
+        Ok((response.json().await?, expires))
+    }
+
+    pub async fn post_unauthenticated<E: Serialize + ?Sized, D: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+        input: &E,
+    ) -> Result<D, ESIError> {
+        let url = format!("https://esi.evetech.net{}", path);
+        Ok(self
+            .raw
+            .post_unauthenticated(&url, input)
+            .await?
+            .json()
+            .await?)
+    }
+
+    // Looks names up across characters, corporations and alliances. ESI only returns
+    // exact (case insensitive) matches, and omits names it doesn't know.
+    pub async fn resolve_ids(&self, names: &[&str]) -> Result<Vec<ResolvedEntity>, ESIError> {
+        #[derive(Debug, Deserialize)]
+        struct IdName {
+            id: i64,
+            name: String,
+        }
+
+        #[derive(Debug, Deserialize)]
+        struct IdsResponse {
+            #[serde(default)]
+            characters: Vec<IdName>,
+            #[serde(default)]
+            corporations: Vec<IdName>,
+            #[serde(default)]
+            alliances: Vec<IdName>,
+        }
+
+        let response: IdsResponse = self
+            .post_unauthenticated("/latest/universe/ids/", names)
+            .await?;
+
+        let mut resolved = Vec::new();
+        for (category, entities) in [
+            ("Character", response.characters),
+            ("Corporation", response.corporations),
+            ("Alliance", response.alliances),
+        ] {
+            resolved.extend(entities.into_iter().map(|entity| ResolvedEntity {
+                id: entity.id,
+                name: entity.name,
+                category,
+            }));
+        }
+
+        Ok(resolved)
+    }
+
+    // Names and categories of IDs, an unknown ID fails the whole request with a 404
+    pub async fn resolve_names(&self, ids: &[i64]) -> Result<Vec<ResolvedEntity>, ESIError> {
+        #[derive(Debug, Deserialize)]
+        struct NameResponse {
+            id: i64,
+            name: String,
+            category: String,
+        }
+
+        let response: Vec<NameResponse> = self
+            .post_unauthenticated("/latest/universe/names/", ids)
+            .await?;
+
+        Ok(response
+            .into_iter()
+            .map(|entity| ResolvedEntity {
+                id: entity.id,
+                name: entity.name,
+                category: match entity.category.as_str() {
+                    "character" => "Character",
+                    "corporation" => "Corporation",
+                    "alliance" => "Alliance",
+                    _ => "Other",
+                },
+            })
+            .collect())
+    }
+
+    // Current corporation and alliance of many characters in one request, an invalid ID fails
+    // the whole request
+    pub async fn character_affiliations(
+        &self,
+        character_ids: &[i64],
+    ) -> Result<Vec<CharacterAffiliation>, ESIError> {
+        self.post_unauthenticated("/latest/characters/affiliation/", character_ids)
+            .await
+    }
+
     pub async fn delete(
         &self,
         path: &str,
@@ -570,6 +795,75 @@ fn split_scopes(input: &str) -> BTreeSet<String> {
         .collect()
 }
 
+// The public lookups the ban handlers and AffiliationService make, so tests can swap in a fake
+// ESI, see util::testapp. Authenticated requests still go through ESIClient directly.
+#[rocket::async_trait]
+pub trait EsiLookup: Send + Sync {
+    async fn get_value(&self, path: &str) -> Result<serde_json::Value, ESIError>;
+    async fn resolve_names(&self, ids: &[i64]) -> Result<Vec<ResolvedEntity>, ESIError>;
+    async fn resolve_ids(&self, names: &[&str]) -> Result<Vec<ResolvedEntity>, ESIError>;
+    async fn character_affiliations(
+        &self,
+        character_ids: &[i64],
+    ) -> Result<Vec<CharacterAffiliation>, ESIError>;
+}
+
+impl dyn EsiLookup {
+    pub async fn get_unauthenticated<D: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+    ) -> Result<D, ESIError> {
+        Ok(serde_json::from_value(self.get_value(path).await?)?)
+    }
+}
+
+#[rocket::async_trait]
+impl EsiLookup for ESIClient {
+    async fn get_value(&self, path: &str) -> Result<serde_json::Value, ESIError> {
+        ESIClient::get_unauthenticated(self, path).await
+    }
+
+    async fn resolve_names(&self, ids: &[i64]) -> Result<Vec<ResolvedEntity>, ESIError> {
+        ESIClient::resolve_names(self, ids).await
+    }
+
+    async fn resolve_ids(&self, names: &[&str]) -> Result<Vec<ResolvedEntity>, ESIError> {
+        ESIClient::resolve_ids(self, names).await
+    }
+
+    async fn character_affiliations(
+        &self,
+        character_ids: &[i64],
+    ) -> Result<Vec<CharacterAffiliation>, ESIError> {
+        ESIClient::character_affiliations(self, character_ids).await
+    }
+}
+
 fn join_scopes(input: &BTreeSet<String>) -> String {
     input.iter().fold(String::new(), |a, b| a + b + " ")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{token_status, TokenStatus, TOKEN_STALE_AFTER};
+
+    #[test]
+    fn test_token_status() {
+        let now = 10_000_000;
+        assert_eq!(
+            token_status(true, Some(now - 60), None, now),
+            TokenStatus::Valid
+        );
+        assert_eq!(
+            token_status(true, Some(now - TOKEN_STALE_AFTER), None, now),
+            TokenStatus::Expiring
+        );
+        // Saved before refreshes were recorded
+        assert_eq!(token_status(true, None, None, now), TokenStatus::Expiring);
+        assert_eq!(
+            token_status(false, None, Some(now - 60), now),
+            TokenStatus::RefreshFailed
+        );
+        assert_eq!(token_status(false, None, None, now), TokenStatus::Missing);
+    }
+}
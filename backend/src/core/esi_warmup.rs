@@ -0,0 +1,202 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::core::esi::{ESIClient, EsiLookup};
+use crate::{
+    config::{Config, EsiWarmupConfig},
+    util::madness::Madness,
+};
+
+// ESI resolves at most this many IDs per request
+const BATCH_SIZE: usize = 1000;
+
+// Looks up the names of corporations and alliances we're likely to need before the first fleet
+// of the day asks for them, and stores them like AffiliationService does. Type names come from
+// the bundled SDE and don't need warming up.
+pub struct EsiWarmup {
+    db: Arc<crate::DB>,
+    esi_client: Arc<dyn EsiLookup>,
+    config: EsiWarmupConfig,
+}
+
+impl EsiWarmup {
+    pub fn new(db: Arc<crate::DB>, config: Config) -> EsiWarmup {
+        let esi_client = ESIClient::new(
+            db.clone(),
+            config.esi.client_id.clone(),
+            config.esi.client_secret.clone(),
+        );
+        Self::with_esi(db, Arc::new(esi_client), config.esi_warmup)
+    }
+
+    pub fn with_esi(
+        db: Arc<crate::DB>,
+        esi_client: Arc<dyn EsiLookup>,
+        config: EsiWarmupConfig,
+    ) -> EsiWarmup {
+        EsiWarmup {
+            db,
+            esi_client,
+            config,
+        }
+    }
+
+    // Runs once in the background, startup doesn't wait for it
+    pub fn start(self) {
+        tokio::spawn(async move {
+            match self.run_once().await {
+                Ok(count) => info!("Warmed up {} corporation and alliance names", count),
+                Err(e) => error!("Error in ESI warmup: {:#?}", e),
+            }
+        });
+    }
+
+    // Returns the number of names stored. A batch ESI rejects is logged and skipped.
+    pub async fn run_once(&self) -> Result<usize, Madness> {
+        let referenced = sqlx::query!(
+            "SELECT entity_id FROM ban
+            WHERE entity_type IN ('Corporation', 'Alliance')
+            GROUP BY entity_id ORDER BY COUNT(*) DESC, entity_id LIMIT $1",
+            self.config.top_referenced
+        )
+        .fetch_all(self.db.as_ref())
+        .await?
+        .into_iter()
+        .map(|row| row.entity_id)
+        .collect::<Vec<_>>();
+
+        let cached = sqlx::query!(
+            "SELECT id AS \"id!\" FROM corporation UNION SELECT id AS \"id!\" FROM alliance"
+        )
+        .fetch_all(self.db.as_ref())
+        .await?
+        .into_iter()
+        .map(|row| row.id)
+        .collect::<HashSet<_>>();
+
+        let mut stored = 0;
+        for batch in warmup_ids(&self.config.ids, &referenced, &cached).chunks(BATCH_SIZE) {
+            let resolved = match self.esi_client.resolve_names(batch).await {
+                Ok(resolved) => resolved,
+                Err(e) => {
+                    warn!("Unable to warm up {} names: {:#?}", batch.len(), e);
+                    continue;
+                }
+            };
+
+            for entity in resolved {
+                match entity.category {
+                    "Corporation" => {
+                        // updated_at 0 so AffiliationService still loads the rest when it's needed
+                        sqlx::query!(
+                            "INSERT INTO corporation (id, name, updated_at) VALUES ($1, $2, 0)
+                            ON CONFLICT (id) DO UPDATE SET name=excluded.name",
+                            entity.id,
+                            entity.name
+                        )
+                        .execute(self.db.as_ref())
+                        .await?;
+                    }
+                    "Alliance" => {
+                        sqlx::query!(
+                            "INSERT INTO alliance (id, name) VALUES ($1, $2)
+                            ON CONFLICT (id) DO UPDATE SET name=excluded.name",
+                            entity.id,
+                            entity.name
+                        )
+                        .execute(self.db.as_ref())
+                        .await?;
+                    }
+                    _ => continue,
+                }
+                stored += 1;
+            }
+        }
+
+        Ok(stored)
+    }
+}
+
+// The configured IDs first, then the referenced ones, without duplicates or names we already have
+fn warmup_ids(configured: &[i64], referenced: &[i64], cached: &HashSet<i64>) -> Vec<i64> {
+    let mut seen = HashSet::new();
+    configured
+        .iter()
+        .chain(referenced.iter())
+        .filter(|id| !cached.contains(id) && seen.insert(**id))
+        .copied()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::sync::Arc;
+
+    use super::{warmup_ids, EsiWarmup};
+    use crate::config::EsiWarmupConfig;
+    use crate::util::{testapp::FakeEsi, testdb::TestDatabase};
+
+    #[test]
+    fn test_warmup_ids() {
+        let cached: HashSet<i64> = [3].iter().copied().collect();
+        assert_eq!(warmup_ids(&[5, 1], &[1, 3, 4], &cached), vec![5, 1, 4]);
+        assert!(warmup_ids(&[], &[3], &cached).is_empty());
+    }
+
+    #[rocket::async_test]
+    async fn test_run_once() {
+        let db = match TestDatabase::fresh().await {
+            Some(db) => db,
+            None => return,
+        };
+        sqlx::query("INSERT INTO character (id, name) VALUES (1000, 'FC')")
+            .execute(db.pool())
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO ban (entity_id, entity_type, issued_at, issued_by, reason)
+            VALUES (98000001, 'Corporation', 1600000000, 1000, 'Reason')",
+        )
+        .execute(db.pool())
+        .await
+        .unwrap();
+
+        let esi = FakeEsi::new(&[
+            (98000001, "Banned Corp", "Corporation"),
+            (99000001, "Configured Alliance", "Alliance"),
+        ]);
+        let warmup = EsiWarmup::with_esi(
+            Arc::new(db.pool().clone()),
+            Arc::new(esi),
+            EsiWarmupConfig {
+                enable: true,
+                ids: vec![99000001],
+                top_referenced: 10,
+            },
+        );
+
+        assert_eq!(warmup.run_once().await.unwrap(), 2);
+        let corporation = sqlx::query!("SELECT name FROM corporation WHERE id=98000001")
+            .fetch_one(db.pool())
+            .await
+            .unwrap();
+        assert_eq!(corporation.name, "Banned Corp");
+        // Already cached
+        assert_eq!(warmup.run_once().await.unwrap(), 0);
+
+        // An ID ESI doesn't know fails its batch, but not the warmup
+        let warmup = EsiWarmup::with_esi(
+            Arc::new(db.pool().clone()),
+            Arc::new(FakeEsi::new(&[])),
+            EsiWarmupConfig {
+                enable: true,
+                ids: vec![12345],
+                top_referenced: 0,
+            },
+        );
+        assert_eq!(warmup.run_once().await.unwrap(), 0);
+
+        db.destroy().await;
+    }
+}
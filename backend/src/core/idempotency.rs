@@ -0,0 +1,125 @@
+use rocket::request::{FromRequest, Outcome, Request};
+
+use crate::util::madness::Madness;
+
+const HEADER_NAME: &str = "Idempotency-Key";
+const KEY_MAX_LENGTH: usize = 255;
+// Replays are answered for a day, after that the key can be reused
+const KEY_TTL: i64 = 86400;
+
+// Lets clients on flaky connections retry a POST without repeating its side effects.
+// The first request with a key claims it, and its response is stored once it succeeds
+// so later requests with the same key get that response back instead.
+pub struct IdempotencyKey(Option<String>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for IdempotencyKey {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(IdempotencyKey(
+            req.headers().get_one(HEADER_NAME).map(str::to_string),
+        ))
+    }
+}
+
+impl IdempotencyKey {
+    // Returns the stored response if this key was already used successfully
+    pub async fn claim(
+        &self,
+        db: &crate::DB,
+        account_id: i64,
+        endpoint: &str,
+        now: i64,
+    ) -> Result<Option<String>, Madness> {
+        let key = match &self.0 {
+            Some(key) => key,
+            None => return Ok(None),
+        };
+        if key.is_empty() || key.len() > KEY_MAX_LENGTH {
+            return Err(Madness::BadRequest(format!(
+                "{} must be between 1 and {} characters",
+                HEADER_NAME, KEY_MAX_LENGTH
+            )));
+        }
+
+        sqlx::query!(
+            "DELETE FROM idempotency_key WHERE created_at < $1",
+            now - KEY_TTL
+        )
+        .execute(db)
+        .await?;
+
+        let claimed = sqlx::query!(
+            "INSERT INTO idempotency_key (account_id, endpoint, idempotency_key, created_at) VALUES ($1, $2, $3, $4) ON CONFLICT DO NOTHING",
+            account_id,
+            endpoint,
+            key,
+            now
+        )
+        .execute(db)
+        .await?
+        .rows_affected();
+        if claimed == 1 {
+            return Ok(None);
+        }
+
+        let response = sqlx::query!(
+            "SELECT response FROM idempotency_key WHERE account_id=$1 AND endpoint=$2 AND idempotency_key=$3",
+            account_id,
+            endpoint,
+            key
+        )
+        .fetch_optional(db)
+        .await?
+        .and_then(|row| row.response);
+
+        match response {
+            Some(response) => Ok(Some(response)),
+            None => Err(Madness::Conflict(format!(
+                "A request with this {} is still being processed",
+                HEADER_NAME
+            ))),
+        }
+    }
+
+    // Stores a successful response, or releases the key so a failed request can be retried
+    pub async fn complete(
+        &self,
+        db: &crate::DB,
+        account_id: i64,
+        endpoint: &str,
+        result: &Result<String, Madness>,
+    ) -> Result<(), Madness> {
+        let key = match &self.0 {
+            Some(key) => key,
+            None => return Ok(()),
+        };
+
+        match result {
+            Ok(response) => {
+                sqlx::query!(
+                    "UPDATE idempotency_key SET response=$4 WHERE account_id=$1 AND endpoint=$2 AND idempotency_key=$3",
+                    account_id,
+                    endpoint,
+                    key,
+                    response
+                )
+                .execute(db)
+                .await?;
+            }
+            Err(_) => {
+                sqlx::query!(
+                    "DELETE FROM idempotency_key WHERE account_id=$1 AND endpoint=$2 AND idempotency_key=$3",
+                    account_id,
+                    endpoint,
+                    key
+                )
+                .execute(db)
+                .await?;
+            }
+        };
+
+        Ok(())
+    }
+}
@@ -1,7 +1,21 @@
 pub mod affiliation;
 pub mod auth;
 pub mod ban;
+pub mod ban_dissolution;
+pub mod ban_expiry;
+pub mod ban_impact;
+pub mod ban_import;
+pub mod ban_names;
+pub mod ban_purge;
+pub mod discord_roles;
 pub mod esi;
+pub mod esi_warmup;
 pub mod fleet_updater;
+pub mod idempotency;
+pub mod name_pattern;
+pub mod ratelimit;
 pub mod skill_updater;
 pub mod sse;
+pub mod waitlist_expiry;
+pub mod waitlist_metrics;
+pub mod webhook;
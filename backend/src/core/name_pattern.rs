@@ -0,0 +1,96 @@
+// Glob patterns for name pattern bans, e.g. "GiveawayBot*".
+//
+// Only * (any run of characters) and ? (one character) are special. Unlike a regex, a glob
+// can't backtrack exponentially: matching is at worst pattern length times name length.
+// Matching ignores case, like EVE does for character names.
+
+const MAX_LENGTH: usize = 64;
+// Stops patterns like "*" or "?*" that would match almost everyone
+const MIN_LITERALS: usize = 3;
+
+pub fn validate(pattern: &str) -> Result<(), String> {
+    if pattern.chars().count() > MAX_LENGTH {
+        return Err(format!(
+            "Name patterns cannot be longer than {} characters",
+            MAX_LENGTH
+        ));
+    }
+
+    // The characters EVE allows in names, plus the wildcards
+    if let Some(c) = pattern
+        .chars()
+        .find(|c| !(c.is_alphanumeric() || matches!(c, ' ' | '-' | '\'' | '.' | '*' | '?')))
+    {
+        return Err(format!("Name patterns cannot contain \"{}\"", c));
+    }
+
+    let literals = pattern.chars().filter(|c| *c != '*' && *c != '?').count();
+    if literals < MIN_LITERALS {
+        return Err(format!(
+            "Name patterns need at least {} characters besides wildcards",
+            MIN_LITERALS
+        ));
+    }
+
+    Ok(())
+}
+
+pub fn matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+
+    let (mut p, mut n) = (0, 0);
+    // Where the last * was, and how much of the name it has swallowed so far
+    let mut star: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            // Let the * swallow one more character and try again
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{matches, validate};
+
+    #[test]
+    fn test_matches() {
+        assert!(matches("GiveawayBot*", "GiveawayBot1"));
+        assert!(matches("GiveawayBot*", "giveawaybot"));
+        assert!(matches("*Bot?", "Giveaway Bot7"));
+        assert!(!matches("*Bot?", "Giveaway Bot"));
+        assert!(!matches("GiveawayBot*", "Not GiveawayBot1"));
+        assert!(matches("a*b*c", "axxbyyc"));
+        assert!(!matches("a*b*c", "axxbyy"));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate("GiveawayBot*").is_ok());
+        assert!(validate("*").is_err());
+        assert!(validate("ab*").is_err());
+        assert!(validate("Bot(.*)+").is_err());
+        assert!(validate(&"a".repeat(65)).is_err());
+    }
+
+    #[test]
+    fn test_no_catastrophic_backtracking() {
+        let pattern = format!("{}b", "a*".repeat(30));
+        let name = "a".repeat(64);
+        assert!(!matches(&pattern, &name));
+    }
+}
@@ -0,0 +1,156 @@
+use std::{collections::HashMap, sync::Mutex};
+
+// Keys beyond this make room by dropping the ones whose hits have all left their window. If
+// that isn't enough new keys are refused until some do, rather than letting the map grow.
+const MAX_KEYS: usize = 10_000;
+
+// Sliding window rate limiter, kept in memory as limits only need to hold per instance
+pub struct RateLimiter {
+    hits: Mutex<HashMap<String, Hits>>,
+}
+
+// Each key keeps the window it's limited over, limits with different windows share the map
+struct Hits {
+    window: i64,
+    times: Vec<i64>,
+}
+
+impl Hits {
+    fn prune(&mut self, now: i64) {
+        let window = self.window;
+        self.times.retain(|t| *t > now - window);
+    }
+}
+
+impl RateLimiter {
+    pub fn new() -> RateLimiter {
+        RateLimiter {
+            hits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Records a hit for the key, or returns the seconds until the next one is allowed
+    pub fn check(&self, key: &str, limit: usize, window: i64, now: i64) -> Result<(), i64> {
+        let mut hits = self.hits.lock().unwrap();
+        wait_for(&mut hits, key, limit, window, now)?;
+        push(&mut hits, key, window, now)
+    }
+
+    // Like check, but without recording a hit, for limits that only count attempts that
+    // went through. Call record once they did.
+    pub fn peek(&self, key: &str, limit: usize, window: i64, now: i64) -> Result<(), i64> {
+        wait_for(&mut self.hits.lock().unwrap(), key, limit, window, now)
+    }
+
+    pub fn record(&self, key: &str, window: i64, now: i64) {
+        // Only refused when the map is full, the attempt went through already
+        let _ = push(&mut self.hits.lock().unwrap(), key, window, now);
+    }
+}
+
+fn wait_for(
+    hits: &mut HashMap<String, Hits>,
+    key: &str,
+    limit: usize,
+    window: i64,
+    now: i64,
+) -> Result<(), i64> {
+    let times = match hits.get_mut(key) {
+        Some(entry) => {
+            entry.window = window;
+            entry.prune(now);
+            &entry.times
+        }
+        None => return Ok(()),
+    };
+
+    match times.first() {
+        Some(first) if times.len() >= limit => Err(first + window - now),
+        _ => Ok(()),
+    }
+}
+
+fn push(hits: &mut HashMap<String, Hits>, key: &str, window: i64, now: i64) -> Result<(), i64> {
+    if !hits.contains_key(key) && hits.len() >= MAX_KEYS {
+        hits.retain(|_, entry| {
+            entry.prune(now);
+            !entry.times.is_empty()
+        });
+        if hits.len() >= MAX_KEYS {
+            // Until the first key runs out of hits
+            return Err(hits
+                .values()
+                .filter_map(|entry| entry.times.last().map(|last| last + entry.window - now))
+                .min()
+                .unwrap_or(window));
+        }
+    }
+
+    hits.entry(key.to_string())
+        .or_insert_with(|| Hits {
+            window,
+            times: Vec::new(),
+        })
+        .times
+        .push(now);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RateLimiter, MAX_KEYS};
+
+    #[test]
+    fn test_limit_and_expiry() {
+        let limiter = RateLimiter::new();
+        assert!(limiter.check("a", 2, 60, 100).is_ok());
+        assert!(limiter.check("a", 2, 60, 110).is_ok());
+        assert_eq!(limiter.check("a", 2, 60, 120), Err(40));
+
+        // Other keys are tracked separately
+        assert!(limiter.check("b", 2, 60, 120).is_ok());
+
+        // The first hit has left the window
+        assert!(limiter.check("a", 2, 60, 161).is_ok());
+    }
+
+    #[test]
+    fn test_peek_and_record() {
+        let limiter = RateLimiter::new();
+        assert!(limiter.peek("a", 1, 60, 100).is_ok());
+        // Peeking doesn't use up the limit
+        assert!(limiter.peek("a", 1, 60, 100).is_ok());
+
+        limiter.record("a", 60, 100);
+        assert_eq!(limiter.peek("a", 1, 60, 110), Err(50));
+        assert_eq!(limiter.check("a", 1, 60, 110), Err(50));
+    }
+
+    #[test]
+    fn test_mixed_windows() {
+        let limiter = RateLimiter::new();
+        assert!(limiter.check("hourly", 1, 3600, 100).is_ok());
+
+        // A key with a short window doesn't prune the hits of a longer one
+        assert!(limiter.check("short", 1, 300, 1000).is_ok());
+        assert!(limiter.peek("short", 1, 300, 1000).is_err());
+        assert_eq!(limiter.check("hourly", 1, 3600, 1000), Err(2700));
+
+        assert!(limiter.check("short", 1, 300, 1301).is_ok());
+        assert!(limiter.check("hourly", 1, 3600, 3701).is_ok());
+    }
+
+    #[test]
+    fn test_max_keys() {
+        let limiter = RateLimiter::new();
+        for i in 0..MAX_KEYS {
+            assert!(limiter.check(&i.to_string(), 1, 60, 100).is_ok());
+        }
+        assert_eq!(limiter.check("new", 1, 60, 110), Err(50));
+        // Known keys are still limited as usual
+        assert_eq!(limiter.check("0", 1, 60, 110), Err(50));
+
+        // Once the old hits have left their window there's room again
+        assert!(limiter.check("new", 1, 60, 160).is_ok());
+    }
+}
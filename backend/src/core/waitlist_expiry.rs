@@ -0,0 +1,100 @@
+use crate::core::sse::{Event, SSEClient};
+use crate::{config::Config, util::madness::Madness};
+use serde::Serialize;
+use std::sync::Arc;
+
+// Removes x-ups from pilots that haven't done anything for a while, so FCs don't invite AFK pilots
+pub struct WaitlistExpiry {
+    db: Arc<crate::DB>,
+    sse_client: SSEClient,
+    config: Config,
+}
+
+#[derive(Debug, Serialize)]
+struct Message {
+    message: &'static str,
+}
+
+impl WaitlistExpiry {
+    pub fn new(db: Arc<crate::DB>, config: Config) -> WaitlistExpiry {
+        WaitlistExpiry {
+            sse_client: SSEClient::new(
+                config.sse.url.clone(),
+                &hex::decode(&config.sse.secret).unwrap(),
+            ),
+            db,
+            config,
+        }
+    }
+
+    pub fn start(self) {
+        tokio::spawn(async move {
+            self.run().await;
+        });
+    }
+
+    async fn run(self) {
+        loop {
+            if let Err(e) = self.run_once().await {
+                error!("Error in waitlist expiry: {:#?}", e);
+            };
+
+            tokio::time::sleep(tokio::time::Duration::from_secs(60)).await;
+        }
+    }
+
+    fn get_db(&self) -> &crate::DB {
+        &self.db
+    }
+
+    async fn run_once(&self) -> Result<(), Madness> {
+        let cutoff = chrono::Utc::now().timestamp() - self.config.waitlist_expiry.max_idle;
+
+        let mut tx = self.get_db().begin().await?;
+        let expired = sqlx::query!(
+            "SELECT id, account_id, notify_on_expiry FROM waitlist_entry WHERE last_activity_at < $1",
+            cutoff
+        )
+        .fetch_all(&mut tx)
+        .await?;
+
+        if expired.is_empty() {
+            return Ok(());
+        }
+
+        for entry in &expired {
+            sqlx::query!("DELETE FROM waitlist_entry_fit WHERE entry_id=$1", entry.id)
+                .execute(&mut tx)
+                .await?;
+            sqlx::query!("DELETE FROM waitlist_entry WHERE id=$1", entry.id)
+                .execute(&mut tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        info!("Removed {} inactive waitlist entries", expired.len());
+
+        let mut events = vec![Event::new_json(
+            "waitlist",
+            "waitlist_update",
+            "waitlist_update",
+        )];
+        let topics: Vec<String> = expired
+            .iter()
+            .filter(|entry| entry.notify_on_expiry)
+            .map(|entry| format!("account;{}", entry.account_id))
+            .collect();
+        for topic in &topics {
+            events.push(Event::new_json(
+                topic,
+                "notification",
+                &Message {
+                    message: "You were removed from the waitlist for inactivity",
+                },
+            ));
+        }
+        self.sse_client.submit(events).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,74 @@
+use crate::data::categories;
+use crate::{config::Config, util::madness::Madness};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+pub struct MetricsRecorder {
+    db: Arc<crate::DB>,
+    config: Config,
+}
+
+impl MetricsRecorder {
+    pub fn new(db: Arc<crate::DB>, config: Config) -> MetricsRecorder {
+        MetricsRecorder { db, config }
+    }
+
+    pub fn start(self) {
+        tokio::spawn(async move {
+            self.run().await;
+        });
+    }
+
+    async fn run(self) {
+        let interval = tokio::time::Duration::from_secs(self.config.waitlist_metrics.interval);
+        loop {
+            if let Err(e) = self.run_once().await {
+                error!("Error in waitlist metrics: {:#?}", e);
+            };
+
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    fn get_db(&self) -> &crate::DB {
+        &self.db
+    }
+
+    async fn run_once(&self) -> Result<(), Madness> {
+        let now = chrono::Utc::now().timestamp();
+
+        let counts: HashMap<String, i64> = sqlx::query!(
+            "SELECT category, COUNT(*) AS \"count!\" FROM waitlist_entry_fit GROUP BY category"
+        )
+        .fetch_all(self.get_db())
+        .await?
+        .into_iter()
+        .map(|row| (row.category, row.count))
+        .collect();
+
+        // Record empty categories too, so the series don't have gaps
+        let mut tx = self.get_db().begin().await?;
+        for category in categories::categories() {
+            sqlx::query!(
+                "INSERT INTO waitlist_metrics (recorded_at, category, size) VALUES ($1, $2, $3)",
+                now,
+                category.id,
+                counts.get(&category.id).copied().unwrap_or(0)
+            )
+            .execute(&mut tx)
+            .await?;
+        }
+
+        let cutoff = now - self.config.waitlist_metrics.retention_days * 86400;
+        sqlx::query!(
+            "DELETE FROM waitlist_metrics WHERE recorded_at < $1",
+            cutoff
+        )
+        .execute(&mut tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,179 @@
+use chrono::TimeZone;
+use serde::Serialize;
+
+use crate::util::types::Ban;
+
+// Used when the config doesn't set discord.ban_template
+pub const DEFAULT_BAN_TEMPLATE: &str =
+    "**{entity_name}** ({entity_type}) has been banned by {issuer}.\nReason: {reason}\nExpires: {expiry}";
+
+const BAN_PLACEHOLDERS: [&str; 5] = ["entity_name", "entity_type", "issuer", "reason", "expiry"];
+
+pub struct WebhookClient {
+    http: reqwest::Client,
+    url: Option<String>,
+    ban_template: String,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum WebhookError {
+    #[error("HTTP error while contacting webhook")]
+    HTTPError(#[from] reqwest::Error),
+}
+
+#[derive(Debug, Serialize)]
+struct DiscordMessage<'a> {
+    content: &'a str,
+}
+
+impl WebhookClient {
+    // Fails if the ban template is invalid, so a bad config is caught at startup
+    pub fn new(url: Option<String>, ban_template: Option<String>) -> Result<WebhookClient, String> {
+        let ban_template = ban_template.unwrap_or_else(|| DEFAULT_BAN_TEMPLATE.to_string());
+        fill_template(&ban_template, |name| {
+            BAN_PLACEHOLDERS.contains(&name).then(String::new)
+        })?;
+
+        Ok(WebhookClient {
+            http: reqwest::Client::new(),
+            url,
+            ban_template,
+        })
+    }
+
+    pub fn is_configured(&self) -> bool {
+        self.url.is_some()
+    }
+
+    pub async fn send(&self, content: &str) -> Result<(), WebhookError> {
+        // Webhooks are optional, nothing to do if one isn't configured
+        let url = match &self.url {
+            Some(url) => url,
+            None => return Ok(()),
+        };
+
+        self.http
+            .post(url)
+            .json(&DiscordMessage { content })
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    // Only ever uses the public reason, as the message is posted to a public channel
+    pub fn ban_message(&self, ban: &Ban) -> String {
+        let (entity_name, entity_type) = match &ban.entity {
+            Some(entity) => (
+                entity.name.clone().unwrap_or_else(|| entity.id.to_string()),
+                entity.category.clone(),
+            ),
+            None => ("Unknown".to_string(), "Entity".to_string()),
+        };
+
+        let issuer = match ban.on_behalf_of.as_ref().or(ban.issued_by.as_ref()) {
+            Some(character) => character.name.clone(),
+            None => "Unknown".to_string(),
+        };
+
+        let expiry = match ban.revoked_at {
+            Some(revoked_at) => chrono::Utc
+                .timestamp(revoked_at, 0)
+                .format("%Y-%m-%d %H:%M EVE")
+                .to_string(),
+            None => "Never".to_string(),
+        };
+
+        let reason = ban
+            .public_reason
+            .clone()
+            .unwrap_or_else(|| "No reason given".to_string());
+
+        // The template was checked when the client was created
+        fill_template(&self.ban_template, |name| match name {
+            "entity_name" => Some(entity_name.clone()),
+            "entity_type" => Some(entity_type.clone()),
+            "issuer" => Some(issuer.clone()),
+            "reason" => Some(reason.clone()),
+            "expiry" => Some(expiry.clone()),
+            _ => None,
+        })
+        .unwrap_or_default()
+    }
+}
+
+// Replaces {placeholder}s in the template, {{ and }} are literal braces
+fn fill_template<F>(template: &str, value: F) -> Result<String, String>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut result = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                result.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                result.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => return Err(format!("Unclosed placeholder \"{{{}\"", name)),
+                    }
+                }
+                match value(&name) {
+                    Some(value) => result.push_str(&value),
+                    None => return Err(format!("Unknown placeholder \"{{{}}}\"", name)),
+                }
+            }
+            '}' => return Err("Unmatched \"}\", use \"}}\" for a literal brace".to_string()),
+            c => result.push(c),
+        }
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fill_template, WebhookClient, DEFAULT_BAN_TEMPLATE};
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "reason" => Some("Scamming".to_string()),
+            "issuer" => Some("Some FC".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_fill_template() {
+        assert_eq!(
+            fill_template("{issuer}: {reason}", lookup),
+            Ok("Some FC: Scamming".to_string())
+        );
+        assert_eq!(
+            fill_template("{{literal}} {reason}", lookup),
+            Ok("{literal} Scamming".to_string())
+        );
+        assert!(fill_template("{unknown}", lookup).is_err());
+        assert!(fill_template("{reason", lookup).is_err());
+        assert!(fill_template("reason}", lookup).is_err());
+    }
+
+    #[test]
+    fn test_ban_template_validation() {
+        assert!(WebhookClient::new(None, None).is_ok());
+        assert!(WebhookClient::new(None, Some(DEFAULT_BAN_TEMPLATE.to_string())).is_ok());
+        assert!(WebhookClient::new(None, Some("Banned: {name}".to_string())).is_err());
+    }
+}
@@ -48,6 +48,10 @@ pub fn categories() -> &'static Vec<WaitlistCategory> {
     &CATEGORY_DATA.categories
 }
 
+pub fn category_exists(id: &str) -> bool {
+    CATEGORY_DATA.categories.iter().any(|c| c.id == id)
+}
+
 pub fn rules() -> &'static Vec<(TypeID, String)> {
     &CATEGORY_DATA.rules
 }
@@ -1,4 +1,5 @@
 use regex::Regex;
+use serde::Serialize;
 use std::collections::{BTreeMap, BTreeSet};
 
 use eve_data_core::{Fitting, TypeID};
@@ -9,23 +10,66 @@ lazy_static::lazy_static! {
     static ref FITS: FitData = load_fits();
 }
 
+// How far a pilot's modules may deviate from the doctrine fit
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Tolerance {
+    // Only the listed modules (or same-tier alternatives)
+    Exact,
+    // Any tier of the listed modules, higher or lower
+    AllowMeta,
+    // Higher tiers of the listed modules, but not lower ones
+    AllowFactionUpgrade,
+}
+
+impl Tolerance {
+    fn parse(input: &str) -> Option<Tolerance> {
+        match input {
+            "exact" => Some(Tolerance::Exact),
+            "allow-meta" => Some(Tolerance::AllowMeta),
+            "allow-faction-upgrade" => Some(Tolerance::AllowFactionUpgrade),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Tolerance::AllowFactionUpgrade
+    }
+}
+
 #[derive(Debug)]
 pub struct DoctrineFit {
     pub name: String,
     pub fit: Fitting,
-    pub hidden: bool
+    pub hidden: bool,
+    pub tolerance: Tolerance,
 }
 
 fn load_fits() -> FitData {
     let mut fits = BTreeMap::new();
 
     let fit_data = std::fs::read_to_string("./data/fits.dat").expect("Could not load fits.dat");
-    let fit_regex = Regex::new(r#"<a href="fitting:([0-9:;_]+)" ?(hidden)?>([^<]+)</a>"#).unwrap();
+    let fit_regex = Regex::new(
+        r#"<a href="fitting:([0-9:;_]+)" ?(hidden)? ?(?:tolerance="([a-z-]+)")?>([^<]+)</a>"#,
+    )
+    .unwrap();
 
     for fit_match in fit_regex.captures_iter(&fit_data) {
         let dna = fit_match.get(1).unwrap().as_str();
         let is_hidden = fit_match.get(2);
-        let fit_name = fit_match.get(3).unwrap().as_str();
+        let fit_name = fit_match.get(4).unwrap().as_str();
+        let tolerance = match fit_match.get(3) {
+            None => Tolerance::default(),
+            Some(tolerance) => Tolerance::parse(tolerance.as_str()).unwrap_or_else(|| {
+                panic!(
+                    "Unknown tolerance \"{}\" for {}",
+                    tolerance.as_str(),
+                    fit_name
+                )
+            }),
+        };
         let parsed = Fitting::from_dna(dna).unwrap();
         fits.entry(parsed.hull)
             .or_insert_with(Vec::new)
@@ -33,6 +77,7 @@ fn load_fits() -> FitData {
                 name: fit_name.to_string(),
                 fit: parsed,
                 hidden: is_hidden.is_some(),
+                tolerance,
             });
     }
 
@@ -61,8 +106,21 @@ pub fn used_module_ids() -> Vec<TypeID> {
 
 #[cfg(test)]
 mod tests {
+    use super::Tolerance;
+
     #[test]
     fn test_load_fits() {
         let _loaded = super::get_fits();
     }
+
+    #[test]
+    fn test_parse_tolerance() {
+        assert_eq!(Tolerance::parse("exact"), Some(Tolerance::Exact));
+        assert_eq!(Tolerance::parse("allow-meta"), Some(Tolerance::AllowMeta));
+        assert_eq!(
+            Tolerance::parse("allow-faction-upgrade"),
+            Some(Tolerance::AllowFactionUpgrade)
+        );
+        assert_eq!(Tolerance::parse("casual"), None);
+    }
 }
@@ -114,3 +114,13 @@ pub async fn load_skills(
 
     Ok(Skills(result))
 }
+
+pub async fn has_gate_override(db: &crate::DB, character_id: i64) -> Result<bool, sqlx::Error> {
+    Ok(sqlx::query!(
+        "SELECT character_id FROM skill_gate_override WHERE character_id=$1",
+        character_id
+    )
+    .fetch_optional(db)
+    .await?
+    .is_some())
+}
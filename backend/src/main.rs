@@ -2,6 +2,7 @@ use std::{env, sync::Arc};
 use rocket::Request;
 
 mod app;
+mod compression;
 mod config;
 mod core;
 mod data;
@@ -54,10 +55,15 @@ tokio::runtime::Builder::new_multi_thread()
         .block_on(async {
             let options = sqlx::postgres::PgPoolOptions::new();
 
-            let config_file = env::var("WAITLIST_CONFIG").unwrap_or_else(|_| "./config.toml".to_string());
+            let config_file =
+                env::var("WAITLIST_CONFIG").unwrap_or_else(|_| "./config.toml".to_string());
             let raw_config = std::fs::read_to_string(&config_file).expect("Could not load config");
-            let config: config::Config = toml::from_str(&raw_config).expect("Could not load config");
-        
+            let config: config::Config =
+                toml::from_str(&raw_config).expect("Could not load config");
+            if let Err(err) = config.validate() {
+                panic!("Invalid config in {}: {}", config_file, err);
+            }
+
             let database = options
                 .idle_timeout(std::time::Duration::from_secs(config.database.idle_timeout))
                 .connect_timeout(std::time::Duration::from_secs(
@@ -82,12 +88,71 @@ tokio::runtime::Builder::new_multi_thread()
                 skill_updater.start();
             }
         
+            if config.waitlist_metrics.enable {
+                let metrics_recorder =
+                    core::waitlist_metrics::MetricsRecorder::new(database.clone(), config.clone());
+                metrics_recorder.start();
+            }
+
+            if config.waitlist_expiry.enable {
+                let waitlist_expiry =
+                    core::waitlist_expiry::WaitlistExpiry::new(database.clone(), config.clone());
+                waitlist_expiry.start();
+            }
+
+            if config.esi_warmup.enable {
+                let esi_warmup = core::esi_warmup::EsiWarmup::new(database.clone(), config.clone());
+                esi_warmup.start();
+            }
+
+            if config.bans.revoke_dissolved_corporations {
+                let dissolution_sweep =
+                    core::ban_dissolution::DissolutionSweep::new(database.clone(), config.clone());
+                dissolution_sweep.start();
+            }
+
+            // Also removes the Discord roles of scheduled bans once they start
+            if config.bans.notify_expiring || config.discord.role_removal_url.is_some() {
+                let ban_expiry_notifier =
+                    core::ban_expiry::BanExpiryNotifier::new(database.clone(), config.clone());
+                ban_expiry_notifier.start();
+            }
+
+            if config.bans.allow_pending_names {
+                let ban_name_refresher =
+                    core::ban_names::BanNameRefresher::new(database.clone(), config.clone());
+                ban_name_refresher.start();
+            }
+
+            if let Err(e) = core::ban_impact::ImpactJobRunner::resume(
+                database.clone(),
+                config.clone(),
+                Arc::new(util::clock::SystemClock),
+            )
+            .await
+            {
+                error!("Unable to resume ban impact jobs: {:#?}", e);
+            }
+
+            let ban_service =
+                core::ban::BanService::new(database.clone(), Arc::new(util::clock::SystemClock));
+            match ban_service.orphaned_references().await {
+                Ok(orphans) if !orphans.is_empty() => warn!(
+                    "{} ban columns point at missing characters, see GET /api/v2/bans/integrity",
+                    orphans.len()
+                ),
+                Ok(_) => (),
+                Err(e) => error!("Unable to check bans for missing characters: {:#?}", e),
+            }
+
+            let slow_threshold = std::time::Duration::from_millis(config.app.slow_request_ms);
             let application = app::new(database, config);
             rocket::build()
                 .register("/", catchers![not_authorized, forbidden, not_found])
                 .mount("/", routes::routes())
                 .manage(application)
-                .attach(request_logger::RequestLogger {})
+                .attach(request_logger::RequestLogger { slow_threshold })
+                .attach(compression::Compression { min_size: 1024 })
                 .launch()
                 .await
                 .unwrap();
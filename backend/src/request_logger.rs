@@ -1,11 +1,43 @@
-use std::time::Instant;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 
 use rocket::fairing::{Fairing, Kind};
+use rocket::request::{FromRequest, Outcome};
 
-pub struct RequestLogger {}
+pub struct RequestLogger {
+    // Requests slower than this are logged as warnings
+    pub slow_threshold: Duration,
+}
 
 struct RequestStart(Option<Instant>);
 
+// Microseconds spent in the database, as reported by handlers through SqlTimer
+#[derive(Default)]
+struct SqlTime(AtomicU64);
+
+// Lets DB-heavy handlers report their query time, it's logged along with the request
+pub struct SqlTimer<'r>(&'r SqlTime);
+
+impl SqlTimer<'_> {
+    pub async fn time<F: std::future::Future>(&self, query: F) -> F::Output {
+        let start = Instant::now();
+        let output = query.await;
+        (self.0)
+            .0
+            .fetch_add(start.elapsed().as_micros() as u64, Ordering::Relaxed);
+        output
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for SqlTimer<'r> {
+    type Error = ();
+
+    async fn from_request(req: &'r rocket::Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(SqlTimer(req.local_cache(SqlTime::default)))
+    }
+}
+
 #[rocket::async_trait]
 impl Fairing for RequestLogger {
     fn info(&self) -> rocket::fairing::Info {
@@ -24,14 +56,31 @@ impl Fairing for RequestLogger {
         if let Some(the_time) = start_time.0 {
             let elapsed = the_time.elapsed();
             let now = chrono::Utc::now();
+            let route = req
+                .route()
+                .map(|route| route.uri.to_string())
+                .unwrap_or_else(|| "-".to_string());
+            let sql =
+                Duration::from_micros(req.local_cache(SqlTime::default).0.load(Ordering::Relaxed));
             println!(
-                r#"[{}] {} "{}" {} {}"#,
+                r#"[{}] {} "{}" {} {} {} sql={}"#,
                 now,
                 req.method(),
                 req.uri(),
+                route,
                 res.status(),
-                elapsed.as_secs_f32()
+                elapsed.as_secs_f32(),
+                sql.as_secs_f32()
             );
+            if elapsed > self.slow_threshold {
+                warn!(
+                    "Slow request: {} {} took {:.3}s, {:.3}s in SQL",
+                    req.method(),
+                    route,
+                    elapsed.as_secs_f32(),
+                    sql.as_secs_f32()
+                );
+            }
         } else {
             error!("Cannot log request, on_request was not invoked?");
         }
@@ -152,25 +152,32 @@ async fn logout<'r>(
     ))
 }
 
-#[get("/api/auth/login_url?<alt>&<fc>")]
-fn login_url(alt: bool, fc: bool, app: &rocket::State<app::Application>) -> String {
+fn player_scopes() -> Vec<ESIScope> {
+    vec![
+        ESIScope::PublicData,
+        ESIScope::Skills_ReadSkills_v1,
+        ESIScope::Clones_ReadImplants_v1,
+    ]
+}
+
+fn fc_scopes() -> Vec<ESIScope> {
+    vec![
+        ESIScope::Fleets_ReadFleet_v1,
+        ESIScope::Fleets_WriteFleet_v1,
+        ESIScope::UI_OpenWindow_v1,
+        ESIScope::Search_v1,
+    ]
+}
+
+fn build_login_url(app: &app::Application, alt: bool, fc: bool) -> String {
     let state = match alt {
         true => "alt",
         false => "normal",
     };
 
-    let mut scopes = vec![
-        ESIScope::PublicData,
-        ESIScope::Skills_ReadSkills_v1,
-        ESIScope::Clones_ReadImplants_v1,
-    ];
+    let mut scopes = player_scopes();
     if fc {
-        scopes.extend(vec![
-            ESIScope::Fleets_ReadFleet_v1,
-            ESIScope::Fleets_WriteFleet_v1,
-            ESIScope::UI_OpenWindow_v1,
-            ESIScope::Search_v1,
-        ])
+        scopes.extend(fc_scopes())
     }
 
     format!(
@@ -182,6 +189,83 @@ fn login_url(alt: bool, fc: bool, app: &rocket::State<app::Application>) -> Stri
     )
 }
 
+#[get("/api/auth/login_url?<alt>&<fc>")]
+fn login_url(alt: bool, fc: bool, app: &rocket::State<app::Application>) -> String {
+    build_login_url(app, alt, fc)
+}
+
+// Features that stop working without a scope, and whether only FCs use them
+const SCOPE_FEATURES: &[(&str, ESIScope, bool)] = &[
+    ("Skill checks", ESIScope::Skills_ReadSkills_v1, false),
+    ("Implant checks", ESIScope::Clones_ReadImplants_v1, false),
+    ("Fleet management", ESIScope::Fleets_ReadFleet_v1, true),
+    (
+        "Fleet invites and squad moves",
+        ESIScope::Fleets_WriteFleet_v1,
+        true,
+    ),
+    ("Opening windows in game", ESIScope::UI_OpenWindow_v1, true),
+    ("Structure search", ESIScope::Search_v1, true),
+];
+
+#[derive(Serialize)]
+struct MissingFeature {
+    feature: &'static str,
+    scope: &'static str,
+}
+
+#[derive(Serialize)]
+struct ScopesResponse {
+    granted: Vec<String>,
+    missing: Vec<MissingFeature>,
+    reauth_url: Option<String>,
+}
+
+#[get("/api/v2/me/scopes")]
+async fn my_scopes(
+    app: &rocket::State<app::Application>,
+    account: AuthenticatedAccount,
+) -> Result<Json<ScopesResponse>, Madness> {
+    let granted: Vec<String> = match sqlx::query!(
+        "SELECT scopes FROM refresh_token WHERE character_id=$1",
+        account.id
+    )
+    .fetch_optional(app.get_db())
+    .await?
+    {
+        Some(token) => token
+            .scopes
+            .split(' ')
+            .filter(|scope| !scope.is_empty())
+            .map(|scope| scope.to_string())
+            .sorted()
+            .collect(),
+        None => Vec::new(),
+    };
+
+    let is_fc = account.access.contains("fleet-configure");
+    let missing: Vec<MissingFeature> = SCOPE_FEATURES
+        .iter()
+        .filter(|(_feature, _scope, fc_only)| is_fc || !fc_only)
+        .filter(|(_feature, scope, _fc_only)| !granted.iter().any(|g| g == scope.as_str()))
+        .map(|(feature, scope, _fc_only)| MissingFeature {
+            feature: *feature,
+            scope: scope.as_str(),
+        })
+        .collect();
+
+    let reauth_url = match missing.is_empty() {
+        true => None,
+        false => Some(build_login_url(app, false, is_fc)),
+    };
+
+    Ok(Json(ScopesResponse {
+        granted,
+        missing,
+        reauth_url,
+    }))
+}
+
 #[derive(Deserialize)]
 struct CallbackData<'r> {
     code: &'r str,
@@ -270,5 +354,12 @@ async fn callback(
 }
 
 pub fn routes() -> Vec<rocket::Route> {
-    routes![whoami, logout, login_url, callback, set_wiki_passwd]
+    routes![
+        whoami,
+        logout,
+        login_url,
+        my_scopes,
+        callback,
+        set_wiki_passwd
+    ]
 }
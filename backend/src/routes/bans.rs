@@ -1,270 +1,5699 @@
 use crate::{
     app::Application,
-    core::auth::AuthenticatedAccount,
+    config::BansConfig,
+    core::{
+        auth::{authorize_character, AuthenticatedAccount},
+        ban::{
+            auto_tags, normalize_tags, parse_reason_tag, replace_tags, unique_ids, ActiveFilter,
+            BanCursor, BanException, ChangeType, Extension, OrphanedReference, SimilarBan,
+            NAME_PATTERN, NAME_PATTERN_ENTITY_ID,
+        },
+        ban_impact::{self, CascadePreview, ImpactJobRunner},
+        ban_import::{self, ImportDiff, ImportedBan},
+        ban_purge::{PurgeToken, PURGE_TOKEN_TTL},
+        esi::ESIError,
+        idempotency::IdempotencyKey,
+        name_pattern,
+        sse::Event,
+    },
+    request_logger::SqlTimer,
     util::{
+        api_version::ApiVersion,
+        client_ip::ClientIp,
+        etag::{IfNoneMatch, Tagged},
+        list_params::{self, ListParams, SortOrder},
         madness::Madness,
-        types::{Ban, Character, Entity},
+        types::{
+            iso_timestamp, parse_timezone, Ban, BanDifference, BanVisibility, BulkResult, Entity,
+            EntityType, LocalBan, ReasonEdit,
+        },
     },
 };
 
+use std::collections::BTreeMap;
+
+use rocket::http::Header;
+use rocket::response::{
+    status::{Created, NoContent},
+    Redirect,
+};
 use rocket::serde::json::Json;
-use serde::Deserialize;
-use sqlx::types::chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+// Column limits of the ban table, see sql/postgres.sql
+const REASON_MAX_LENGTH: usize = 512;
+const PUBLIC_REASON_MAX_LENGTH: usize = 512;
+const CATEGORY_MAX_LENGTH: usize = 32;
+const FC_NOTE_MAX_LENGTH: usize = 512;
+const ENTITY_NAME_MAX_LENGTH: usize = 64;
+
+// Stops the announce endpoint being used to spam the channel, counted per FC across all bans
+const ANNOUNCE_LIMIT: usize = 3;
+const ANNOUNCE_WINDOW: i64 = 60 * 5;
+
+const LIST_DEFAULT_LIMIT: i64 = 50;
+// Bans also start and end on their own, without a change to bump bans_generation
+const LIST_ETAG_WINDOW: i64 = 60;
+
+const RECENT_DEFAULT_COUNT: i64 = 10;
+const RECENT_MAX_COUNT: i64 = 50;
+
+const SUGGESTION_MAX_COUNT: usize = 5;
+
+const SIMILAR_DEFAULT_COUNT: i64 = 10;
+const SIMILAR_MAX_COUNT: i64 = 50;
+// Trigrams say little about shorter reasons
+const SIMILAR_MIN_LENGTH: usize = 3;
+
+// Drafts are free-form, these keep one account from filling the table
+const DRAFT_MAX_SIZE: usize = 16 * 1024;
+const DRAFT_MAX_COUNT: i64 = 20;
+
+const PUBLIC_CHECK_WINDOW: i64 = 60 * 60;
+
+// ESI resolves at most this many affiliations per request
+const CHECK_MAX_COUNT: usize = 1000;
+
+const BULK_MAX_COUNT: usize = 1000;
+
+const CHANGES_DEFAULT_LIMIT: i64 = 100;
+const CHANGES_MAX_LIMIT: i64 = 500;
+
+// Anything shorter rarely says more than "rmt" or "see discord"
+const INCOMPLETE_DEFAULT_MIN_LENGTH: i32 = 20;
+
+// Bots poll the status endpoint, a slightly stale answer is fine
+const STATUS_MAX_AGE: u32 = 30;
+
+#[derive(Deserialize)]
+struct CreateBanRequest {
+    #[serde(flatten)]
+    ban: Ban,
+    on_behalf_of: Option<i64>,
+    // Use the internal reason as the public one too
+    #[serde(default)]
+    mirror_reason: bool,
+}
 
 #[derive(Deserialize)]
 struct EsiResponse {
     name: String,
 }
 
-#[get("/api/v2/bans")]
-async fn list(
-    account: AuthenticatedAccount,
-    app: &rocket::State<Application>,
-) -> Result<Json<Vec<Ban>>, Madness> {
-    account.require_access("bans-manage")?;
+fn validate_field_length(field: &str, value: &str, max_length: usize) -> Result<(), Madness> {
+    // VARCHAR limits are counted in characters, not bytes
+    if value.chars().count() > max_length {
+        return Err(Madness::BadRequest(format!(
+            "\"{}\" cannot be longer than {} characters",
+            field, max_length
+        )));
+    }
+    Ok(())
+}
 
-    let now = Utc::now().timestamp();
+fn validate_reasons(config: &BansConfig, ban: &Ban) -> Result<(), Madness> {
+    let public_reason_blank = ban
+        .public_reason
+        .as_deref()
+        .map_or(true, |reason| reason.trim().is_empty());
+    // Whatever the settings, a ban has to say why to someone
+    if ban.reason.trim().is_empty() && public_reason_blank {
+        return Err(Madness::BadRequest(
+            "Either \"reason\" or \"public_reason\" is required".to_string(),
+        ));
+    }
+    if config.require_reason && ban.reason.trim().is_empty() {
+        return Err(Madness::BadRequest("\"reason\" is required".to_string()));
+    }
+    // Every ban is player visible, banned pilots see the public reason when they try to log in,
+    // through the public check and when acknowledging
+    if config.require_public_reason && public_reason_blank {
+        return Err(Madness::BadRequest(
+            "\"public_reason\" is required, players are shown it".to_string(),
+        ));
+    }
 
-    let rows = sqlx::query!(
-        "SELECT
-	        ban.id,
-	        entity_id,
-	        entity_name,
-	        entity_type,
-	        issued_at,
-	        public_reason,
-	        reason,
-	        revoked_at,
-	        issuer.id AS \"issued_by_id\",
-	        issuer.name AS \"issued_by_name\"
-        FROM
-	        ban
-        JOIN
-	        character as issuer ON issued_by=issuer.id
-        WHERE
-            revoked_at IS NULL OR revoked_at > $1",
-        now
+    validate_field_length("reason", &ban.reason, REASON_MAX_LENGTH)?;
+    if let Some(public_reason) = &ban.public_reason {
+        validate_field_length("public_reason", public_reason, PUBLIC_REASON_MAX_LENGTH)?;
+    }
+    if let Some(category) = &ban.category {
+        validate_field_length("category", category, CATEGORY_MAX_LENGTH)?;
+    }
+    if let Some(fc_note) = &ban.fc_note {
+        validate_field_length("fc_note", fc_note, FC_NOTE_MAX_LENGTH)?;
+    }
+    Ok(())
+}
+
+// A scheduled ban has to start in the future, and before it would expire
+fn validate_starts_at(
+    app: &Application,
+    starts_at: i64,
+    expiry_day: Option<i64>,
+) -> Result<(), Madness> {
+    if starts_at <= app.clock.now().timestamp() {
+        return Err(Madness::UnprocessableEntity(
+            "\"starts_at\" has to be in the future, leave it out to ban now".to_string(),
+        ));
+    }
+    if expiry_day.map_or(false, |day| app.ban_service.expires_at(day) <= starts_at) {
+        return Err(Madness::UnprocessableEntity(
+            "The ban would expire before it starts".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// A ban that has already expired would never be in effect, revoke it instead
+fn validate_expiry(app: &Application, expiry_day: Option<i64>) -> Result<(), Madness> {
+    let now = app.clock.now().timestamp();
+    if expiry_day.map_or(false, |day| app.ban_service.expires_at(day) <= now) {
+        return Err(Madness::UnprocessableEntity(
+            "The expiry has already passed".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// Stops an old session of an inactive FC from being used to change bans, see bans.max_login_age
+fn require_recent_login(app: &Application, account: &AuthenticatedAccount) -> Result<(), Madness> {
+    let config = &app.config.bans;
+    let oldest = app.clock.now().timestamp() - config.max_login_age * 3600;
+    if config.require_recent_login && account.logged_in_at < oldest {
+        return Err(Madness::Forbidden(
+            "Your login is too old to change bans, please log in again".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// Keeps FCs from changing each other's fresh bans, see bans.ownership_window. Edits leave the
+// issuer and issued_at alone, so the window always runs from when the ban was created.
+fn require_ownership(
+    app: &Application,
+    account: &AuthenticatedAccount,
+    ban: &Ban,
+) -> Result<(), Madness> {
+    let config = &app.config.bans;
+    if !config.enforce_ownership || account.access.contains("bans-admin") {
+        return Ok(());
+    }
+    let owner = match ban.on_behalf_of.as_ref().or(ban.issued_by.as_ref()) {
+        Some(owner) => owner,
+        None => return Ok(()),
+    };
+    let is_owner = [&ban.issued_by, &ban.on_behalf_of]
+        .iter()
+        .any(|character| character.as_ref().map_or(false, |c| c.id == account.id));
+    let remaining =
+        ban.issued_at.unwrap_or(0) + config.ownership_window * 3600 - app.clock.now().timestamp();
+    if !is_owner && remaining > 0 {
+        return Err(Madness::Forbidden(format!(
+            "This ban belongs to {} for another {} hour(s), only they or a ban admin can change it",
+            owner.name,
+            (remaining + 3599) / 3600
+        )));
+    }
+    Ok(())
+}
+
+// A missing ban is left for the caller to report
+async fn require_ban_ownership(
+    app: &Application,
+    account: &AuthenticatedAccount,
+    ban_id: i64,
+) -> Result<(), Madness> {
+    if !app.config.bans.enforce_ownership {
+        return Ok(());
+    }
+    match app.ban_service.find(ban_id).await? {
+        Some(ban) => require_ownership(app, account, &ban),
+        None => Ok(()),
+    }
+}
+
+// Accounts are keyed by their main character, so both ban types can hit the FC's own account
+fn validate_not_self(account: &AuthenticatedAccount, entity: &Entity) -> Result<(), Madness> {
+    if (entity.category == "Character" || entity.category == "Account") && entity.id == account.id {
+        return Err(Madness::UnprocessableEntity(
+            "You cannot ban yourself".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn has_configured_access(account: &AuthenticatedAccount, key: &str) -> bool {
+    key.is_empty() || account.access.contains(key)
+}
+
+// Which reasons the account may read, anyone who can read the internal reason can read the public one.
+// The FC note isn't configurable, it's only for FCs who manage bans.
+fn ban_visibility(
+    app: &Application,
+    account: &AuthenticatedAccount,
+) -> Result<BanVisibility, Madness> {
+    let reason = has_configured_access(account, &app.config.bans.reason_access);
+    let visibility = BanVisibility {
+        reason,
+        public_reason: reason
+            || has_configured_access(account, &app.config.bans.public_reason_access),
+        fc_note: account.access.contains("bans-manage"),
+    };
+
+    if !visibility.public_reason {
+        return Err(Madness::AccessDenied);
+    }
+    Ok(visibility)
+}
+
+// Nobody is told about a ban before it starts, except those who can read the internal reason.
+// Every route that can return a scheduled ban filters with this.
+fn shown_to(visibility: BanVisibility, ban: &Ban, now: i64) -> bool {
+    visibility.reason || ban.starts_at.map_or(true, |starts_at| starts_at <= now)
+}
+
+// Relative timestamps are opt-in, as they go stale as soon as the response is cached
+fn relative_now(app: &Application, relative: Option<bool>) -> Option<i64> {
+    match relative {
+        Some(true) => Some(app.clock.now().timestamp()),
+        _ => None,
+    }
+}
+
+// Only changes when a ban does, see Application::bans_changed, or once the window passes. The
+// rest is whatever else the list depends on.
+fn list_etag(
+    app: &Application,
+    visibility: BanVisibility,
+    version: ApiVersion,
+    rest: &str,
+) -> String {
+    let key = format!(
+        "{}:{}:{:?}:{:?}:{}",
+        app.bans_generation(),
+        app.clock.now().timestamp() / LIST_ETAG_WINDOW,
+        visibility,
+        version,
+        rest
+    );
+    format!("\"{}\"", hex::encode(&Sha256::digest(key.as_bytes())[..16]))
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum BanList {
+    All(Vec<LocalBan>),
+    Page {
+        bans: Vec<LocalBan>,
+        next_cursor: Option<String>,
+    },
+    // For clients that want the paging details in the body
+    Envelope {
+        items: Vec<LocalBan>,
+        total: i64,
+        limit: Option<i64>,
+        offset: i64,
+        next_cursor: Option<String>,
+    },
+}
+
+// Looks an FC up by name among the characters that have issued bans, so no ESI call is needed.
+// An exact (case insensitive) name wins, otherwise the name has to be the start of just one.
+async fn resolve_issuer(app: &Application, name: &str) -> Result<i64, Madness> {
+    let pattern = format!("{}%", list_params::escape_like(name.trim()));
+    let candidates = sqlx::query!(
+        "SELECT id, name FROM character
+        WHERE name ILIKE $1
+            AND id IN (SELECT issued_by FROM ban UNION SELECT on_behalf_of FROM ban)
+        ORDER BY name LIMIT 10",
+        pattern
     )
     .fetch_all(app.get_db())
     .await?;
 
-    let bans = rows
+    if let Some(exact) = candidates
+        .iter()
+        .find(|candidate| candidate.name.eq_ignore_ascii_case(name.trim()))
+    {
+        return Ok(exact.id);
+    }
+    match candidates.as_slice() {
+        [] => Err(Madness::NotFound("No FC has issued bans under that name")),
+        [only] => Ok(only.id),
+        _ => Err(Madness::BadRequest(format!(
+            "\"issued_by_name\" matches more than one FC: {}",
+            candidates
+                .iter()
+                .map(|candidate| format!("{} ({})", candidate.name, candidate.id))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))),
+    }
+}
+
+// Without limit, offset or cursor every active ban is returned as a plain list, otherwise as
+// { bans, next_cursor }. envelope=true always gives { items, total, limit, offset, next_cursor },
+// where limit is null when every ban is returned. Repeat tag to only get bans with all of the tags.
+// expiring_within only returns the temporary bans that end within that many seconds.
+// Responses carry an ETag unless relative is set, send it back in If-None-Match to get a 304.
+// sort=issued_at and order=asc|desc (newest first by default) order the list, search matches
+// entity names. include_scheduled=true also lists the bans that haven't started yet, for staff.
+#[get("/api/v2/bans?<tz>&<relative>&<cursor>&<tag>&<envelope>&<expiring_within>&<issued_by>&<issued_by_name>&<include_scheduled>")]
+#[allow(clippy::too_many_arguments)]
+async fn list(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    version: ApiVersion,
+    sql: SqlTimer<'_>,
+    if_none_match: IfNoneMatch,
+    tz: Option<&str>,
+    relative: Option<bool>,
+    params: Result<ListParams, String>,
+    cursor: Option<&str>,
+    tag: Vec<String>,
+    envelope: Option<bool>,
+    expiring_within: Option<i64>,
+    issued_by: Option<i64>,
+    issued_by_name: Option<&str>,
+    include_scheduled: Option<bool>,
+) -> Result<Tagged<Json<BanList>>, Madness> {
+    let params = params.map_err(Madness::BadRequest)?;
+    let envelope = envelope.unwrap_or(false);
+    let include_scheduled = include_scheduled.unwrap_or(false);
+    let visibility = ban_visibility(app, &account)?;
+    let issued_by = match (issued_by, issued_by_name) {
+        (Some(_), Some(_)) => {
+            return Err(Madness::BadRequest(
+                "Pass either issued_by or issued_by_name".to_string(),
+            ))
+        }
+        (None, Some(name)) => Some(resolve_issuer(app, name).await?),
+        (issued_by, None) => issued_by,
+    };
+    // Relative timestamps change every second
+    let etag = match relative {
+        Some(true) => None,
+        _ => Some(list_etag(
+            app,
+            visibility,
+            version,
+            &format!(
+                "{:?}",
+                (
+                    tz,
+                    &params,
+                    cursor,
+                    &tag,
+                    envelope,
+                    expiring_within,
+                    issued_by,
+                    include_scheduled
+                )
+            ),
+        )),
+    };
+    if let Some(etag) = etag.as_ref().filter(|etag| if_none_match.matches(etag)) {
+        return Ok(Tagged::not_modified(etag.clone()));
+    }
+
+    // Tags are internal, filtering by them would give them away
+    if !tag.is_empty() && !visibility.reason {
+        return Err(Madness::AccessDenied);
+    }
+    // See shown_to
+    if include_scheduled && !visibility.reason {
+        return Err(Madness::AccessDenied);
+    }
+    let tags = normalize_tags(&tag).map_err(Madness::BadRequest)?;
+    if expiring_within.map_or(false, |window| window < 0) {
+        return Err(Madness::BadRequest(
+            "expiring_within cannot be negative".to_string(),
+        ));
+    }
+    let sort = params.sort_by(&["issued_at"])?;
+    let ascending = params.order == Some(SortOrder::Asc);
+    let filter = ActiveFilter {
+        tags: &tags,
+        expiring_within,
+        issued_by,
+        search: params.search.as_deref(),
+        include_scheduled,
+    };
+    let tz = parse_timezone(tz);
+    let status_at = app.clock.now().timestamp();
+    let now = relative_now(app, relative);
+
+    if params.limit.is_none() && params.offset.is_none() && cursor.is_none() {
+        let mut bans = sql.time(app.ban_service.all_active(&filter)).await?;
+        if sort.is_some() || params.order.is_some() {
+            bans.sort_by_key(|ban| (ban.issued_at, ban.id));
+            if !ascending {
+                bans.reverse();
+            }
+        }
+        let bans: Vec<LocalBan> = bans
+            .into_iter()
+            .map(|ban| {
+                ban.redact(visibility)
+                    .in_timezone(tz, status_at)
+                    .relative_to(now)
+                    .at_version(version)
+            })
+            .collect();
+
+        if envelope {
+            return Ok(Tagged::fresh(
+                Json(BanList::Envelope {
+                    total: bans.len() as i64,
+                    items: bans,
+                    limit: None,
+                    offset: 0,
+                    next_cursor: None,
+                }),
+                etag,
+            ));
+        }
+        return Ok(Tagged::fresh(Json(BanList::All(bans)), etag));
+    }
+
+    let after = match cursor {
+        Some(cursor) => match BanCursor::decode(cursor) {
+            Some(cursor) => Some(cursor),
+            None => return Err(Madness::BadRequest("Invalid cursor".to_string())),
+        },
+        None => None,
+    };
+    let limit = params.limit.unwrap_or(LIST_DEFAULT_LIMIT);
+    // The cursor already says where the page starts
+    let offset = match after {
+        Some(_) => 0,
+        None => params.offset.unwrap_or(0),
+    };
+
+    let bans = sql
+        .time(
+            app.ban_service
+                .active_page(limit, offset, after, ascending, &filter),
+        )
+        .await?;
+    let next_cursor = match bans.last() {
+        Some(last) if bans.len() as i64 == limit => BanCursor::after(last).map(|c| c.encode()),
+        _ => None,
+    };
+
+    let bans = bans
         .into_iter()
-        .map(|ban| Ban {
-            id: Some(ban.id.unwrap()),
-            entity: Some(Entity {
-                id: ban.entity_id.unwrap(),
-                name: ban.entity_name,
-                category: ban.entity_type.unwrap(),
-            }),
-            issued_at: Some(ban.issued_at.unwrap()),
-            issued_by: Some(Character {
-                id: ban.issued_by_id.unwrap(),
-                name: ban.issued_by_name.unwrap(),
-                corporation_id: None,
-            }),
-            reason: ban.reason.unwrap(),
-            public_reason: ban.public_reason,
-            revoked_at: ban.revoked_at,
-            revoked_by: None,
+        .map(|ban| {
+            ban.redact(visibility)
+                .in_timezone(tz, status_at)
+                .relative_to(now)
+                .at_version(version)
         })
         .collect();
 
-    return Ok(Json(bans));
+    if envelope {
+        return Ok(Tagged::fresh(
+            Json(BanList::Envelope {
+                items: bans,
+                total: sql.time(app.ban_service.count_active(&filter)).await?,
+                limit: Some(limit),
+                offset,
+                next_cursor,
+            }),
+            etag,
+        ));
+    }
+    Ok(Tagged::fresh(
+        Json(BanList::Page { bans, next_cursor }),
+        etag,
+    ))
 }
 
-#[post("/api/v2/bans", data = "<req_body>")]
-async fn create(
+#[derive(Serialize)]
+struct RecentBan {
+    id: i64,
+    entity: Entity,
+    issued_at: i64,
+    issued_by_name: String,
+    public_reason: Option<String>,
+}
+
+// Past bans with reasons like the one an FC is writing, so the wording and duration can follow
+// precedent
+#[get("/api/v2/bans/similar?<reason>&<entity_type>&<count>")]
+async fn similar(
     account: AuthenticatedAccount,
     app: &rocket::State<Application>,
-    req_body: Json<Ban>,
-) -> Result<&'static str, Madness> {
+    reason: &str,
+    entity_type: Option<&str>,
+    count: Option<i64>,
+) -> Result<Json<Vec<SimilarBan>>, Madness> {
     account.require_access("bans-manage")?;
+    // The matches are internal reasons
+    if !ban_visibility(app, &account)?.reason {
+        return Err(Madness::AccessDenied);
+    }
 
-    let now = Utc::now().timestamp();
-
-    if let None = &req_body.entity {
+    let reason = reason.trim();
+    if reason.chars().count() < SIMILAR_MIN_LENGTH {
         return Err(Madness::BadRequest(format!(
-            "One or more body paramaters are missing: [\"{}\", \"{}\", \"{}\"]",
-            "id", "name", "kind"
+            "\"reason\" needs at least {} characters",
+            SIMILAR_MIN_LENGTH
         )));
     }
+    if let Some(entity_type) = entity_type {
+        if EntityType::parse(entity_type).is_none() && entity_type != NAME_PATTERN {
+            return Err(Madness::BadRequest(format!(
+                "Unknown entity type {}",
+                entity_type
+            )));
+        }
+    }
+    let count = count
+        .unwrap_or(SIMILAR_DEFAULT_COUNT)
+        .clamp(1, SIMILAR_MAX_COUNT);
 
-    let e = req_body.entity.as_ref().unwrap();
-    let esi_res: EsiResponse = app
-        .esi_client
-        .get_unauthenticated(&format!(
-            "/latest/{}s/{}",
-            req_body.entity.as_ref().unwrap().category.to_lowercase(),
-            req_body.entity.as_ref().unwrap().id
-        ))
-        .await?;
+    Ok(Json(
+        app.ban_service.similar(reason, entity_type, count).await?,
+    ))
+}
 
-    // Stop FCs from banning other FCs
-    // See: https://github.com/Contingency-Incursions/legacy-waitlist/issues/43
-    if let Some(admin) = sqlx::query!(
-        "SELECT * FROM admin WHERE character_id=$1",
-        e.id
-    )
-    .fetch_optional(app.get_db())
-    .await? {
-        return Err(Madness::BadRequest(format!(
-            "{} accounts cannot be banned.",
-            admin.role
-        )));
-    }
+// Pending and scheduled bans are left out, they haven't taken effect yet
+#[get("/api/v2/bans/recent?<count>")]
+async fn recent(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    count: Option<i64>,
+) -> Result<Json<Vec<RecentBan>>, Madness> {
+    account.require_access("bans-manage")?;
 
-    let expires_at = match req_body.revoked_at.as_ref() {
-        None => None,
-        Some(day) => {
-            let downtime = 60 * 60 * 11;
-            Some(day + downtime)
-        }
-    };
+    let count = count
+        .unwrap_or(RECENT_DEFAULT_COUNT)
+        .clamp(1, RECENT_MAX_COUNT);
 
-    sqlx::query!(
-        "INSERT INTO ban (entity_type, entity_id, entity_name, issued_at, issued_by, reason, public_reason, revoked_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
-        e.category,
-        e.id,
-        esi_res.name,
-        now,
-        account.id,
-        req_body.reason,
-        req_body.public_reason,
-        expires_at,
+    let bans = sqlx::query!(
+        "SELECT
+            ban.id,
+            entity_id,
+            entity_name,
+            entity_type,
+            issued_at,
+            public_reason,
+            issuer.name AS \"issued_by_name\"
+        FROM
+            ban
+        JOIN
+            character as issuer ON issued_by=issuer.id
+        WHERE
+            NOT pending_approval AND (starts_at IS NULL OR starts_at <= $2)
+        ORDER BY
+            issued_at DESC, ban.id DESC
+        LIMIT $1",
+        count,
+        app.clock.now().timestamp()
     )
-    .execute(app.get_db())
-    .await?;
+    .fetch_all(app.get_db())
+    .await?
+    .into_iter()
+    .map(|ban| RecentBan {
+        id: ban.id,
+        entity: Entity {
+            id: ban.entity_id,
+            name: ban.entity_name,
+            category: ban.entity_type,
+        },
+        issued_at: ban.issued_at,
+        issued_by_name: ban.issued_by_name,
+        public_reason: ban.public_reason,
+    })
+    .collect();
 
-    Ok("Ok")
+    Ok(Json(bans))
 }
 
-#[get("/api/v2/bans/<character_id>")]
-async fn character_history(
+// The FC's own bans, newest first, so they can check what they've done recently
+#[get("/api/v2/bans/mine?<tz>&<relative>&<limit>")]
+async fn mine(
     account: AuthenticatedAccount,
     app: &rocket::State<Application>,
-    character_id: i64,
-) -> Result<Json<Vec<Ban>>, Madness> {
+    version: ApiVersion,
+    tz: Option<&str>,
+    relative: Option<bool>,
+    limit: Option<i64>,
+) -> Result<Json<Vec<LocalBan>>, Madness> {
     account.require_access("bans-manage")?;
 
-    if let Some(bans) = app.ban_service.all_bans(character_id, "Character").await? {
-        return Ok(Json(bans));
-    }
+    let visibility = ban_visibility(app, &account)?;
+    let tz = parse_timezone(tz);
+    let status_at = app.clock.now().timestamp();
+    let now = relative_now(app, relative);
+    let limit = limit
+        .unwrap_or(LIST_DEFAULT_LIMIT)
+        .clamp(1, list_params::MAX_LIMIT);
 
-    Ok(Json(Vec::new()))
+    Ok(Json(
+        app.ban_service
+            .issued_by(account.id, limit)
+            .await?
+            .into_iter()
+            .filter(|ban| shown_to(visibility, ban, status_at))
+            .map(|ban| {
+                ban.redact(visibility)
+                    .in_timezone(tz, status_at)
+                    .relative_to(now)
+                    .at_version(version)
+            })
+            .collect(),
+    ))
 }
 
-#[patch("/api/v2/bans/<ban_id>", data = "<req_body>")]
-async fn update(
+#[derive(Serialize)]
+struct ChangedBan {
+    change: ChangeType,
+    changed_at: i64,
+    ban: LocalBan,
+}
+
+#[derive(Serialize)]
+struct ChangeFeed {
+    changes: Vec<ChangedBan>,
+    // Passed back as since and after to get the next page, or to poll again later
+    next_since: i64,
+    next_after: Option<i64>,
+    has_more: bool,
+}
+
+// Bans created, updated or ended after since, for partners syncing our list without fetching
+// all of it every time. A ban only shows up once per page, with its latest change.
+#[get("/api/v2/bans/changes?<since>&<after>&<limit>&<tz>")]
+async fn changes(
     account: AuthenticatedAccount,
     app: &rocket::State<Application>,
-    ban_id: i64,
-    req_body: Json<Ban>,
-) -> Result<&'static str, Madness> {
-    account.require_access("bans-manage")?;
+    version: ApiVersion,
+    since: i64,
+    after: Option<i64>,
+    limit: Option<i64>,
+    tz: Option<&str>,
+) -> Result<Json<ChangeFeed>, Madness> {
+    let visibility = ban_visibility(app, &account)?;
+    let tz = parse_timezone(tz);
+    let status_at = app.clock.now().timestamp();
+    let limit = limit
+        .unwrap_or(CHANGES_DEFAULT_LIMIT)
+        .clamp(1, CHANGES_MAX_LIMIT);
 
-    let now = Utc::now().timestamp();
+    let changes = app.ban_service.changes(since, after, limit).await?;
+    let has_more = changes.len() as i64 == limit;
+    let (next_since, next_after) = match changes.last() {
+        Some(last) => (last.changed_at, last.ban.id),
+        None => (since, after),
+    };
 
-    if let None = sqlx::query!(
-        "SELECT * FROM ban WHERE id=$1 AND (revoked_at IS NULL OR revoked_at > $2)",
-        ban_id,
-        now
-    )
-    .fetch_optional(app.get_db())
-    .await?
-    {
-        return Err(Madness::BadRequest(format!(
-            "Cannot revoke invalid ban. It is either invalid or doesn't exist"
-        )));
+    Ok(Json(ChangeFeed {
+        changes: changes
+            .into_iter()
+            .filter(|change| shown_to(visibility, &change.ban, status_at))
+            .map(|change| ChangedBan {
+                change: change.change,
+                changed_at: change.changed_at,
+                ban: change
+                    .ban
+                    .redact(visibility)
+                    .in_timezone(tz, status_at)
+                    .at_version(version),
+            })
+            .collect(),
+        next_since,
+        next_after,
+        has_more,
+    }))
+}
+
+// Active bans that are poorly documented, for going back and filling the reasons in. There's no
+// evidence to link to bans yet, so only the reason is checked.
+#[get("/api/v2/bans/incomplete?<min_length>&<tz>&<limit>")]
+async fn incomplete(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    version: ApiVersion,
+    min_length: Option<i32>,
+    tz: Option<&str>,
+    limit: Option<i64>,
+) -> Result<Json<Vec<LocalBan>>, Madness> {
+    account.require_access("bans-manage")?;
+    // Judged on the internal reason, which the account has to be able to read
+    let visibility = ban_visibility(app, &account)?;
+    if !visibility.reason {
+        return Err(Madness::AccessDenied);
     }
 
-    let expires_at = match req_body.revoked_at.as_ref() {
-        None => None,
-        Some(day) => {
-            let downtime = 60 * 60 * 11;
-            Some(day + downtime)
-        }
-    };
+    let min_length = min_length
+        .unwrap_or(INCOMPLETE_DEFAULT_MIN_LENGTH)
+        .clamp(1, REASON_MAX_LENGTH as i32);
+    let tz = parse_timezone(tz);
+    let status_at = app.clock.now().timestamp();
+    let limit = limit
+        .unwrap_or(LIST_DEFAULT_LIMIT)
+        .clamp(1, list_params::MAX_LIMIT);
 
-    sqlx::query!(
-        "UPDATE
-            ban
-        SET
-            reason=$1,
-            public_reason=$2,
-            revoked_at=$3,
-            issued_by=$4,
-            issued_at=$5
-        WHERE
-          id=$6",
-        req_body.reason,
-        req_body.public_reason,
-        expires_at,
-        account.id,
-        now,
-        ban_id
-    )
-    .execute(app.get_db())
-    .await?;
+    Ok(Json(
+        app.ban_service
+            .incomplete(min_length, limit)
+            .await?
+            .into_iter()
+            .map(|ban| {
+                ban.redact(visibility)
+                    .in_timezone(tz, status_at)
+                    .at_version(version)
+            })
+            .collect(),
+    ))
+}
 
-    Ok("Ok")
+#[derive(Serialize)]
+struct BanPermissions {
+    can_view: bool,
+    can_view_internal: bool,
+    can_create: bool,
+    can_revoke: bool,
+    can_approve: bool,
+    can_ban_on_behalf: bool,
+    is_admin: bool,
 }
 
-#[delete("/api/v2/bans/<ban_id>")]
-async fn revoke(
+// What the UI should offer the account, the handlers still check access themselves
+#[get("/api/v2/bans/permissions")]
+fn permissions(
     account: AuthenticatedAccount,
     app: &rocket::State<Application>,
-    ban_id: i64,
-) -> Result<&'static str, Madness> {
-    account.require_access("bans-manage")?;
+) -> Json<BanPermissions> {
+    let can_manage = account.access.contains("bans-manage");
+    let can_view_internal = has_configured_access(&account, &app.config.bans.reason_access);
 
-    if let Some(ban) = sqlx::query!("select * from ban WHERE id=$1", ban_id)
-        .fetch_optional(app.get_db())
-        .await?
-    {
-        let now = Utc::now().timestamp();
-        if !ban.revoked_at.is_none() && ban.revoked_at.unwrap() < now {
-            if ban.revoked_by.is_none() {
-                return Err(Madness::BadRequest(format!(
-                    "Cannot revoke the ban as it has already expired"
-                )));
-            }
+    Json(BanPermissions {
+        can_view: can_view_internal
+            || has_configured_access(&account, &app.config.bans.public_reason_access),
+        can_view_internal,
+        can_create: can_manage,
+        can_revoke: can_manage,
+        can_approve: can_manage,
+        can_ban_on_behalf: account.access.contains("bans-manage-on-behalf"),
+        is_admin: account.access.contains("bans-admin"),
+    })
+}
 
-            let fc_id = ban.revoked_by.unwrap();
+// Offers the FC some "did you mean?" candidates when the ID they gave doesn't exist
+async fn entity_not_found(app: &Application, entity: &Entity) -> Madness {
+    const MESSAGE: &str = "Entity not found";
 
-            let fc = sqlx::query!("SELECT * FROM character WHERE id=$1", fc_id)
-                .fetch_one(app.get_db())
-                .await?;
-            return Err(Madness::BadRequest(format!(
-                "{} has already revoked this ban",
-                fc.name
-            )));
+    let name = match entity.name.as_deref().map(str::trim) {
+        Some(name) if !name.is_empty() => name,
+        _ => return Madness::NotFound(MESSAGE),
+    };
+
+    let mut candidates = match app.esi_lookup.resolve_ids(&[name]).await {
+        Ok(candidates) => candidates,
+        Err(e) => {
+            warn!("Unable to look up ban candidates for {}: {:#?}", name, e);
+            return Madness::NotFound(MESSAGE);
         }
+    };
 
-        sqlx::query!(
-            "UPDATE ban SET revoked_at=$1, revoked_by=$2 WHERE id=$3",
-            now,
-            account.id,
-            ban_id
-        )
-        .execute(app.get_db())
-        .await?;
+    // Prefer candidates of the type the FC was trying to ban
+    candidates.sort_by_key(|candidate| candidate.category != entity.category);
+    candidates.truncate(SUGGESTION_MAX_COUNT);
+
+    Madness::NotFoundWithDetails(MESSAGE, serde_json::json!({ "candidates": candidates }))
+}
+
+// Looks up the name of the entity being banned, and makes sure it isn't one of our FCs
+// Returns the entity name, whether it's a placeholder, and the category ESI reported
+async fn lookup_entity(
+    app: &Application,
+    e: &Entity,
+) -> Result<(String, bool, Option<String>), Madness> {
+    // Resolving by ID alone tells us what the entity really is, the FC supplied type can be wrong
+    let (entity_name, name_pending, esi_category) =
+        match app.esi_lookup.resolve_names(&[e.id]).await {
+            Ok(resolved) => match resolved.into_iter().find(|entity| entity.id == e.id) {
+                Some(entity) => (entity.name, false, Some(entity.category.to_string())),
+                None => return Err(entity_not_found(app, e).await),
+            },
+            Err(ESIError::Status(404)) | Err(ESIError::WithMessage(404, _)) => {
+                return Err(entity_not_found(app, e).await)
+            }
+            // The real name is filled in by core::ban_names once ESI is back
+            Err(err) if err.is_unavailable() && app.config.bans.allow_pending_names => {
+                warn!(
+                    "ESI is unavailable, banning {} with a placeholder name: {:#?}",
+                    e.id, err
+                );
+                let placeholder = e
+                    .name
+                    .clone()
+                    .unwrap_or_else(|| format!("{} {}", e.category, e.id));
+                (
+                    placeholder.chars().take(ENTITY_NAME_MAX_LENGTH).collect(),
+                    true,
+                    None,
+                )
+            }
+            Err(err) => return Err(err.into()),
+        };
 
-        return Ok("Ok");
+    if let Some(category) = esi_category.as_ref().filter(|c| **c != e.category) {
+        warn!("{} {} is a {} according to ESI", e.category, e.id, category);
     }
 
-    return Err(Madness::BadRequest(format!(
-        "Could not find a ban with the ID of {}",
-        ban_id
-    )));
+    // Stop FCs from banning other FCs
+    // See: https://github.com/Contingency-Incursions/legacy-waitlist/issues/43
+    if let Some(admin) = sqlx::query!(
+        "SELECT * FROM admin WHERE character_id=$1",
+        e.id
+    )
+    .fetch_optional(app.get_db())
+    .await? {
+        return Err(Madness::UnprocessableEntity(format!(
+            "{} accounts cannot be banned.",
+            admin.role
+        )));
+    }
+
+    Ok((entity_name, name_pending, esi_category))
 }
 
-pub fn routes() -> Vec<rocket::Route> {
-    routes![
-        list,              //  GET     /api/v2/bans
+#[derive(Serialize)]
+struct CreatedBan {
+    id: Option<i64>,
+}
+
+// 201 with the new ban's details as the Location
+fn created(stored: &str) -> Created<Json<CreatedBan>> {
+    match stored.parse() {
+        Ok(ban_id) => Created::new(format!("/api/v2/bans/{}/details", ban_id))
+            .body(Json(CreatedBan { id: Some(ban_id) })),
+        // Replays stored before the ID was kept only have "Ok"
+        Err(_) => Created::new("/api/v2/bans").body(Json(CreatedBan { id: None })),
+    }
+}
+
+// Pass the ID of a draft to delete it once the ban is created
+#[post("/api/v2/bans?<allow_duplicate>&<draft>", data = "<req_body>")]
+async fn create(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    idempotency_key: IdempotencyKey,
+    allow_duplicate: Option<bool>,
+    draft: Option<i64>,
+    req_body: Json<CreateBanRequest>,
+) -> Result<Created<Json<CreatedBan>>, Madness> {
+    account.require_access("bans-manage")?;
+    require_recent_login(app, &account)?;
+
+    if let Some(response) = idempotency_key
+        .claim(
+            app.get_db(),
+            account.id,
+            "ban-create",
+            app.clock.now().timestamp(),
+        )
+        .await?
+    {
+        return Ok(created(&response));
+    }
+
+    // The ban ID is what gets stored for replays
+    let result = create_ban(&account, app, &req_body, allow_duplicate.unwrap_or(false))
+        .await
+        .map(|ban_id| ban_id.to_string());
+    // Once the ban is committed nothing after it may turn the response into an error, a retry
+    // would then either ban twice or find the key still claimed
+    if let Err(e) = idempotency_key
+        .complete(app.get_db(), account.id, "ban-create", &result)
+        .await
+    {
+        error!("Unable to store the response of a ban create: {:#?}", e);
+    }
+    if let (Ok(_), Some(draft_id)) = (&result, draft) {
+        if let Err(e) = sqlx::query!(
+            "DELETE FROM ban_draft WHERE id=$1 AND account_id=$2",
+            draft_id,
+            account.id
+        )
+        .execute(app.get_db())
+        .await
+        {
+            warn!("Unable to delete draft {} of a new ban: {:#?}", draft_id, e);
+        }
+    }
+    result.map(|ban_id| created(&ban_id))
+}
+
+async fn create_ban(
+    account: &AuthenticatedAccount,
+    app: &Application,
+    input: &CreateBanRequest,
+    allow_duplicate: bool,
+) -> Result<i64, Madness> {
+    let mut requested = input.ban.clone();
+    if input.mirror_reason {
+        if requested
+            .public_reason
+            .as_deref()
+            .map_or(false, |reason| !reason.trim().is_empty())
+        {
+            return Err(Madness::BadRequest(
+                "Leave out \"public_reason\" when using \"mirror_reason\"".to_string(),
+            ));
+        }
+        requested.public_reason = Some(requested.reason.clone());
+    }
+    let req_body = &requested;
+    let now = app.clock.now().timestamp();
+
+    // Integrations record the FC the ban is really from, so regular FCs can't pass it
+    if let Some(on_behalf_of) = input.on_behalf_of {
+        account.require_access("bans-manage-on-behalf")?;
+
+        if sqlx::query!("SELECT id FROM character WHERE id=$1", on_behalf_of)
+            .fetch_optional(app.get_db())
+            .await?
+            .is_none()
+        {
+            return Err(Madness::BadRequest(format!(
+                "Unknown character {} for \"on_behalf_of\"",
+                on_behalf_of
+            )));
+        }
+    }
+
+    if let None = &req_body.entity {
+        return Err(Madness::BadRequest(format!(
+            "One or more body paramaters are missing: [\"{}\", \"{}\", \"{}\"]",
+            "id", "name", "kind"
+        )));
+    }
+
+    // Validate before calling ESI so we don't fail the insert after a successful lookup
+    validate_reasons(&app.config.bans, req_body)?;
+    let tags = normalize_tags(&req_body.tags).map_err(Madness::BadRequest)?;
+    validate_expiry(app, req_body.revoked_at)?;
+    if let Some(starts_at) = req_body.starts_at {
+        validate_starts_at(app, starts_at, req_body.revoked_at)?;
+    }
+
+    let e = req_body.entity.as_ref().unwrap();
+    validate_not_self(account, e)?;
+
+    if let Some(supersedes_ban_id) = req_body.supersedes_ban_id {
+        let superseded = match app.ban_service.find(supersedes_ban_id).await? {
+            Some(ban) => ban,
+            None => {
+                return Err(Madness::UnprocessableEntity(format!(
+                    "Ban {} for \"supersedes_ban_id\" doesn't exist",
+                    supersedes_ban_id
+                )))
+            }
+        };
+        // Name patterns share an entity ID, so their patterns have to match too
+        let same_entity = match &superseded.entity {
+            Some(entity) => {
+                entity.category == e.category
+                    && entity.id == e.id
+                    && (e.category != NAME_PATTERN || entity.name == e.name)
+            }
+            None => false,
+        };
+        if !same_entity {
+            return Err(Madness::UnprocessableEntity(
+                "A ban can only supersede a ban on the same entity".to_string(),
+            ));
+        }
+        app.ban_service
+            .check_supersedes(None, supersedes_ban_id)
+            .await?;
+    }
+
+    // A second active ban for the same entity is usually a mistake, admins can insist
+    if allow_duplicate {
+        account.require_access("bans-admin")?;
+    }
+    let duplicate = app.ban_service.has_active(e).await?;
+    if duplicate {
+        if !allow_duplicate {
+            return Err(Madness::Conflict(
+                "There already is an active ban for this entity".to_string(),
+            ));
+        }
+        info!(
+            "{} overrode the duplicate check to ban {} {}",
+            account.id, e.category, e.id
+        );
+    }
+
+    // Stops FCs flipping a ban back and forth
+    if app.config.bans.enforce_reban_cooldown
+        && e.category != NAME_PATTERN
+        && !account.access.contains("bans-admin")
+    {
+        if let Some(revoked_at) = app.ban_service.last_revoked_at(e).await? {
+            let remaining = revoked_at + app.config.bans.reban_cooldown * 60 - now;
+            if revoked_at <= now && remaining > 0 {
+                return Err(Madness::UnprocessableEntity(format!(
+                    "A ban on this {} was revoked recently, it can be banned again in {} minute(s)",
+                    e.category.to_lowercase(),
+                    (remaining + 59) / 60
+                )));
+            }
+        }
+    }
+
+    let (entity_id, entity_name, name_pending, esi_category) = if e.category == NAME_PATTERN {
+        // A bad pattern could ban far more pilots than intended
+        account.require_access("bans-admin")?;
+        let pattern = e.name.clone().unwrap_or_default();
+        name_pattern::validate(&pattern).map_err(Madness::BadRequest)?;
+        (NAME_PATTERN_ENTITY_ID, pattern, false, None)
+    } else {
+        let (entity_name, name_pending, esi_category) = lookup_entity(app, e).await?;
+        (e.id, entity_name, name_pending, esi_category)
+    };
+
+    let mut ban = Ban {
+        entity: Some(Entity {
+            id: entity_id,
+            name: Some(entity_name),
+            category: e.category.clone(),
+        }),
+        issued_at: Some(now),
+        name_pending,
+        esi_category,
+        // Permanent bans wait for a second FC when the four-eyes policy is on
+        pending_approval: app.config.bans.require_approval && req_body.revoked_at.is_none(),
+        // An explicit category wins over a tag at the start of the reason
+        category: req_body
+            .category
+            .clone()
+            .or_else(|| parse_reason_tag(&req_body.reason)),
+        tags,
+        fc_note: req_body
+            .fc_note
+            .clone()
+            .filter(|note| !note.trim().is_empty()),
+        ..req_body.clone()
+    };
+    // Matched against the FC the ban is really from
+    if !app.config.bans.auto_tags.is_empty() {
+        let issuer_role = sqlx::query!(
+            "SELECT role FROM admin WHERE character_id=$1",
+            input.on_behalf_of.unwrap_or(account.id)
+        )
+        .fetch_optional(app.get_db())
+        .await?
+        .map(|admin| admin.role);
+        let matched = auto_tags(&app.config.bans.auto_tags, &ban, issuer_role.as_deref());
+        ban.tags = normalize_tags(&[ban.tags, matched].concat()).map_err(Madness::BadRequest)?;
+    }
+    let ban_id = app
+        .ban_service
+        .insert(&ban, account.id, input.on_behalf_of)
+        .await?;
+    if duplicate {
+        app.ban_service
+            .record_duplicate_override(ban_id, account.id)
+            .await?;
+    }
+
+    notify_ban_change(app, ban_id, "created").await;
+    if !ban.pending_approval {
+        remove_discord_roles(app, ban_id).await;
+    }
+
+    // Silent bans are recorded as normal, they just aren't announced. Pending bans are
+    // announced once they're approved.
+    if !req_body.silent && !ban.pending_approval && app.feature_enabled("discord_webhook") {
+        if let Some(ban) = app.ban_service.find(ban_id).await? {
+            // A failed announcement shouldn't fail the ban
+            let message = app.webhook_client.ban_message(&ban);
+            if let Err(e) = app.webhook_client.send(&message).await {
+                warn!("Unable to announce ban {}: {:#?}", ban_id, e);
+            }
+        }
+    }
+
+    Ok(ban_id)
+}
+
+// Tells open ban managers to refresh, and cached lists that they're out of date. Not being able
+// to reach the SSE server shouldn't fail the change itself, clients still see it on their next
+// load.
+async fn notify_ban_change(app: &Application, ban_id: i64, action: &str) {
+    app.bans_changed();
+
+    #[derive(Debug, Serialize)]
+    struct BanChange<'a> {
+        id: i64,
+        action: &'a str,
+    }
+
+    if let Err(e) = app
+        .sse_client
+        .submit(vec![Event::new_json(
+            "bans",
+            "ban_change",
+            &BanChange { id: ban_id, action },
+        )])
+        .await
+    {
+        warn!(
+            "Unable to send the {} event for ban {}: {:#?}",
+            action, ban_id, e
+        );
+    }
+}
+
+// Only character bans are handled, the bot can't tell which Discord users are in a corporation.
+// Failures are only logged, the ban stands either way and roles can still be removed by hand.
+// Scheduled bans are skipped, core::ban_expiry removes their roles once they start.
+// The bot is called in the background so a slow bot doesn't hold up the request.
+async fn remove_discord_roles(app: &Application, ban_id: i64) {
+    if !app.role_removal_client.is_enabled() || !app.feature_enabled("discord_role_removal") {
+        return;
+    }
+
+    let link = match sqlx::query!(
+        "SELECT character_discord.character_id, character_discord.discord_id FROM ban JOIN character_discord ON character_discord.character_id=ban.entity_id WHERE ban.id=$1 AND ban.entity_type='Character' AND (ban.starts_at IS NULL OR ban.starts_at <= $2)",
+        ban_id,
+        app.clock.now().timestamp()
+    )
+    .fetch_optional(app.get_db())
+    .await
+    {
+        Ok(Some(link)) => link,
+        Ok(None) => return,
+        Err(e) => {
+            warn!("Unable to look up the Discord account for ban {}: {:#?}", ban_id, e);
+            return;
+        }
+    };
+
+    let client = app.role_removal_client.clone();
+    let now = app.clock.now().timestamp();
+    tokio::spawn(async move {
+        if let Err(e) = client
+            .remove_roles(&link.discord_id, link.character_id, ban_id, now)
+            .await
+        {
+            warn!(
+                "Unable to remove the Discord roles of {} for ban {}: {:#?}",
+                link.character_id, ban_id, e
+            );
+        }
+    });
+}
+
+#[post("/api/v2/bans/<ban_id>/announce")]
+async fn announce(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    ban_id: i64,
+) -> Result<&'static str, Madness> {
+    account.require_access("bans-manage")?;
+    require_recent_login(app, &account)?;
+    if !app.webhook_client.is_configured() {
+        return Err(Madness::NotFound("No ban webhook is configured"));
+    }
+    if !app.feature_enabled("discord_webhook") {
+        return Err(Madness::NotFound("Ban announcements are turned off"));
+    }
+
+    let ban = match app.ban_service.find(ban_id).await? {
+        Some(ban) => ban,
+        None => return Err(Madness::NotFound("Ban not found")),
+    };
+
+    if ban.silent {
+        return Err(Madness::BadRequest(
+            "Silent bans cannot be announced".to_string(),
+        ));
+    }
+    // Approving posts it, announcing it earlier would get round the second FC
+    if ban.pending_approval {
+        return Err(Madness::UnprocessableEntity(
+            "Pending bans are announced once approved".to_string(),
+        ));
+    }
+
+    let now = app.clock.now().timestamp();
+    if let Some(revoked_at) = ban.revoked_at {
+        if revoked_at < now {
+            return Err(Madness::BadRequest(
+                "Cannot announce a ban that is no longer active".to_string(),
+            ));
+        }
+    }
+
+    // Only announcements that were posted count, so a failed one can be retried
+    let key = format!("ban-announce;{}", account.id);
+    if let Err(retry_after) = app
+        .rate_limiter
+        .peek(&key, ANNOUNCE_LIMIT, ANNOUNCE_WINDOW, now)
+    {
+        return Err(Madness::TooManyRequests(format!(
+            "You announced {} bans recently, try again in {} seconds",
+            ANNOUNCE_LIMIT, retry_after
+        )));
+    }
+
+    app.webhook_client
+        .send(&app.webhook_client.ban_message(&ban))
+        .await?;
+    app.rate_limiter.record(&key, ANNOUNCE_WINDOW, now);
+
+    Ok("Ok")
+}
+
+#[derive(Serialize)]
+struct BanMatches {
+    exact: Vec<Ban>,
+    pattern: Vec<Ban>,
+}
+
+// Which active bans a character with this name and/or ID would run into, for checking
+// name patterns before relying on them
+#[get("/api/v2/bans/match?<name>&<id>")]
+async fn match_bans(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    name: Option<&str>,
+    id: Option<i64>,
+) -> Result<Json<BanMatches>, Madness> {
+    account.require_access("bans-manage")?;
+    let visibility = ban_visibility(app, &account)?;
+
+    if name.is_none() && id.is_none() {
+        return Err(Madness::BadRequest(
+            "Pass a name, an id or both".to_string(),
+        ));
+    }
+
+    let exact = match id {
+        Some(id) => app.ban_service.active_bans(id, "Character").await?,
+        None => None,
+    };
+    let pattern = match name {
+        Some(name) => app.ban_service.name_pattern_bans(name).await?,
+        None => None,
+    };
+
+    let redact = |bans: Option<Vec<Ban>>| -> Vec<Ban> {
+        bans.unwrap_or_default()
+            .into_iter()
+            .map(|ban| ban.redact(visibility))
+            .collect()
+    };
+
+    Ok(Json(BanMatches {
+        exact: redact(exact),
+        pattern: redact(pattern),
+    }))
+}
+
+#[derive(Deserialize)]
+struct CheckRequest {
+    character_ids: Vec<i64>,
+}
+
+#[derive(Serialize)]
+struct CharacterCheck {
+    character_id: i64,
+    banned: bool,
+    // The ban that applies, directly or through the corporation or alliance
+    ban: Option<Ban>,
+}
+
+// Screens many characters at once, e.g. everyone who X'd up, with their current affiliations
+#[post("/api/v2/bans/check", data = "<input>")]
+async fn check(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    input: Json<CheckRequest>,
+) -> Result<Json<Vec<CharacterCheck>>, Madness> {
+    account.require_access("bans-manage")?;
+    let visibility = ban_visibility(app, &account)?;
+
+    let mut character_ids = input.character_ids.clone();
+    character_ids.sort_unstable();
+    character_ids.dedup();
+    if character_ids.len() > CHECK_MAX_COUNT {
+        return Err(Madness::BadRequest(format!(
+            "Cannot check more than {} characters at once",
+            CHECK_MAX_COUNT
+        )));
+    }
+    if character_ids.is_empty() {
+        return Ok(Json(Vec::new()));
+    }
+
+    let affiliations = app
+        .esi_lookup
+        .character_affiliations(&character_ids)
+        .await?;
+
+    Ok(Json(
+        app.ban_service
+            .check_characters(&affiliations)
+            .await?
+            .into_iter()
+            .map(|(character_id, bans)| {
+                let ban = bans
+                    .and_then(|bans| bans.into_iter().next())
+                    .map(|ban| ban.redact(visibility));
+                CharacterCheck {
+                    character_id,
+                    banned: ban.is_some(),
+                    ban,
+                }
+            })
+            .collect(),
+    ))
+}
+
+#[get("/api/v2/bans/pending?<tz>")]
+async fn pending(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    version: ApiVersion,
+    tz: Option<&str>,
+) -> Result<Json<Vec<LocalBan>>, Madness> {
+    account.require_access("bans-manage")?;
+    let visibility = ban_visibility(app, &account)?;
+    let tz = parse_timezone(tz);
+    let status_at = app.clock.now().timestamp();
+
+    Ok(Json(
+        app.ban_service
+            .pending()
+            .await?
+            .into_iter()
+            .filter(|ban| shown_to(visibility, ban, status_at))
+            .map(|ban| {
+                ban.redact(visibility)
+                    .in_timezone(tz, status_at)
+                    .at_version(version)
+            })
+            .collect(),
+    ))
+}
+
+#[post("/api/v2/bans/<ban_id>/approve")]
+async fn approve(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    ban_id: i64,
+) -> Result<&'static str, Madness> {
+    account.require_access("bans-manage")?;
+    require_recent_login(app, &account)?;
+
+    app.ban_service.approve(ban_id, account.id).await?;
+    notify_ban_change(app, ban_id, "updated").await;
+    remove_discord_roles(app, ban_id).await;
+
+    if let Some(ban) = app.ban_service.find(ban_id).await? {
+        if !ban.silent && app.feature_enabled("discord_webhook") {
+            let message = app.webhook_client.ban_message(&ban);
+            if let Err(e) = app.webhook_client.send(&message).await {
+                warn!("Unable to announce ban {}: {:#?}", ban_id, e);
+            }
+        }
+    }
+
+    Ok("Ok")
+}
+
+#[derive(Serialize)]
+struct HistoryBan {
+    #[serde(flatten)]
+    ban: LocalBan,
+    // Ran out within bans.recently_expired_grace, left out for V2 clients
+    #[serde(skip_serializing_if = "Option::is_none")]
+    recently_expired: Option<bool>,
+}
+
+// With as_of, only the bans that were in effect at that time, e.g. for settling disputes
+#[get("/api/v2/bans/<character_id>?<tz>&<relative>&<as_of>")]
+async fn character_history(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    version: ApiVersion,
+    character_id: i64,
+    tz: Option<&str>,
+    relative: Option<bool>,
+    as_of: Option<i64>,
+) -> Result<Json<Vec<HistoryBan>>, Madness> {
+    let visibility = ban_visibility(app, &account)?;
+    let tz = parse_timezone(tz);
+    let now = relative_now(app, relative);
+    let current_time = app.clock.now().timestamp();
+    let grace = app.config.bans.recently_expired_grace * 3600;
+
+    if let Some(bans) = app.ban_service.all_bans(character_id, "Character").await? {
+        return Ok(Json(
+            bans.into_iter()
+                .filter(|ban| shown_to(visibility, ban, current_time))
+                .filter(|ban| as_of.map_or(true, |as_of| ban.active_at(as_of)))
+                .map(|ban| HistoryBan {
+                    recently_expired: if version == ApiVersion::V2 {
+                        None
+                    } else {
+                        Some(ban.recently_expired(current_time, grace))
+                    },
+                    ban: ban
+                        .redact(visibility)
+                        .in_timezone(tz, current_time)
+                        .relative_to(now)
+                        .at_version(version),
+                })
+                .collect(),
+        ));
+    }
+
+    Ok(Json(Vec::new()))
+}
+
+// Everything an unauthenticated caller gets to see, never add staff-only fields here
+#[derive(Serialize)]
+struct PublicBanCheck {
+    banned: bool,
+    public_reason: Option<String>,
+    expires_at_iso: Option<String>,
+}
+
+// For "check if you're banned before applying" pages, disabled unless bans.public_check is set
+#[get("/api/v2/public/bans/check?<character_id>")]
+async fn public_check(
+    app: &rocket::State<Application>,
+    ip: ClientIp,
+    character_id: i64,
+) -> Result<Json<PublicBanCheck>, Madness> {
+    if !app.config.bans.public_check {
+        return Err(Madness::NotFound("Not found"));
+    }
+
+    if let Err(retry_after) = app.rate_limiter.check(
+        &format!("public-ban-check;{}", ip.0),
+        app.config.bans.public_check_limit,
+        PUBLIC_CHECK_WINDOW,
+        app.clock.now().timestamp(),
+    ) {
+        return Err(Madness::TooManyRequests(format!(
+            "Too many lookups, try again in {} seconds",
+            retry_after
+        )));
+    }
+
+    let bans = app
+        .ban_service
+        .character_bans(character_id)
+        .await?
+        .unwrap_or_default();
+
+    Ok(Json(PublicBanCheck {
+        banned: !bans.is_empty(),
+        public_reason: bans.iter().find_map(|ban| ban.public_reason.clone()),
+        expires_at_iso: iso_timestamp(expires_at(&bans), chrono_tz::Tz::UTC),
+    }))
+}
+
+// The ban that lasts longest decides when the entity is free again, None if any is permanent
+fn expires_at(bans: &[Ban]) -> Option<i64> {
+    match bans.iter().any(|ban| ban.revoked_at.is_none()) {
+        true => None,
+        false => bans.iter().filter_map(|ban| ban.revoked_at).max(),
+    }
+}
+
+#[derive(Serialize)]
+struct EntityBanStatus {
+    banned: bool,
+    public_reason: Option<String>,
+    expires_at: Option<i64>,
+}
+
+#[derive(Responder)]
+struct CachedStatus {
+    inner: Json<EntityBanStatus>,
+    cache_control: Header<'static>,
+}
+
+// A cheap yes/no for bots. Characters also inherit the bans of their corporation and alliance,
+// going by the cached affiliation, and corporations those of their alliance.
+#[get("/api/v2/bans/status/<entity_type>/<entity_id>")]
+async fn status(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    entity_type: &str,
+    entity_id: i64,
+) -> Result<CachedStatus, Madness> {
+    account.require_access("bans-manage")?;
+    let visibility = ban_visibility(app, &account)?;
+
+    let bans = match EntityType::parse(entity_type) {
+        Some(EntityType::Character) => app.ban_service.character_bans(entity_id).await?,
+        Some(EntityType::Corporation) => app.ban_service.corporation_bans(entity_id).await?,
+        Some(_) => app.ban_service.active_bans(entity_id, entity_type).await?,
+        None => {
+            return Err(Madness::BadRequest(format!(
+                "Unknown entity type {}",
+                entity_type
+            )))
+        }
+    }
+    .unwrap_or_default();
+
+    Ok(CachedStatus {
+        inner: Json(EntityBanStatus {
+            banned: !bans.is_empty(),
+            public_reason: match visibility.public_reason {
+                true => bans.iter().find_map(|ban| ban.public_reason.clone()),
+                false => None,
+            },
+            expires_at: expires_at(&bans),
+        }),
+        cache_control: Header::new(
+            "Cache-Control",
+            format!("private, max-age={}", STATUS_MAX_AGE),
+        ),
+    })
+}
+
+// Exports end up with the pilot, so they only carry what the pilot is told anyway
+const EXPORT_VISIBILITY: BanVisibility = BanVisibility {
+    reason: false,
+    public_reason: true,
+    fc_note: false,
+};
+
+// Every ban on the entity the pilot may be told about
+async fn full_history(
+    app: &Application,
+    entity_id: i64,
+    entity_type: &str,
+) -> Result<Vec<Ban>, Madness> {
+    let now = app.clock.now().timestamp();
+    Ok(app
+        .ban_service
+        .all_bans(entity_id, entity_type)
+        .await?
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|ban| shown_to(EXPORT_VISIBILITY, ban, now))
+        .map(|ban| ban.redact(EXPORT_VISIBILITY))
+        .collect())
+}
+
+#[derive(Serialize)]
+struct BanExport {
+    character_id: i64,
+    // As last seen by the waitlist, unknown if the character never logged in
+    character_name: Option<String>,
+    corporation: Option<Entity>,
+    alliance: Option<Entity>,
+    exported_at: i64,
+    bans: Vec<Ban>,
+    // Bans of the current corporation and alliance, which apply to the character through them
+    corporation_bans: Vec<Ban>,
+    alliance_bans: Vec<Ban>,
+    // Active name pattern bans matching the character's name
+    pattern_bans: Vec<Ban>,
+}
+
+// The complete record of one pilot, for data requests and appeals
+// Ranked below /api/v2/bans/jobs/<job_id> like the reasons route
+#[get("/api/v2/bans/<character_id>/export.json", rank = 2)]
+async fn export_character(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    character_id: i64,
+) -> Result<Json<BanExport>, Madness> {
+    account.require_access("bans-admin")?;
+
+    let affiliation = sqlx::query!(
+        "SELECT
+            character.name,
+            corporation.id AS \"corporation_id?\",
+            corporation.name AS \"corporation_name?\",
+            alliance.id AS \"alliance_id?\",
+            alliance.name AS \"alliance_name?\"
+        FROM
+            character
+        LEFT JOIN
+            corporation ON character.corporation_id=corporation.id
+        LEFT JOIN
+            alliance ON corporation.alliance_id=alliance.id
+        WHERE
+            character.id=$1",
+        character_id
+    )
+    .fetch_optional(app.get_db())
+    .await?;
+
+    let (character_name, corporation, alliance) = match affiliation {
+        Some(row) => {
+            let (corporation_name, alliance_name) = (row.corporation_name, row.alliance_name);
+            (
+                Some(row.name),
+                row.corporation_id.map(|id| Entity {
+                    id,
+                    name: corporation_name,
+                    category: "Corporation".to_string(),
+                }),
+                row.alliance_id.map(|id| Entity {
+                    id,
+                    name: alliance_name,
+                    category: "Alliance".to_string(),
+                }),
+            )
+        }
+        None => (None, None, None),
+    };
+
+    let corporation_bans = match &corporation {
+        Some(corporation) => full_history(app, corporation.id, "Corporation").await?,
+        None => Vec::new(),
+    };
+    let alliance_bans = match &alliance {
+        Some(alliance) => full_history(app, alliance.id, "Alliance").await?,
+        None => Vec::new(),
+    };
+    let pattern_bans = match &character_name {
+        Some(name) => app
+            .ban_service
+            .name_pattern_bans(name)
+            .await?
+            .unwrap_or_default()
+            .into_iter()
+            .map(|ban| ban.redact(EXPORT_VISIBILITY))
+            .collect(),
+        None => Vec::new(),
+    };
+
+    info!("{} exported the bans of {}", account.id, character_id);
+
+    Ok(Json(BanExport {
+        character_id,
+        exported_at: app.clock.now().timestamp(),
+        bans: full_history(app, character_id, "Character").await?,
+        character_name,
+        corporation,
+        alliance,
+        corporation_bans,
+        alliance_bans,
+        pattern_bans,
+    }))
+}
+
+#[get("/api/v2/bans/<ban_id>/details?<tz>&<relative>")]
+async fn details(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    version: ApiVersion,
+    ban_id: i64,
+    tz: Option<&str>,
+    relative: Option<bool>,
+) -> Result<Json<LocalBan>, Madness> {
+    let visibility = ban_visibility(app, &account)?;
+    let now = relative_now(app, relative);
+    let status_at = app.clock.now().timestamp();
+
+    match app.ban_service.find(ban_id).await? {
+        Some(ban) if shown_to(visibility, &ban, status_at) => Ok(Json(
+            ban.redact(visibility)
+                .in_timezone(parse_timezone(tz), status_at)
+                .relative_to(now)
+                .at_version(version),
+        )),
+        _ => Err(Madness::NotFound("Ban not found")),
+    }
+}
+
+#[derive(Serialize)]
+struct BanComparison {
+    a: LocalBan,
+    b: LocalBan,
+    differences: Vec<BanDifference>,
+}
+
+// Two bans side by side, usually a ban under appeal and an earlier one on the same entity
+#[get("/api/v2/bans/compare?<a>&<b>&<tz>")]
+async fn compare(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    version: ApiVersion,
+    a: i64,
+    b: i64,
+    tz: Option<&str>,
+) -> Result<Json<BanComparison>, Madness> {
+    account.require_access("bans-manage")?;
+    let visibility = ban_visibility(app, &account)?;
+    let status_at = app.clock.now().timestamp();
+
+    let (a, b) = match (
+        app.ban_service.find(a).await?,
+        app.ban_service.find(b).await?,
+    ) {
+        (Some(a), Some(b))
+            if shown_to(visibility, &a, status_at) && shown_to(visibility, &b, status_at) =>
+        {
+            (a.redact(visibility), b.redact(visibility))
+        }
+        _ => return Err(Madness::NotFound("Ban not found")),
+    };
+    let differences = a.differences(&b);
+    let tz = parse_timezone(tz);
+
+    Ok(Json(BanComparison {
+        a: a.in_timezone(tz, status_at).at_version(version),
+        b: b.in_timezone(tz, status_at).at_version(version),
+        differences,
+    }))
+}
+
+// Ranked below /api/v2/bans/jobs/<job_id>, which matches the same paths
+#[get("/api/v2/bans/<ban_id>/reasons", rank = 2)]
+async fn reasons(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    ban_id: i64,
+) -> Result<Json<Vec<ReasonEdit>>, Madness> {
+    account.require_access("bans-manage")?;
+    if !ban_visibility(app, &account)?.reason {
+        return Err(Madness::AccessDenied);
+    }
+
+    if app.ban_service.find(ban_id).await?.is_none() {
+        return Err(Madness::NotFound("Ban not found"));
+    }
+
+    Ok(Json(app.ban_service.reason_history(ban_id).await?))
+}
+
+fn ended_ban() -> Madness {
+    Madness::BadRequest(
+        "This ban has already ended and can't be edited, create a new ban instead".to_string(),
+    )
+}
+
+// Tells a field that was left out (None) apart from one sent as null (Some(None))
+fn present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::deserialize(deserializer).map(Some)
+}
+
+// The body of a PATCH. Fields that are left out keep their current value. Sending null clears
+// the public reason or the FC note, and makes the ban permanent for revoked_at.
+#[derive(Deserialize)]
+struct BanUpdate {
+    // Can't be cleared, null is the same as leaving it out
+    reason: Option<String>,
+    #[serde(default, deserialize_with = "present")]
+    public_reason: Option<Option<String>>,
+    // The new expiry day, like the revoked_at of a new ban
+    #[serde(default, deserialize_with = "present")]
+    revoked_at: Option<Option<i64>>,
+    // An empty note clears it, like null
+    #[serde(default, deserialize_with = "present")]
+    fc_note: Option<Option<String>>,
+    // Replaces all tags
+    tags: Option<Vec<String>>,
+}
+
+// Returns the ban as it is after the update
+#[patch("/api/v2/bans/<ban_id>?<tz>", data = "<req_body>")]
+async fn update(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    version: ApiVersion,
+    ban_id: i64,
+    tz: Option<&str>,
+    req_body: Json<BanUpdate>,
+) -> Result<Json<LocalBan>, Madness> {
+    account.require_access("bans-manage")?;
+    require_recent_login(app, &account)?;
+
+    let now = app.clock.now().timestamp();
+
+    // Editing only adjusts bans that are still in effect. Giving an ended ban a new expiry
+    // would quietly bring it back, so it has to be banned again instead.
+    let mut ban = match app.ban_service.find(ban_id).await? {
+        Some(ban) => ban,
+        None => return Err(Madness::NotFound("Ban not found")),
+    };
+    if ban.revoked_at.map_or(false, |revoked_at| revoked_at <= now) {
+        return Err(ended_ban());
+    }
+    require_ownership(app, &account, &ban)?;
+
+    // The reasons are checked as they'll be after the update
+    if let Some(reason) = &req_body.reason {
+        ban.reason = reason.clone();
+    }
+    if let Some(public_reason) = &req_body.public_reason {
+        ban.public_reason = public_reason.clone();
+    }
+    if let Some(fc_note) = &req_body.fc_note {
+        ban.fc_note = fc_note.clone();
+    }
+    validate_reasons(&app.config.bans, &ban)?;
+    let tags = match &req_body.tags {
+        Some(tags) => Some(normalize_tags(tags).map_err(Madness::BadRequest)?),
+        None => None,
+    };
+
+    let expiry_day = req_body.revoked_at.flatten();
+    validate_expiry(app, expiry_day)?;
+    let expires_at = expiry_day.map(|day| app.ban_service.expires_at(day));
+    let fc_note = req_body
+        .fc_note
+        .as_ref()
+        .map(|note| note.clone().unwrap_or_default());
+
+    // The previous reason is kept in ban_reason_history whenever it changes. Both statements
+    // see the ban as it was before the update. $9 and $10 say whether the public reason and
+    // the expiry were sent, a NULL $8 means the FC note wasn't. The tags are replaced in the
+    // same transaction, so a failure there doesn't leave the ban half updated.
+    let mut tx = app.get_db().begin().await?;
+    let updated = sqlx::query!(
+        "WITH previous AS (
+            INSERT INTO ban_reason_history (ban_id, reason, edited_at, edited_by)
+            SELECT id, reason, $5, $4 FROM ban
+            WHERE id=$6 AND reason <> $1 AND (revoked_at IS NULL OR revoked_at > $5)
+        )
+        UPDATE
+            ban
+        SET
+            reason=COALESCE($1, reason),
+            public_reason=CASE WHEN $9 THEN $2 ELSE public_reason END,
+            revoked_at=CASE WHEN $10 THEN $3 ELSE revoked_at END,
+            expiry_day=CASE WHEN $10 THEN $7 ELSE expiry_day END,
+            updated_by=$4,
+            updated_at=$5,
+            fc_note=CASE WHEN $8::VARCHAR IS NULL THEN fc_note ELSE NULLIF($8, '') END
+        WHERE
+          id=$6 AND (revoked_at IS NULL OR revoked_at > $5)",
+        req_body.reason,
+        req_body.public_reason.clone().flatten(),
+        expires_at,
+        account.id,
+        now,
+        ban_id,
+        expiry_day,
+        fc_note,
+        req_body.public_reason.is_some(),
+        req_body.revoked_at.is_some()
+    )
+    .execute(&mut tx)
+    .await?
+    .rows_affected();
+    // It ended between the check and the update
+    if updated == 0 {
+        return Err(ended_ban());
+    }
+    if let Some(tags) = tags {
+        replace_tags(&mut tx, ban_id, &tags).await?;
+    }
+    tx.commit().await?;
+
+    notify_ban_change(app, ban_id, "updated").await;
+
+    match app.ban_service.find(ban_id).await? {
+        Some(ban) => Ok(Json(
+            ban.redact(ban_visibility(app, &account)?)
+                .in_timezone(parse_timezone(tz), app.clock.now().timestamp())
+                .at_version(version),
+        )),
+        None => Err(Madness::NotFound("Ban not found")),
+    }
+}
+
+// For the banned player, to record they were shown why. Only character bans can be acknowledged,
+// by the character or an account it's linked to.
+#[post("/api/v2/bans/<ban_id>/acknowledge")]
+async fn acknowledge(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    ban_id: i64,
+) -> Result<&'static str, Madness> {
+    let ban = match app.ban_service.find(ban_id).await? {
+        Some(ban) => ban,
+        None => return Err(Madness::NotFound("Ban not found")),
+    };
+    let character_id = match &ban.entity {
+        Some(entity) if entity.category == "Character" => entity.id,
+        _ => {
+            return Err(Madness::BadRequest(
+                "Only character bans can be acknowledged".to_string(),
+            ))
+        }
+    };
+    authorize_character(app.get_db(), &account, character_id, None).await?;
+
+    if !app.ban_service.acknowledge(ban_id).await? {
+        return Err(Madness::BadRequest(
+            "Only active bans can be acknowledged".to_string(),
+        ));
+    }
+    app.bans_changed();
+    Ok("Ok")
+}
+
+#[derive(Deserialize)]
+struct ExtendRequest {
+    // Either a new expiry day, like the revoked_at of a new ban, or a number of days to add
+    expiry_day: Option<i64>,
+    days: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct ExtendResponse {
+    expires_at: i64,
+}
+
+#[post("/api/v2/bans/<ban_id>/extend", data = "<input>")]
+async fn extend(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    ban_id: i64,
+    input: Json<ExtendRequest>,
+) -> Result<Json<ExtendResponse>, Madness> {
+    account.require_access("bans-manage")?;
+    require_recent_login(app, &account)?;
+
+    let extension = match (input.expiry_day, input.days) {
+        (Some(day), None) => Extension::Until(day),
+        (None, Some(days)) if days > 0 => Extension::Days(days),
+        (None, Some(_)) => return Err(Madness::BadRequest("days must be positive".to_string())),
+        _ => {
+            return Err(Madness::BadRequest(
+                "Pass either expiry_day or days".to_string(),
+            ))
+        }
+    };
+    require_ban_ownership(app, &account, ban_id).await?;
+
+    let expires_at = app.ban_service.extend(ban_id, extension).await?;
+    info!(
+        "{} extended ban {} until {}",
+        account.id, ban_id, expires_at
+    );
+    notify_ban_change(app, ban_id, "updated").await;
+
+    Ok(Json(ExtendResponse { expires_at }))
+}
+
+#[delete("/api/v2/bans/<ban_id>")]
+async fn revoke(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    ban_id: i64,
+) -> Result<NoContent, Madness> {
+    account.require_access("bans-manage")?;
+    require_recent_login(app, &account)?;
+    require_ban_ownership(app, &account, ban_id).await?;
+
+    app.ban_service.revoke(ban_id, account.id).await?;
+    notify_ban_change(app, ban_id, "revoked").await;
+
+    Ok(NoContent)
+}
+
+#[derive(Deserialize)]
+struct BulkRevokeRequest {
+    ids: Vec<i64>,
+}
+
+// Revokes each ban on its own, the ones that can't be revoked are listed with why
+#[post("/api/v2/bans/revoke", data = "<input>")]
+async fn bulk_revoke(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    input: Json<BulkRevokeRequest>,
+) -> Result<Json<BulkResult<i64>>, Madness> {
+    account.require_access("bans-manage")?;
+    require_recent_login(app, &account)?;
+    if input.ids.len() > BULK_MAX_COUNT {
+        return Err(Madness::BadRequest(format!(
+            "Cannot revoke more than {} bans at once",
+            BULK_MAX_COUNT
+        )));
+    }
+
+    let mut result = BulkResult::default();
+    for ban_id in unique_ids(&input.ids) {
+        let revoked = match require_ban_ownership(app, &account, ban_id).await {
+            Ok(()) => app.ban_service.revoke(ban_id, account.id).await,
+            Err(e) => Err(e),
+        };
+        match revoked {
+            Ok(()) => {
+                notify_ban_change(app, ban_id, "revoked").await;
+                result.succeed(ban_id);
+            }
+            // Some bans may already be revoked, so the caller still needs to know which.
+            // The details only go to the log.
+            Err(Madness::DatabaseError(e)) => {
+                warn!("Unable to revoke ban {}: {:#?}", ban_id, e);
+                result.fail(ban_id, "Database error, try again".to_string());
+            }
+            Err(e) => result.fail(ban_id, e.to_string()),
+        }
+    }
+
+    Ok(Json(result))
+}
+
+// Ban managers subscribe here for "ban_change" events with the ban ID and what happened to it.
+// The SSE server holds the connection, like /api/sse/stream.
+#[get("/api/v2/bans/stream")]
+fn stream(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+) -> Result<Redirect, Madness> {
+    account.require_access("bans-manage")?;
+
+    Ok(Redirect::temporary(
+        app.sse_client.events_url(&["bans".to_string()]),
+    ))
+}
+
+#[derive(Serialize)]
+struct BannedCorporation {
+    corporation: Entity,
+    member_count: Option<i64>,
+    // ESI no longer knows the corporation, or it has no members left
+    closed: bool,
+    // The most recent active ban
+    ban: Ban,
+}
+
+#[get("/api/v2/bans/corporations")]
+async fn corporations(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+) -> Result<Json<Vec<BannedCorporation>>, Madness> {
+    let visibility = ban_visibility(app, &account)?;
+
+    let mut latest: BTreeMap<i64, Ban> = BTreeMap::new();
+    for ban in app.ban_service.all_active(&ActiveFilter::default()).await? {
+        let corporation_id = match &ban.entity {
+            Some(entity) if entity.category == "Corporation" => entity.id,
+            _ => continue,
+        };
+        match latest.get(&corporation_id) {
+            Some(existing) if existing.issued_at >= ban.issued_at => (),
+            _ => {
+                latest.insert(corporation_id, ban);
+            }
+        }
+    }
+
+    let mut corporations = Vec::new();
+    for (corporation_id, ban) in latest {
+        let (member_count, closed) = match app
+            .affiliation_service
+            .cached_member_count(corporation_id)
+            .await
+        {
+            Ok(count) => (count, count == Some(0)),
+            Err(Madness::ESIError(ESIError::Status(404)))
+            | Err(Madness::ESIError(ESIError::WithMessage(404, _))) => (None, true),
+            Err(e) => {
+                warn!(
+                    "Unable to load the member count of {}: {:#?}",
+                    corporation_id, e
+                );
+                (None, false)
+            }
+        };
+
+        corporations.push(BannedCorporation {
+            corporation: ban.entity.clone().unwrap(),
+            member_count,
+            closed,
+            ban: ban.redact(visibility),
+        });
+    }
+
+    Ok(Json(corporations))
+}
+
+#[get("/api/v2/bans/cascade-preview/<entity_type>/<entity_id>")]
+async fn cascade_preview(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    entity_type: &str,
+    entity_id: i64,
+) -> Result<Json<CascadePreview>, Madness> {
+    account.require_access("bans-manage")?;
+    ban_impact::check_entity_type(entity_type)?;
+
+    Ok(Json(
+        ban_impact::preview(
+            &app.affiliation_service,
+            entity_type,
+            entity_id,
+            app.clock.now().timestamp(),
+        )
+        .await?,
+    ))
+}
+
+#[derive(Deserialize)]
+struct ImpactJobRequest {
+    entity_type: String,
+    entity_id: i64,
+}
+
+#[derive(Serialize)]
+struct ImpactJobCreated {
+    id: i64,
+}
+
+// Like the cascade preview, but also adds up the member count of every corporation in an
+// alliance. Poll the job for the result.
+#[post("/api/v2/bans/jobs", data = "<req_body>")]
+async fn create_impact_job(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    req_body: Json<ImpactJobRequest>,
+) -> Result<Json<ImpactJobCreated>, Madness> {
+    account.require_access("bans-manage")?;
+
+    let id = ImpactJobRunner::create(
+        app.get_db(),
+        &req_body.entity_type,
+        req_body.entity_id,
+        account.id,
+        app.clock.now().timestamp(),
+    )
+    .await?;
+    ImpactJobRunner::new(app.db.clone(), app.config.clone(), app.clock.clone()).start(id);
+
+    Ok(Json(ImpactJobCreated { id }))
+}
+
+#[derive(Serialize)]
+struct ImpactJob {
+    id: i64,
+    entity: Entity,
+    status: String,
+    progress: i64,
+    total: Option<i64>,
+    result: Option<CascadePreview>,
+    error: Option<String>,
+}
+
+#[get("/api/v2/bans/jobs/<job_id>")]
+async fn impact_job(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    job_id: i64,
+) -> Result<Json<ImpactJob>, Madness> {
+    account.require_access("bans-manage")?;
+
+    let job = match sqlx::query!("SELECT * FROM ban_impact_job WHERE id=$1", job_id)
+        .fetch_optional(app.get_db())
+        .await?
+    {
+        Some(job) => job,
+        None => return Err(Madness::NotFound("Job not found")),
+    };
+
+    Ok(Json(ImpactJob {
+        id: job.id,
+        entity: Entity {
+            id: job.entity_id,
+            name: None,
+            category: job.entity_type,
+        },
+        status: job.status,
+        progress: job.progress,
+        total: job.total,
+        result: job
+            .result
+            .and_then(|result| serde_json::from_str(&result).ok()),
+        error: job.error,
+    }))
+}
+
+#[get("/api/v2/bans/exceptions")]
+async fn list_exceptions(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+) -> Result<Json<Vec<BanException>>, Madness> {
+    account.require_access("bans-manage")?;
+
+    Ok(Json(app.ban_service.exceptions().await?))
+}
+
+#[derive(Deserialize)]
+struct CreateExceptionRequest {
+    corporation_id: i64,
+    reason: String,
+}
+
+// An exception exempts the members of a corporation from inherited corporation and alliance
+// bans. Direct character bans still apply.
+#[post("/api/v2/bans/exceptions", data = "<req_body>")]
+async fn create_exception(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    req_body: Json<CreateExceptionRequest>,
+) -> Result<&'static str, Madness> {
+    account.require_access("bans-manage")?;
+    require_recent_login(app, &account)?;
+
+    validate_field_length("reason", &req_body.reason, REASON_MAX_LENGTH)?;
+
+    let esi_res: EsiResponse = match app
+        .esi_lookup
+        .get_unauthenticated(&EntityType::Corporation.esi_path(req_body.corporation_id))
+        .await
+    {
+        Ok(res) => res,
+        Err(ESIError::Status(404)) | Err(ESIError::WithMessage(404, _)) => {
+            return Err(Madness::NotFound("Corporation not found"))
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    app.ban_service
+        .add_exception(
+            &Entity {
+                id: req_body.corporation_id,
+                name: Some(esi_res.name),
+                category: "Corporation".to_string(),
+            },
+            &req_body.reason,
+            account.id,
+        )
+        .await?;
+
+    Ok("Ok")
+}
+
+#[delete("/api/v2/bans/exceptions/<corporation_id>")]
+async fn delete_exception(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    corporation_id: i64,
+) -> Result<&'static str, Madness> {
+    account.require_access("bans-manage")?;
+    require_recent_login(app, &account)?;
+
+    if !app.ban_service.remove_exception(corporation_id).await? {
+        return Err(Madness::NotFound("Exception not found"));
+    }
+
+    Ok("Ok")
+}
+
+#[derive(Serialize)]
+struct BanDraft {
+    id: i64,
+    payload: serde_json::Value,
+    created_at: i64,
+    updated_at: i64,
+}
+
+#[get("/api/v2/bans/drafts")]
+async fn list_drafts(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+) -> Result<Json<Vec<BanDraft>>, Madness> {
+    account.require_access("bans-manage")?;
+
+    let drafts = sqlx::query!(
+        "SELECT id, payload, created_at, updated_at FROM ban_draft WHERE account_id=$1 ORDER BY updated_at DESC",
+        account.id
+    )
+    .fetch_all(app.get_db())
+    .await?;
+
+    Ok(Json(
+        drafts
+            .into_iter()
+            .map(|draft| BanDraft {
+                id: draft.id,
+                payload: serde_json::from_str(&draft.payload).unwrap_or_default(),
+                created_at: draft.created_at,
+                updated_at: draft.updated_at,
+            })
+            .collect(),
+    ))
+}
+
+#[derive(Deserialize)]
+struct SaveDraftRequest {
+    // Overwrites an existing draft instead of creating a new one
+    id: Option<i64>,
+    payload: serde_json::Value,
+}
+
+#[derive(Serialize)]
+struct SaveDraftResponse {
+    id: i64,
+}
+
+// Drafts are stored as-is and only validated when they're submitted as a ban
+#[post("/api/v2/bans/drafts", data = "<req_body>")]
+async fn save_draft(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    req_body: Json<SaveDraftRequest>,
+) -> Result<Json<SaveDraftResponse>, Madness> {
+    account.require_access("bans-manage")?;
+
+    let payload = req_body.payload.to_string();
+    if payload.len() > DRAFT_MAX_SIZE {
+        return Err(Madness::BadRequest(format!(
+            "Drafts cannot be larger than {} bytes",
+            DRAFT_MAX_SIZE
+        )));
+    }
+    let now = app.clock.now().timestamp();
+
+    if let Some(draft_id) = req_body.id {
+        let updated = sqlx::query!(
+            "UPDATE ban_draft SET payload=$1, updated_at=$2 WHERE id=$3 AND account_id=$4",
+            payload,
+            now,
+            draft_id,
+            account.id
+        )
+        .execute(app.get_db())
+        .await?
+        .rows_affected();
+        if updated == 0 {
+            return Err(Madness::NotFound("Draft not found"));
+        }
+        return Ok(Json(SaveDraftResponse { id: draft_id }));
+    }
+
+    let mut tx = app.get_db().begin().await?;
+    // Serializes saves per account so two tabs can't both squeeze past the limit
+    sqlx::query!(
+        "SELECT id FROM character WHERE id=$1 FOR UPDATE",
+        account.id
+    )
+    .fetch_optional(&mut tx)
+    .await?;
+    let count = sqlx::query!(
+        "SELECT COUNT(*) AS \"count!\" FROM ban_draft WHERE account_id=$1",
+        account.id
+    )
+    .fetch_one(&mut tx)
+    .await?
+    .count;
+    if count >= DRAFT_MAX_COUNT {
+        return Err(Madness::BadRequest(format!(
+            "You cannot have more than {} drafts",
+            DRAFT_MAX_COUNT
+        )));
+    }
+
+    let id = sqlx::query!(
+        "INSERT INTO ban_draft (account_id, payload, created_at, updated_at) VALUES ($1, $2, $3, $3) RETURNING id",
+        account.id,
+        payload,
+        now
+    )
+    .fetch_one(&mut tx)
+    .await?
+    .id;
+    tx.commit().await?;
+
+    Ok(Json(SaveDraftResponse { id }))
+}
+
+#[delete("/api/v2/bans/drafts/<draft_id>")]
+async fn delete_draft(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    draft_id: i64,
+) -> Result<&'static str, Madness> {
+    account.require_access("bans-manage")?;
+
+    let deleted = sqlx::query!(
+        "DELETE FROM ban_draft WHERE id=$1 AND account_id=$2",
+        draft_id,
+        account.id
+    )
+    .execute(app.get_db())
+    .await?
+    .rows_affected();
+    // Someone else's draft looks the same as a missing one
+    if deleted == 0 {
+        return Err(Madness::NotFound("Draft not found"));
+    }
+
+    Ok("Ok")
+}
+
+#[derive(Deserialize)]
+struct BulkTagRequest {
+    ids: Vec<i64>,
+    tag: String,
+}
+
+// The tag of a bulk request, normalized like the tags of a single ban
+fn bulk_tag(
+    app: &Application,
+    account: &AuthenticatedAccount,
+    input: &BulkTagRequest,
+) -> Result<String, Madness> {
+    account.require_access("bans-manage")?;
+    require_recent_login(app, account)?;
+    // Tags are internal, like the reason
+    if !ban_visibility(app, account)?.reason {
+        return Err(Madness::AccessDenied);
+    }
+    if input.ids.len() > BULK_MAX_COUNT {
+        return Err(Madness::BadRequest(format!(
+            "Cannot tag more than {} bans at once",
+            BULK_MAX_COUNT
+        )));
+    }
+
+    let tags = normalize_tags(&[input.tag.clone()]).map_err(Madness::BadRequest)?;
+    match tags.into_iter().next() {
+        Some(tag) => Ok(tag),
+        None => Err(Madness::BadRequest("The tag cannot be empty".to_string())),
+    }
+}
+
+// For managing shared sets of bans, e.g. the ones synced with a coalition
+#[post("/api/v2/bans/tag", data = "<input>")]
+async fn tag(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    input: Json<BulkTagRequest>,
+) -> Result<Json<BulkResult<i64>>, Madness> {
+    let tag = bulk_tag(app, &account, &input)?;
+    let result = app.ban_service.add_tag(&input.ids, &tag).await?;
+    app.bans_changed();
+    Ok(Json(result))
+}
+
+#[post("/api/v2/bans/untag", data = "<input>")]
+async fn untag(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    input: Json<BulkTagRequest>,
+) -> Result<Json<BulkResult<i64>>, Madness> {
+    let tag = bulk_tag(app, &account, &input)?;
+    let result = app.ban_service.remove_tag(&input.ids, &tag).await?;
+    app.bans_changed();
+    Ok(Json(result))
+}
+
+// One of the two, a partner's list as they sent it
+#[derive(Deserialize)]
+struct ImportDiffRequest {
+    csv: Option<String>,
+    bans: Option<Vec<ImportedBan>>,
+}
+
+// What importing a partner's list would change, without importing anything
+#[post("/api/v2/bans/import/diff", data = "<input>")]
+async fn import_diff(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    input: Json<ImportDiffRequest>,
+) -> Result<Json<ImportDiff>, Madness> {
+    account.require_access("bans-manage")?;
+    // The diff shows our internal reasons
+    if !ban_visibility(app, &account)?.reason {
+        return Err(Madness::AccessDenied);
+    }
+
+    let input = input.into_inner();
+    let bans = match (input.csv, input.bans) {
+        (Some(csv), None) => ban_import::parse_csv(&csv).map_err(Madness::BadRequest)?,
+        (None, Some(bans)) => bans,
+        _ => return Err(Madness::BadRequest("Send either csv or bans".to_string())),
+    };
+    ban_import::validate(&bans).map_err(Madness::BadRequest)?;
+
+    Ok(Json(ban_import::diff(&app.ban_service, &bans).await?))
+}
+
+#[derive(Serialize)]
+struct RecomputeExpiryResponse {
+    updated: u64,
+}
+
+#[derive(Deserialize)]
+struct ReassignRequest {
+    from_character_id: i64,
+    to_character_id: i64,
+}
+
+// For FCs leaving, their active bans move to a successor. Kept apart from update so editing a
+// ban never changes who is accountable for it.
+#[post("/api/v2/bans/reassign", data = "<input>")]
+async fn reassign(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    input: Json<ReassignRequest>,
+) -> Result<Json<BulkResult<i64>>, Madness> {
+    account.require_access("bans-admin")?;
+    require_recent_login(app, &account)?;
+
+    if input.from_character_id == input.to_character_id {
+        return Err(Madness::BadRequest(
+            "Cannot reassign bans to the same character".to_string(),
+        ));
+    }
+    if sqlx::query!(
+        "SELECT id FROM character WHERE id=$1",
+        input.to_character_id
+    )
+    .fetch_optional(app.get_db())
+    .await?
+    .is_none()
+    {
+        return Err(Madness::BadRequest(format!(
+            "Unknown character {} for \"to_character_id\"",
+            input.to_character_id
+        )));
+    }
+
+    let ban_ids = app
+        .ban_service
+        .reassign(input.from_character_id, input.to_character_id, account.id)
+        .await?;
+    info!(
+        "{} reassigned {} bans from {} to {}: {:?}",
+        account.id,
+        ban_ids.len(),
+        input.from_character_id,
+        input.to_character_id,
+        ban_ids
+    );
+    // All of them move together, so none can fail on their own
+    let mut result = BulkResult::default();
+    for ban_id in ban_ids {
+        notify_ban_change(app, ban_id, "updated").await;
+        result.succeed(ban_id);
+    }
+
+    Ok(Json(result))
+}
+
+#[derive(Serialize)]
+struct PurgePreview {
+    count: i64,
+    cutoff: i64,
+    token: String,
+    expires_in: u32,
+}
+
+#[derive(Deserialize)]
+struct PurgeRequest {
+    older_than_days: i64,
+    token: String,
+}
+
+#[derive(Serialize)]
+struct PurgeResponse {
+    deleted: u64,
+}
+
+fn check_older_than_days(older_than_days: i64) -> Result<(), Madness> {
+    if older_than_days < 0 {
+        return Err(Madness::BadRequest(
+            "older_than_days cannot be negative".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// Step one of a purge: how many ended bans would go, and the token that confirms it
+#[get("/api/v2/bans/purge/preview?<older_than_days>")]
+async fn purge_preview(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    older_than_days: i64,
+) -> Result<Json<PurgePreview>, Madness> {
+    account.require_access("bans-admin")?;
+    check_older_than_days(older_than_days)?;
+
+    let token = PurgeToken::new(account.id, older_than_days, app.clock.now().timestamp());
+    Ok(Json(PurgePreview {
+        count: app.ban_service.count_ended_before(token.cutoff).await?,
+        cutoff: token.cutoff,
+        token: token.encode(&app.token_secret),
+        expires_in: PURGE_TOKEN_TTL,
+    }))
+}
+
+// Deletes bans for good, only with a token from the preview for the same older_than_days.
+// The cutoff comes from the token, so bans that ended since the preview are kept. The token is
+// sent in the body to keep it out of access logs.
+#[delete("/api/v2/bans/purge", data = "<req_body>")]
+async fn purge(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    req_body: Json<PurgeRequest>,
+) -> Result<Json<PurgeResponse>, Madness> {
+    account.require_access("bans-admin")?;
+    require_recent_login(app, &account)?;
+    check_older_than_days(req_body.older_than_days)?;
+
+    let token = match PurgeToken::decode(
+        &req_body.token,
+        &app.token_secret,
+        account.id,
+        req_body.older_than_days,
+    ) {
+        Some(token) => token,
+        None => {
+            return Err(Madness::BadRequest(
+                "The confirmation token is invalid, expired or for other parameters, preview the purge again".to_string(),
+            ))
+        }
+    };
+
+    let deleted = app.ban_service.purge(token.cutoff).await?;
+    app.bans_changed();
+    info!(
+        "{} purged {} bans that ended before {}",
+        account.id, deleted, token.cutoff
+    );
+    Ok(Json(PurgeResponse { deleted }))
+}
+
+// Ban columns pointing at characters that don't exist, which would make ban lookups fail
+#[get("/api/v2/bans/integrity")]
+async fn integrity(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+) -> Result<Json<Vec<OrphanedReference>>, Madness> {
+    account.require_access("bans-admin")?;
+
+    Ok(Json(app.ban_service.orphaned_references().await?))
+}
+
+#[post("/api/v2/bans/recompute-expiry")]
+async fn recompute_expiry(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+) -> Result<Json<RecomputeExpiryResponse>, Madness> {
+    account.require_access("bans-admin")?;
+    require_recent_login(app, &account)?;
+
+    let updated = app.ban_service.recompute_expiry().await?;
+    app.bans_changed();
+    info!("{} recomputed the expiry of {} bans", account.id, updated);
+
+    Ok(Json(RecomputeExpiryResponse { updated }))
+}
+
+pub fn routes() -> Vec<rocket::Route> {
+    routes![
+        list,              //  GET     /api/v2/bans
+        recent,            //  GET     /api/v2/bans/recent
+        similar,           //  GET     /api/v2/bans/similar
+        mine,              //  GET     /api/v2/bans/mine
+        incomplete,        //  GET     /api/v2/bans/incomplete
+        changes,           //  GET     /api/v2/bans/changes
+        permissions,       //  GET     /api/v2/bans/permissions
         create,            //  POST    /api/v2/bans
+        announce,          //  POST    /api/v2/bans/<ban_id>/announce
+        pending,           //  GET     /api/v2/bans/pending
+        match_bans,        //  GET     /api/v2/bans/match
+        check,             //  POST    /api/v2/bans/check
+        status,            //  GET     /api/v2/bans/status/<entity_type>/<entity_id>
+        approve,           //  POST    /api/v2/bans/<ban_id>/approve
+        recompute_expiry,  //  POST    /api/v2/bans/recompute-expiry
+        integrity,         //  GET     /api/v2/bans/integrity
+        reassign,          //  POST    /api/v2/bans/reassign
+        purge_preview,     //  GET     /api/v2/bans/purge/preview
+        purge,             //  DELETE  /api/v2/bans/purge
+        tag,               //  POST    /api/v2/bans/tag
+        import_diff,       //  POST    /api/v2/bans/import/diff
+        untag,             //  POST    /api/v2/bans/untag
+        corporations,      //  GET     /api/v2/bans/corporations
+        cascade_preview,   //  GET     /api/v2/bans/cascade-preview/<entity_type>/<entity_id>
+        create_impact_job, //  POST    /api/v2/bans/jobs
+        impact_job,        //  GET     /api/v2/bans/jobs/<job_id>
+        list_exceptions,   //  GET     /api/v2/bans/exceptions
+        create_exception,  //  POST    /api/v2/bans/exceptions
+        delete_exception,  //  DELETE  /api/v2/bans/exceptions/<corporation_id>
+        list_drafts,       //  GET     /api/v2/bans/drafts
+        save_draft,        //  POST    /api/v2/bans/drafts
+        delete_draft,      //  DELETE  /api/v2/bans/drafts/<draft_id>
         character_history, //  GET     /api/v2/bans/<character_id>
-        update,            //  PUT     /api/v2/bans/<ban_id>
-        revoke             //  DELETE  /api/v2/bans/<ban_id>
+        details,           //  GET     /api/v2/bans/<ban_id>/details
+        compare,           //  GET     /api/v2/bans/compare
+        export_character,  //  GET     /api/v2/bans/<character_id>/export.json
+        reasons,           //  GET     /api/v2/bans/<ban_id>/reasons
+        update,            //  PATCH   /api/v2/bans/<ban_id>
+        extend,            //  POST    /api/v2/bans/<ban_id>/extend
+        acknowledge,       //  POST    /api/v2/bans/<ban_id>/acknowledge
+        revoke,            //  DELETE  /api/v2/bans/<ban_id>
+        bulk_revoke,       //  POST    /api/v2/bans/revoke
+        stream,            //  GET     /api/v2/bans/stream
+        public_check       //  GET     /api/v2/public/bans/check
     ]
 }
+
+#[cfg(test)]
+mod tests {
+    use rocket::http::{ContentType, Header, Status};
+    use serde_json::{json, Value};
+
+    use super::ANNOUNCE_LIMIT;
+    use crate::config::AutoTagRule;
+    use crate::util::{
+        testapp::{FakeEsi, ReadJson, TestApp},
+        testdb::TestDatabase,
+    };
+
+    const FC: i64 = 1001;
+    const PILOT: i64 = 1002;
+    const TARGET: i64 = 2001;
+    const CORPORATION: i64 = 98000001;
+    const BAD_CORPORATION: i64 = 98000002;
+
+    async fn setup() -> Option<TestApp> {
+        let app = TestApp::new(
+            FakeEsi::new(&[
+                (FC, "Some FC", "Character"),
+                (TARGET, "Bad Pilot", "Character"),
+                (BAD_CORPORATION, "Bad Corp", "Corporation"),
+            ])
+            .with_affiliation(FC, CORPORATION, None)
+            .with_affiliation(PILOT, BAD_CORPORATION, None)
+            .with_affiliation(TARGET, CORPORATION, None),
+        )
+        .await?;
+        app.add_character(FC, "Some FC", Some("FC")).await;
+        app.add_character(PILOT, "Some Pilot", None).await;
+        Some(app)
+    }
+
+    async fn active_bans(app: &TestApp) -> Vec<Value> {
+        let response = app
+            .login(app.client.get("/api/v2/bans"), FC)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        response.json().await.unwrap()
+    }
+
+    #[rocket::async_test]
+    async fn test_ban_lifecycle() {
+        let app = match setup().await {
+            Some(app) => app,
+            None => return,
+        };
+
+        let response = app
+            .login(app.client.post("/api/v2/bans"), FC)
+            .header(ContentType::JSON)
+            .body(
+                json!({
+                    "entity": { "id": TARGET, "category": "Character" },
+                    "reason": "[RMT] Selling ISK",
+                    "public_reason": "Breaking the rules",
+                })
+                .to_string(),
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Created);
+        let location = response.headers().get_one("Location").unwrap().to_string();
+        let created: Value = response.json().await.unwrap();
+
+        let bans = active_bans(&app).await;
+        assert_eq!(bans.len(), 1);
+        assert_eq!(bans[0]["entity"]["name"], "Bad Pilot");
+        assert_eq!(bans[0]["category"], "RMT");
+        assert_eq!(bans[0]["type_mismatch"], false);
+        let ban_id = bans[0]["id"].as_i64().unwrap();
+        assert_eq!(created["id"], ban_id);
+        assert_eq!(location, format!("/api/v2/bans/{}/details", ban_id));
+
+        let response = app
+            .login(
+                app.client
+                    .patch(format!("/api/v2/bans/{}?tz=Asia/Tokyo", ban_id)),
+                FC,
+            )
+            .header(ContentType::JSON)
+            .body(json!({ "reason": "Selling ISK for real money" }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let updated: Value = response.json().await.unwrap();
+        assert_eq!(updated["id"], ban_id);
+        assert_eq!(updated["reason"], "Selling ISK for real money");
+        assert!(updated["issued_at_iso"]
+            .as_str()
+            .unwrap()
+            .ends_with("+09:00"));
+        assert_eq!(
+            active_bans(&app).await[0]["reason"],
+            "Selling ISK for real money"
+        );
+
+        let response = app
+            .login(app.client.delete(format!("/api/v2/bans/{}", ban_id)), FC)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NoContent);
+        assert!(active_bans(&app).await.is_empty());
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_list_etag() {
+        let app = match setup().await {
+            Some(app) => app,
+            None => return,
+        };
+
+        async fn list(app: &TestApp, if_none_match: Option<&str>) -> (Status, String) {
+            let mut request = app.login(app.client.get("/api/v2/bans"), FC);
+            if let Some(etag) = if_none_match {
+                request = request.header(Header::new("If-None-Match", etag.to_string()));
+            }
+            let response = request.dispatch().await;
+            let etag = response.headers().get_one("ETag").unwrap().to_string();
+            (response.status(), etag)
+        }
+
+        let (status, etag) = list(&app, None).await;
+        assert_eq!(status, Status::Ok);
+        assert_eq!(
+            list(&app, Some(&etag)).await,
+            (Status::NotModified, etag.clone())
+        );
+
+        let response = app
+            .login(app.client.post("/api/v2/bans"), FC)
+            .header(ContentType::JSON)
+            .body(
+                json!({ "entity": { "id": TARGET, "category": "Character" }, "reason": "x" })
+                    .to_string(),
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Created);
+
+        // The cached copy is out of date now
+        let (status, changed) = list(&app, Some(&etag)).await;
+        assert_eq!(status, Status::Ok);
+        assert_ne!(changed, etag);
+
+        // Relative timestamps are never cached
+        let response = app
+            .login(app.client.get("/api/v2/bans?relative=true"), FC)
+            .dispatch()
+            .await;
+        assert_eq!(response.headers().get_one("ETag"), None);
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_public_check() {
+        let app = match TestApp::with_config(
+            FakeEsi::new(&[(TARGET, "Bad Pilot", "Character")]),
+            |config| {
+                config.bans.public_check = true;
+                config.bans.public_check_limit = 2;
+            },
+        )
+        .await
+        {
+            Some(app) => app,
+            None => return,
+        };
+        app.add_character(FC, "Some FC", Some("FC")).await;
+
+        let response = app
+            .login(app.client.post("/api/v2/bans"), FC)
+            .header(ContentType::JSON)
+            .body(
+                json!({
+                    "entity": { "id": TARGET, "category": "Character" },
+                    "reason": "Internal notes",
+                    "public_reason": "Breaking the rules",
+                })
+                .to_string(),
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Created);
+
+        let check = |character_id: i64| {
+            app.client
+                .get(format!(
+                    "/api/v2/public/bans/check?character_id={}",
+                    character_id
+                ))
+                .remote("127.0.0.1:50000".parse().unwrap())
+                .dispatch()
+        };
+
+        let response = check(TARGET).await;
+        assert_eq!(response.status(), Status::Ok);
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(
+            body,
+            json!({ "banned": true, "public_reason": "Breaking the rules", "expires_at_iso": null })
+        );
+
+        let body: Value = check(PILOT).await.json().await.unwrap();
+        assert_eq!(body["banned"], false);
+
+        assert_eq!(check(TARGET).await.status(), Status::TooManyRequests);
+
+        // X-Real-IP is only believed from a trusted proxy
+        let response = app
+            .client
+            .get(format!("/api/v2/public/bans/check?character_id={}", TARGET))
+            .remote("127.0.0.1:50000".parse().unwrap())
+            .header(Header::new("X-Real-IP", "10.0.0.1"))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::TooManyRequests);
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_public_check_behind_proxy() {
+        let app = match TestApp::with_config(FakeEsi::new(&[]), |config| {
+            config.app.trusted_proxies = vec!["127.0.0.1".parse().unwrap()];
+            config.bans.public_check = true;
+            config.bans.public_check_limit = 1;
+        })
+        .await
+        {
+            Some(app) => app,
+            None => return,
+        };
+
+        let check = |real_ip: &'static str| {
+            app.client
+                .get(format!("/api/v2/public/bans/check?character_id={}", TARGET))
+                .remote("127.0.0.1:50000".parse().unwrap())
+                .header(Header::new("X-Real-IP", real_ip))
+                .dispatch()
+        };
+        // Each client behind the proxy has a limit of its own
+        assert_eq!(check("10.0.0.1").await.status(), Status::Ok);
+        assert_eq!(check("10.0.0.1").await.status(), Status::TooManyRequests);
+        assert_eq!(check("10.0.0.2").await.status(), Status::Ok);
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_status() {
+        let app = match setup().await {
+            Some(app) => app,
+            None => return,
+        };
+
+        let response = app
+            .login(app.client.post("/api/v2/bans"), FC)
+            .header(ContentType::JSON)
+            .body(
+                json!({
+                    "entity": { "id": BAD_CORPORATION, "category": "Corporation" },
+                    "reason": "x",
+                    "public_reason": "Awoxing",
+                })
+                .to_string(),
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Created);
+
+        // The pilot is only banned through their corporation
+        sqlx::query!(
+            "INSERT INTO corporation (id, name, updated_at) VALUES ($1, 'Bad Corp', 0)",
+            BAD_CORPORATION
+        )
+        .execute(app.db())
+        .await
+        .unwrap();
+        sqlx::query!(
+            "UPDATE character SET corporation_id=$1 WHERE id=$2",
+            BAD_CORPORATION,
+            PILOT
+        )
+        .execute(app.db())
+        .await
+        .unwrap();
+
+        for (path, banned) in [
+            (format!("Corporation/{}", BAD_CORPORATION), true),
+            (format!("Character/{}", PILOT), true),
+            (format!("Character/{}", TARGET), false),
+        ]
+        .iter()
+        {
+            let response = app
+                .login(app.client.get(format!("/api/v2/bans/status/{}", path)), FC)
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Ok);
+            assert_eq!(
+                response.headers().get_one("Cache-Control"),
+                Some("private, max-age=30")
+            );
+            let body: Value = response.json().await.unwrap();
+            let expected = match banned {
+                true => json!({ "banned": true, "public_reason": "Awoxing", "expires_at": null }),
+                false => json!({ "banned": false, "public_reason": null, "expires_at": null }),
+            };
+            assert_eq!(body, expected, "{}", path);
+        }
+
+        let response = app
+            .login(app.client.get("/api/v2/bans/status/Planet/1"), FC)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::BadRequest);
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_scheduled() {
+        let app = match setup().await {
+            Some(app) => app,
+            None => return,
+        };
+
+        async fn schedule(
+            app: &TestApp,
+            starts_at: i64,
+        ) -> rocket::local::asynchronous::LocalResponse<'_> {
+            app.login(app.client.post("/api/v2/bans"), FC)
+                .header(ContentType::JSON)
+                .body(
+                    json!({
+                        "entity": { "id": BAD_CORPORATION, "category": "Corporation" },
+                        "reason": "Grace period ends in a week",
+                        "starts_at": starts_at,
+                    })
+                    .to_string(),
+                )
+                .dispatch()
+                .await
+        }
+
+        async fn banned(app: &TestApp) -> Value {
+            let response = app
+                .login(
+                    app.client.get(format!(
+                        "/api/v2/bans/status/Corporation/{}",
+                        BAD_CORPORATION
+                    )),
+                    FC,
+                )
+                .dispatch()
+                .await;
+            let body: Value = response.json().await.unwrap();
+            body["banned"].clone()
+        }
+
+        let now = chrono::Utc::now().timestamp();
+        assert_eq!(
+            schedule(&app, now - 60).await.status(),
+            Status::UnprocessableEntity
+        );
+
+        let response = schedule(&app, now + 3600).await;
+        assert_eq!(response.status(), Status::Created);
+        let created: Value = response.json().await.unwrap();
+        let ban_id = created["id"].as_i64().unwrap();
+
+        // Staff can see it, but it isn't enforced yet
+        assert!(active_bans(&app).await.is_empty());
+        let response = app
+            .login(app.client.get("/api/v2/bans?include_scheduled=true"), FC)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let listed: Vec<Value> = response.json().await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0]["id"], ban_id);
+        assert_eq!(listed[0]["status"], "scheduled");
+        let response = app
+            .login(app.client.get("/api/v2/bans?include_scheduled=true"), PILOT)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Unauthorized);
+        let response = app
+            .login(
+                app.client.get(format!("/api/v2/bans/{}/details", ban_id)),
+                FC,
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let details: Value = response.json().await.unwrap();
+        assert_eq!(details["status"], "scheduled");
+        assert_eq!(details["starts_at"], now + 3600);
+        assert_eq!(banned(&app).await, false);
+
+        sqlx::query!("UPDATE ban SET starts_at=$1 WHERE id=$2", now - 1, ban_id)
+            .execute(app.db())
+            .await
+            .unwrap();
+        let bans = active_bans(&app).await;
+        assert_eq!(bans.len(), 1);
+        assert_eq!(bans[0]["status"], "active");
+        assert_eq!(banned(&app).await, true);
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_scheduled_needs_reason_access() {
+        let app = match TestApp::with_config(
+            FakeEsi::new(&[(TARGET, "Bad Pilot", "Character")]),
+            |config| config.bans.public_reason_access = "".to_string(),
+        )
+        .await
+        {
+            Some(app) => app,
+            None => return,
+        };
+        app.add_character(FC, "Some FC", Some("FC")).await;
+        app.add_character(PILOT, "Some Pilot", None).await;
+
+        let now = chrono::Utc::now().timestamp();
+        let response = app
+            .login(app.client.post("/api/v2/bans"), FC)
+            .header(ContentType::JSON)
+            .body(
+                json!({
+                    "entity": { "id": TARGET, "category": "Character" },
+                    "reason": "Grace period ends in a week",
+                    "starts_at": now + 3600,
+                })
+                .to_string(),
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Created);
+        let created: Value = response.json().await.unwrap();
+        let ban_id = created["id"].as_i64().unwrap();
+
+        let get =
+            |path: String, account_id: i64| app.login(app.client.get(path), account_id).dispatch();
+
+        // Pilots can look bans up, but only see this one once it starts
+        let response = get(format!("/api/v2/bans/{}/details", ban_id), PILOT).await;
+        assert_eq!(response.status(), Status::NotFound);
+        let response = get(format!("/api/v2/bans/{}", TARGET), PILOT).await;
+        let history: Vec<Value> = response.json().await.unwrap();
+        assert!(history.is_empty());
+        let response = get("/api/v2/bans/changes?since=0".to_string(), PILOT).await;
+        let feed: Value = response.json().await.unwrap();
+        assert_eq!(feed["changes"], json!([]));
+
+        let response = get(format!("/api/v2/bans/{}/details", ban_id), FC).await;
+        assert_eq!(response.status(), Status::Ok);
+        let response = get(format!("/api/v2/bans/{}", TARGET), FC).await;
+        let history: Vec<Value> = response.json().await.unwrap();
+        assert_eq!(history.len(), 1);
+
+        sqlx::query!("UPDATE ban SET starts_at=$1 WHERE id=$2", now - 1, ban_id)
+            .execute(app.db())
+            .await
+            .unwrap();
+        let response = get(format!("/api/v2/bans/{}/details", ban_id), PILOT).await;
+        assert_eq!(response.status(), Status::Ok);
+        let response = get(format!("/api/v2/bans/{}", TARGET), PILOT).await;
+        let history: Vec<Value> = response.json().await.unwrap();
+        assert_eq!(history.len(), 1);
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_recent() {
+        let app = match setup().await {
+            Some(app) => app,
+            None => return,
+        };
+
+        let now = chrono::Utc::now().timestamp();
+        for (id, category, starts_at) in [
+            (TARGET, "Character", None),
+            (BAD_CORPORATION, "Corporation", Some(now + 3600)),
+        ]
+        .iter()
+        {
+            let response = app
+                .login(app.client.post("/api/v2/bans"), FC)
+                .header(ContentType::JSON)
+                .body(
+                    json!({
+                        "entity": { "id": id, "category": category },
+                        "reason": "x",
+                        "starts_at": starts_at,
+                    })
+                    .to_string(),
+                )
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Created);
+        }
+
+        // The scheduled ban only shows up once it starts
+        let response = app
+            .login(app.client.get("/api/v2/bans/recent"), FC)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let bans: Vec<Value> = response.json().await.unwrap();
+        assert_eq!(bans.len(), 1);
+        assert_eq!(bans[0]["entity"]["id"], TARGET);
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_public_check_is_off_by_default() {
+        let app = match setup().await {
+            Some(app) => app,
+            None => return,
+        };
+
+        let response = app
+            .client
+            .get(format!("/api/v2/public/bans/check?character_id={}", TARGET))
+            .remote("127.0.0.1:50000".parse().unwrap())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NotFound);
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_check_many_characters() {
+        let app = match setup().await {
+            Some(app) => app,
+            None => return,
+        };
+
+        for (id, category) in [(TARGET, "Character"), (BAD_CORPORATION, "Corporation")].iter() {
+            let response = app
+                .login(app.client.post("/api/v2/bans"), FC)
+                .header(ContentType::JSON)
+                .body(
+                    json!({ "entity": { "id": id, "category": category }, "reason": "x" })
+                        .to_string(),
+                )
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Created);
+        }
+
+        let response = app
+            .login(app.client.post("/api/v2/bans/check"), FC)
+            .header(ContentType::JSON)
+            .body(json!({ "character_ids": [TARGET, FC, PILOT, FC] }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let checks: Vec<Value> = response.json().await.unwrap();
+        assert_eq!(checks.len(), 3);
+
+        let check = |id: i64| checks.iter().find(|c| c["character_id"] == id).unwrap();
+        assert_eq!(check(FC)["banned"], false);
+        assert!(check(FC)["ban"].is_null());
+        assert_eq!(check(TARGET)["banned"], true);
+        assert_eq!(check(TARGET)["ban"]["entity"]["category"], "Character");
+        assert_eq!(check(PILOT)["banned"], true);
+        assert_eq!(check(PILOT)["ban"]["entity"]["id"], BAD_CORPORATION);
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_extend() {
+        let app = match setup().await {
+            Some(app) => app,
+            None => return,
+        };
+
+        let response = app
+            .login(app.client.post("/api/v2/bans"), FC)
+            .header(ContentType::JSON)
+            .body(
+                json!({
+                    "entity": { "id": TARGET, "category": "Character" },
+                    "reason": "Keep this",
+                    "revoked_at": 4_000_000_000i64,
+                })
+                .to_string(),
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Created);
+        let ban = active_bans(&app).await.remove(0);
+        let ban_id = ban["id"].as_i64().unwrap();
+        let expires_at = ban["revoked_at"].as_i64().unwrap();
+
+        let extend = |body: Value| {
+            app.login(
+                app.client.post(format!("/api/v2/bans/{}/extend", ban_id)),
+                FC,
+            )
+            .header(ContentType::JSON)
+            .body(body.to_string())
+            .dispatch()
+        };
+
+        let response = extend(json!({ "days": 2 })).await;
+        assert_eq!(response.status(), Status::Ok);
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(body["expires_at"], expires_at + 2 * 24 * 60 * 60);
+
+        let ban = active_bans(&app).await.remove(0);
+        assert_eq!(ban["revoked_at"], expires_at + 2 * 24 * 60 * 60);
+        assert_eq!(ban["reason"], "Keep this");
+
+        // Extending can't shorten a ban
+        let response = extend(json!({ "expiry_day": 4_000_000_000i64 })).await;
+        assert_eq!(response.status(), Status::BadRequest);
+
+        let response = app
+            .login(app.client.delete(format!("/api/v2/bans/{}", ban_id)), FC)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NoContent);
+        let response = extend(json!({ "days": 2 })).await;
+        assert_eq!(response.status(), Status::BadRequest);
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_changes() {
+        let app = match setup().await {
+            Some(app) => app,
+            None => return,
+        };
+
+        async fn create(app: &TestApp, entity: Value) -> i64 {
+            let response = app
+                .login(app.client.post("/api/v2/bans"), FC)
+                .header(ContentType::JSON)
+                .body(
+                    json!({ "entity": entity, "reason": "x", "revoked_at": 4_000_000_000i64 })
+                        .to_string(),
+                )
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Created);
+            let created: Value = response.json().await.unwrap();
+            created["id"].as_i64().unwrap()
+        }
+
+        async fn changes(app: &TestApp, query: &str) -> Value {
+            let response = app
+                .login(
+                    app.client.get(format!("/api/v2/bans/changes?{}", query)),
+                    FC,
+                )
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Ok);
+            response.json().await.unwrap()
+        }
+
+        let revoked = create(
+            &app,
+            json!({ "id": BAD_CORPORATION, "category": "Corporation" }),
+        )
+        .await;
+        let created = create(&app, json!({ "id": TARGET, "category": "Character" })).await;
+        sqlx::query!("UPDATE ban SET issued_at=1000 WHERE id=$1", revoked)
+            .execute(app.db())
+            .await
+            .unwrap();
+        let response = app
+            .login(app.client.delete(format!("/api/v2/bans/{}", revoked)), FC)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NoContent);
+
+        let feed = changes(&app, "since=1500").await;
+        let mut seen: Vec<(i64, &str)> = feed["changes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|change| {
+                (
+                    change["ban"]["id"].as_i64().unwrap(),
+                    change["change"].as_str().unwrap(),
+                )
+            })
+            .collect();
+        seen.sort();
+        let mut expected = vec![(revoked, "revoked"), (created, "created")];
+        expected.sort();
+        assert_eq!(seen, expected);
+        assert_eq!(feed["has_more"], false);
+
+        // An older ban shows up again once it's changed
+        sqlx::query!("UPDATE ban SET issued_at=1000 WHERE id=$1", created)
+            .execute(app.db())
+            .await
+            .unwrap();
+        let since = feed["next_since"].as_i64().unwrap();
+        assert!(changes(&app, &format!("since={}", since)).await["changes"]
+            .as_array()
+            .unwrap()
+            .is_empty());
+        let response = app
+            .login(
+                app.client.post(format!("/api/v2/bans/{}/extend", created)),
+                FC,
+            )
+            .header(ContentType::JSON)
+            .body(json!({ "days": 1 }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+
+        let feed = changes(&app, "since=1500&limit=1").await;
+        assert_eq!(feed["changes"].as_array().unwrap().len(), 1);
+        assert_eq!(feed["has_more"], true);
+        let feed = changes(
+            &app,
+            &format!(
+                "since={}&after={}&limit=1",
+                feed["next_since"], feed["next_after"]
+            ),
+        )
+        .await;
+        assert_eq!(feed["changes"].as_array().unwrap().len(), 1);
+        assert_eq!(feed["changes"][0]["ban"]["id"], created);
+        assert_eq!(feed["changes"][0]["change"], "updated");
+
+        // Edits are updates too, they don't make the ban look new
+        let response = app
+            .login(app.client.patch(format!("/api/v2/bans/{}", created)), FC)
+            .header(ContentType::JSON)
+            .body(json!({ "reason": "y" }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let feed = changes(&app, "since=1500").await;
+        let edited = feed["changes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|change| change["ban"]["id"] == created)
+            .unwrap();
+        assert_eq!(edited["change"], "updated");
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_history_as_of() {
+        let app = match setup().await {
+            Some(app) => app,
+            None => return,
+        };
+
+        let response = app
+            .login(app.client.post("/api/v2/bans"), FC)
+            .header(ContentType::JSON)
+            .body(
+                json!({ "entity": { "id": TARGET, "category": "Character" }, "reason": "x" })
+                    .to_string(),
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Created);
+        let ban_id = active_bans(&app).await[0]["id"].as_i64().unwrap();
+
+        let history = |query: &str| {
+            app.login(
+                app.client.get(format!("/api/v2/bans/{}{}", TARGET, query)),
+                FC,
+            )
+            .dispatch()
+        };
+        async fn count(response: rocket::local::asynchronous::LocalResponse<'_>) -> usize {
+            assert_eq!(response.status(), Status::Ok);
+            response.json::<Vec<Value>>().await.unwrap().len()
+        }
+
+        // An edit doesn't move when the ban started
+        sqlx::query!("UPDATE ban SET issued_at=1000 WHERE id=$1", ban_id)
+            .execute(app.db())
+            .await
+            .unwrap();
+        let response = app
+            .login(app.client.patch(format!("/api/v2/bans/{}", ban_id)), FC)
+            .header(ContentType::JSON)
+            .body(json!({ "reason": "y" }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(count(history("?as_of=1500").await).await, 1);
+
+        // In effect from 1000 until 2000
+        sqlx::query!("UPDATE ban SET revoked_at=2000 WHERE id=$1", ban_id)
+            .execute(app.db())
+            .await
+            .unwrap();
+
+        assert_eq!(count(history("").await).await, 1);
+        assert_eq!(count(history("?as_of=1500").await).await, 1);
+        assert_eq!(count(history("?as_of=999").await).await, 0);
+        assert_eq!(count(history("?as_of=2000").await).await, 0);
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_recently_expired() {
+        let app = match setup().await {
+            Some(app) => app,
+            None => return,
+        };
+
+        // One ran out an hour ago, the other ten days ago
+        let now = chrono::Utc::now().timestamp();
+        for revoked_at in [now - 3600, now - 10 * 86400].iter() {
+            let response = app
+                .login(app.client.post("/api/v2/bans"), FC)
+                .header(ContentType::JSON)
+                .body(
+                    json!({ "entity": { "id": TARGET, "category": "Character" }, "reason": "x" })
+                        .to_string(),
+                )
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Created);
+            let ban_id = active_bans(&app).await[0]["id"].as_i64().unwrap();
+            sqlx::query!(
+                "UPDATE ban SET issued_at=$2, revoked_at=$3 WHERE id=$1",
+                ban_id,
+                revoked_at - 86400,
+                revoked_at
+            )
+            .execute(app.db())
+            .await
+            .unwrap();
+        }
+
+        let response = app
+            .login(app.client.get(format!("/api/v2/bans/{}", TARGET)), FC)
+            .dispatch()
+            .await;
+        let history: Vec<Value> = response.json().await.unwrap();
+        let mut flags: Vec<(i64, bool)> = history
+            .iter()
+            .map(|ban| {
+                (
+                    ban["revoked_at"].as_i64().unwrap(),
+                    ban["recently_expired"].as_bool().unwrap(),
+                )
+            })
+            .collect();
+        flags.sort();
+        assert_eq!(flags, vec![(now - 10 * 86400, false), (now - 3600, true)]);
+
+        // Not part of the V2 shape
+        let response = app
+            .login(app.client.get(format!("/api/v2/bans/{}?v=2", TARGET)), FC)
+            .dispatch()
+            .await;
+        let history: Vec<Value> = response.json().await.unwrap();
+        assert!(history[0].get("recently_expired").is_none());
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_acknowledge() {
+        let app = match setup().await {
+            Some(app) => app,
+            None => return,
+        };
+        app.add_character(TARGET, "Bad Pilot", None).await;
+
+        let response = app
+            .login(app.client.post("/api/v2/bans"), FC)
+            .header(ContentType::JSON)
+            .body(
+                json!({ "entity": { "id": TARGET, "category": "Character" }, "reason": "x" })
+                    .to_string(),
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Created);
+        let ban_id = active_bans(&app).await[0]["id"].as_i64().unwrap();
+        assert!(active_bans(&app).await[0]["acknowledged_at"].is_null());
+
+        let acknowledge = |account_id: i64| {
+            app.login(
+                app.client
+                    .post(format!("/api/v2/bans/{}/acknowledge", ban_id)),
+                account_id,
+            )
+            .dispatch()
+        };
+
+        // Only the banned character
+        assert_eq!(acknowledge(PILOT).await.status(), Status::Unauthorized);
+        assert_eq!(acknowledge(TARGET).await.status(), Status::Ok);
+        assert!(active_bans(&app).await[0]["acknowledged_at"].is_i64());
+
+        let response = app
+            .login(app.client.delete(format!("/api/v2/bans/{}", ban_id)), FC)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NoContent);
+        assert_eq!(acknowledge(TARGET).await.status(), Status::BadRequest);
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_list_envelope() {
+        let app = match setup().await {
+            Some(app) => app,
+            None => return,
+        };
+
+        for (id, category) in [(TARGET, "Character"), (BAD_CORPORATION, "Corporation")].iter() {
+            let response = app
+                .login(app.client.post("/api/v2/bans"), FC)
+                .header(ContentType::JSON)
+                .body(
+                    json!({ "entity": { "id": id, "category": category }, "reason": "x" })
+                        .to_string(),
+                )
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Created);
+        }
+
+        let list = |query: &'static str| {
+            app.login(app.client.get(format!("/api/v2/bans{}", query)), FC)
+                .dispatch()
+        };
+
+        // The default shapes don't change
+        let body: Value = list("").await.json().await.unwrap();
+        assert_eq!(body.as_array().unwrap().len(), 2);
+        let body: Value = list("?limit=1").await.json().await.unwrap();
+        assert_eq!(body["bans"].as_array().unwrap().len(), 1);
+
+        let body: Value = list("?limit=1&offset=1&envelope=true")
+            .await
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(body["items"].as_array().unwrap().len(), 1);
+        assert_eq!(body["total"], 2);
+        assert_eq!(body["limit"], 1);
+        assert_eq!(body["offset"], 1);
+
+        let body: Value = list("?envelope=true").await.json().await.unwrap();
+        assert_eq!(body["items"].as_array().unwrap().len(), 2);
+        assert_eq!(body["total"], 2);
+        assert!(body["limit"].is_null());
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_supersedes() {
+        let app = match setup().await {
+            Some(app) => app,
+            None => return,
+        };
+
+        let create = |body: Value| {
+            app.login(app.client.post("/api/v2/bans"), FC)
+                .header(ContentType::JSON)
+                .body(body.to_string())
+                .dispatch()
+        };
+
+        let response = create(
+            json!({ "entity": { "id": TARGET, "category": "Character" }, "reason": "Typo" }),
+        )
+        .await;
+        assert_eq!(response.status(), Status::Created);
+        let first = active_bans(&app).await[0]["id"].as_i64().unwrap();
+        let response = app
+            .login(app.client.delete(format!("/api/v2/bans/{}", first)), FC)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NoContent);
+
+        // Only bans on the same entity
+        let response = create(json!({
+            "entity": { "id": BAD_CORPORATION, "category": "Corporation" },
+            "reason": "x",
+            "supersedes_ban_id": first,
+        }))
+        .await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        let response = create(json!({
+            "entity": { "id": TARGET, "category": "Character" },
+            "reason": "x",
+            "supersedes_ban_id": first + 1000,
+        }))
+        .await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+
+        let response = create(json!({
+            "entity": { "id": TARGET, "category": "Character" },
+            "reason": "Fixed",
+            "supersedes_ban_id": first,
+        }))
+        .await;
+        assert_eq!(response.status(), Status::Created);
+
+        let response = app
+            .login(app.client.get(format!("/api/v2/bans/{}", TARGET)), FC)
+            .dispatch()
+            .await;
+        let history: Vec<Value> = response.json().await.unwrap();
+        assert_eq!(history.len(), 2);
+        for ban in history {
+            match ban["id"] == first {
+                true => assert!(ban["supersedes_ban_id"].is_null()),
+                false => assert_eq!(ban["supersedes_ban_id"], first),
+            }
+        }
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_public_reason_can_be_required() {
+        let app = match TestApp::with_config(
+            FakeEsi::new(&[(TARGET, "Bad Pilot", "Character")]),
+            |config| config.bans.require_public_reason = true,
+        )
+        .await
+        {
+            Some(app) => app,
+            None => return,
+        };
+        app.add_character(FC, "Some FC", Some("FC")).await;
+
+        let create = |body: Value| {
+            app.login(app.client.post("/api/v2/bans"), FC)
+                .header(ContentType::JSON)
+                .body(body.to_string())
+                .dispatch()
+        };
+
+        let response = create(json!({
+            "entity": { "id": TARGET, "category": "Character" },
+            "reason": "x",
+            "public_reason": " ",
+        }))
+        .await;
+        assert_eq!(response.status(), Status::BadRequest);
+        assert!(response
+            .into_string()
+            .await
+            .unwrap()
+            .contains("\"public_reason\""));
+
+        let response = create(json!({
+            "entity": { "id": TARGET, "category": "Character" },
+            "reason": "x",
+            "public_reason": "Breaking the rules",
+        }))
+        .await;
+        assert_eq!(response.status(), Status::Created);
+        let ban_id = active_bans(&app).await[0]["id"].as_i64().unwrap();
+
+        // Also when editing, leaving it out keeps it
+        let edit = |body: Value| {
+            app.login(app.client.patch(format!("/api/v2/bans/{}", ban_id)), FC)
+                .header(ContentType::JSON)
+                .body(body.to_string())
+                .dispatch()
+        };
+        assert_eq!(
+            edit(json!({ "public_reason": null })).await.status(),
+            Status::BadRequest
+        );
+        assert_eq!(edit(json!({ "reason": "y" })).await.status(), Status::Ok);
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_mirror_reason() {
+        let app = match setup().await {
+            Some(app) => app,
+            None => return,
+        };
+
+        let create = |body: Value| {
+            app.login(app.client.post("/api/v2/bans"), FC)
+                .header(ContentType::JSON)
+                .body(body.to_string())
+                .dispatch()
+        };
+        let entity = json!({ "id": TARGET, "category": "Character" });
+
+        // One of the reasons has to be there
+        let response =
+            create(json!({ "entity": entity, "reason": " ", "public_reason": "" })).await;
+        assert_eq!(response.status(), Status::BadRequest);
+        let response = create(json!({
+            "entity": entity,
+            "reason": "Awoxing",
+            "public_reason": "Breaking the rules",
+            "mirror_reason": true,
+        }))
+        .await;
+        assert_eq!(response.status(), Status::BadRequest);
+
+        let response = create(json!({
+            "entity": entity,
+            "reason": "Awoxing",
+            "mirror_reason": true,
+        }))
+        .await;
+        assert_eq!(response.status(), Status::Created);
+        let bans = active_bans(&app).await;
+        assert_eq!(bans[0]["reason"], "Awoxing");
+        assert_eq!(bans[0]["public_reason"], "Awoxing");
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_auto_tags() {
+        const LEADER: i64 = 1003;
+
+        let app = match TestApp::with_config(
+            FakeEsi::new(&[
+                (TARGET, "Bad Pilot", "Character"),
+                (BAD_CORPORATION, "Bad Corp", "Corporation"),
+            ]),
+            |config| {
+                config.bans.auto_tags = vec![
+                    AutoTagRule {
+                        tag: "permanent-review".to_string(),
+                        categories: vec!["RMT".to_string()],
+                        issuer_roles: Vec::new(),
+                        permanent: Some(true),
+                    },
+                    AutoTagRule {
+                        tag: "leadership".to_string(),
+                        categories: Vec::new(),
+                        issuer_roles: vec!["Leadership".to_string()],
+                        permanent: None,
+                    },
+                ]
+            },
+        )
+        .await
+        {
+            Some(app) => app,
+            None => return,
+        };
+        app.add_character(FC, "Some FC", Some("FC")).await;
+        app.add_character(LEADER, "Some Leader", Some("Leadership"))
+            .await;
+
+        async fn create(app: &TestApp, account_id: i64, body: Value) -> Value {
+            let response = app
+                .login(app.client.post("/api/v2/bans"), account_id)
+                .header(ContentType::JSON)
+                .body(body.to_string())
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Created);
+            let created: Value = response.json().await.unwrap();
+            let response = app
+                .login(
+                    app.client
+                        .get(format!("/api/v2/bans/{}/details", created["id"])),
+                    FC,
+                )
+                .dispatch()
+                .await;
+            let details: Value = response.json().await.unwrap();
+            details["tags"].clone()
+        }
+
+        // Merged with the FC's own tags
+        let tags = create(
+            &app,
+            FC,
+            json!({
+                "entity": { "id": TARGET, "category": "Character" },
+                "reason": "[RMT] Selling ISK",
+                "tags": ["Awox"],
+            }),
+        )
+        .await;
+        assert_eq!(tags, json!(["awox", "permanent-review"]));
+
+        let tags = create(
+            &app,
+            LEADER,
+            json!({
+                "entity": { "id": BAD_CORPORATION, "category": "Corporation" },
+                "reason": "[RMT] Selling ISK",
+                "revoked_at": 4_000_000_000i64,
+            }),
+        )
+        .await;
+        assert_eq!(tags, json!(["leadership"]));
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_list_by_issuer() {
+        const LEADER: i64 = 1003;
+
+        let app = match setup().await {
+            Some(app) => app,
+            None => return,
+        };
+        app.add_character(LEADER, "Some Leader", Some("Leadership"))
+            .await;
+
+        for (account_id, id, category) in [
+            (FC, TARGET, "Character"),
+            (LEADER, BAD_CORPORATION, "Corporation"),
+        ]
+        .iter()
+        {
+            let response = app
+                .login(app.client.post("/api/v2/bans"), *account_id)
+                .header(ContentType::JSON)
+                .body(
+                    json!({ "entity": { "id": id, "category": category }, "reason": "x" })
+                        .to_string(),
+                )
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Created);
+        }
+
+        let list = |query: &'static str| {
+            app.login(app.client.get(format!("/api/v2/bans?{}", query)), FC)
+                .dispatch()
+        };
+        async fn issuers(response: rocket::local::asynchronous::LocalResponse<'_>) -> Vec<i64> {
+            assert_eq!(response.status(), Status::Ok);
+            let bans: Vec<Value> = response.json().await.unwrap();
+            bans.iter()
+                .map(|ban| ban["issued_by"]["id"].as_i64().unwrap())
+                .collect()
+        }
+
+        assert_eq!(issuers(list("issued_by=1003").await).await, vec![LEADER]);
+        assert_eq!(
+            issuers(list("issued_by_name=some%20fc").await).await,
+            vec![FC]
+        );
+        // A unique start of the name is enough
+        assert_eq!(
+            issuers(list("issued_by_name=Some%20L").await).await,
+            vec![LEADER]
+        );
+
+        // Both FCs start with "Some"
+        let response = list("issued_by_name=Some").await;
+        assert_eq!(response.status(), Status::BadRequest);
+        let message = response.into_string().await.unwrap();
+        assert!(message.contains("Some FC (1001)") && message.contains("Some Leader (1003)"));
+
+        assert_eq!(
+            list("issued_by_name=Nobody").await.status(),
+            Status::NotFound
+        );
+        assert_eq!(
+            list("issued_by=1001&issued_by_name=Some%20FC")
+                .await
+                .status(),
+            Status::BadRequest
+        );
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_duplicate_override() {
+        const LEADER: i64 = 1003;
+
+        let app = match setup().await {
+            Some(app) => app,
+            None => return,
+        };
+        app.add_character(LEADER, "Some Leader", Some("Leadership"))
+            .await;
+
+        let ban = |account_id, path: &'static str| {
+            app.login(app.client.post(path), account_id)
+                .header(ContentType::JSON)
+                .body(
+                    json!({
+                        "entity": { "id": BAD_CORPORATION, "category": "Corporation" },
+                        "reason": "x",
+                    })
+                    .to_string(),
+                )
+                .dispatch()
+        };
+        let response = ban(FC, "/api/v2/bans").await;
+        assert_eq!(response.status(), Status::Created);
+        let first: Value = response.json().await.unwrap();
+        assert_eq!(ban(FC, "/api/v2/bans").await.status(), Status::Conflict);
+        assert_eq!(
+            ban(FC, "/api/v2/bans?allow_duplicate=true").await.status(),
+            Status::Unauthorized
+        );
+        let response = ban(LEADER, "/api/v2/bans?allow_duplicate=true").await;
+        assert_eq!(response.status(), Status::Created);
+        let second: Value = response.json().await.unwrap();
+
+        let details = |ban_id: &Value| {
+            app.login(
+                app.client
+                    .get(format!("/api/v2/bans/{}/details", ban_id.as_i64().unwrap())),
+                FC,
+            )
+            .dispatch()
+        };
+        let body: Value = details(&second["id"]).await.json().await.unwrap();
+        assert_eq!(body["duplicate_override_by"]["id"], LEADER);
+        let body: Value = details(&first["id"]).await.json().await.unwrap();
+        assert!(body["duplicate_override_by"].is_null());
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_list_params() {
+        let app = match setup().await {
+            Some(app) => app,
+            None => return,
+        };
+        for (id, category) in [(TARGET, "Character"), (BAD_CORPORATION, "Corporation")].iter() {
+            let response = app
+                .login(app.client.post("/api/v2/bans"), FC)
+                .header(ContentType::JSON)
+                .body(
+                    json!({ "entity": { "id": id, "category": category }, "reason": "x" })
+                        .to_string(),
+                )
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Created);
+        }
+
+        let list = |query: &'static str| {
+            app.login(app.client.get(format!("/api/v2/bans?{}", query)), FC)
+                .dispatch()
+        };
+        let entities = |bans: &Value| {
+            bans.as_array()
+                .unwrap()
+                .iter()
+                .map(|ban| ban["entity"]["id"].as_i64().unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        let bans: Value = list("search=CORP").await.json().await.unwrap();
+        assert_eq!(entities(&bans), vec![BAD_CORPORATION]);
+        let bans: Value = list("search=bad").await.json().await.unwrap();
+        assert_eq!(entities(&bans).len(), 2);
+
+        let bans: Value = list("sort=issued_at").await.json().await.unwrap();
+        assert_eq!(entities(&bans), vec![BAD_CORPORATION, TARGET]);
+        let page: Value = list("order=asc&limit=10").await.json().await.unwrap();
+        assert_eq!(entities(&page["bans"]), vec![TARGET, BAD_CORPORATION]);
+        // Limits past the maximum are clamped rather than rejected
+        let page: Value = list("order=asc&limit=100000").await.json().await.unwrap();
+        assert_eq!(entities(&page["bans"]), vec![TARGET, BAD_CORPORATION]);
+
+        // With a message saying what's wrong
+        for &(query, error) in [
+            ("sort=nonsense", "Cannot sort by nonsense"),
+            ("order=sideways", "Unknown order sideways"),
+            ("limit=ten", "limit has to be a number"),
+        ]
+        .iter()
+        {
+            let response = list(query).await;
+            assert_eq!(response.status(), Status::BadRequest);
+            assert!(response.into_string().await.unwrap().contains(error));
+        }
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_compare() {
+        let app = match setup().await {
+            Some(app) => app,
+            None => return,
+        };
+
+        let create = |reason: &'static str| {
+            app.login(app.client.post("/api/v2/bans"), FC)
+                .header(ContentType::JSON)
+                .body(
+                    json!({ "entity": { "id": TARGET, "category": "Character" }, "reason": reason })
+                        .to_string(),
+                )
+                .dispatch()
+        };
+
+        let response = create("Awoxing").await;
+        assert_eq!(response.status(), Status::Created);
+        let first: Value = response.json().await.unwrap();
+        let response = app
+            .login(
+                app.client.delete(format!("/api/v2/bans/{}", first["id"])),
+                FC,
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NoContent);
+        let response = create("Awoxing again").await;
+        assert_eq!(response.status(), Status::Created);
+        let second: Value = response.json().await.unwrap();
+
+        let path = format!("/api/v2/bans/compare?a={}&b={}", first["id"], second["id"]);
+        let response = app.login(app.client.get(path.clone()), FC).dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(body["a"]["id"], first["id"]);
+        assert_eq!(body["b"]["id"], second["id"]);
+        let reason = body["differences"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|difference| difference["field"] == "reason")
+            .unwrap();
+        assert_eq!(reason["a"], "Awoxing");
+        assert_eq!(reason["b"], "Awoxing again");
+
+        let response = app
+            .login(
+                app.client
+                    .get(format!("/api/v2/bans/compare?a={}&b=0", first["id"])),
+                FC,
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NotFound);
+
+        let response = app.login(app.client.get(path), PILOT).dispatch().await;
+        assert_eq!(response.status(), Status::Unauthorized);
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_recent_login_can_be_required() {
+        let app = match TestApp::with_config(
+            FakeEsi::new(&[(TARGET, "Bad Pilot", "Character")]),
+            |config| config.bans.require_recent_login = true,
+        )
+        .await
+        {
+            Some(app) => app,
+            None => return,
+        };
+        app.add_character(FC, "Some FC", Some("FC")).await;
+        let two_days_ago = chrono::Utc::now().timestamp() - 2 * 86400;
+
+        let body = json!({ "entity": { "id": TARGET, "category": "Character" }, "reason": "x" });
+        let response = app
+            .login_at(app.client.post("/api/v2/bans"), FC, two_days_ago)
+            .header(ContentType::JSON)
+            .body(body.to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Forbidden);
+        assert!(response
+            .into_string()
+            .await
+            .unwrap()
+            .contains("log in again"));
+
+        let response = app
+            .login(app.client.post("/api/v2/bans"), FC)
+            .header(ContentType::JSON)
+            .body(body.to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Created);
+
+        // Reading bans doesn't need a recent login
+        let response = app
+            .login_at(app.client.get("/api/v2/bans"), FC, two_days_ago)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let bans: Vec<Value> = response.json().await.unwrap();
+        let ban_id = bans[0]["id"].as_i64().unwrap();
+
+        let response = app
+            .login_at(
+                app.client.delete(format!("/api/v2/bans/{}", ban_id)),
+                FC,
+                two_days_ago,
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Forbidden);
+        assert_eq!(active_bans(&app).await.len(), 1);
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_ownership_window() {
+        const LEADER: i64 = 1003;
+        const OTHER_FC: i64 = 1004;
+
+        let app = match TestApp::with_config(
+            FakeEsi::new(&[
+                (TARGET, "Bad Pilot", "Character"),
+                (BAD_CORPORATION, "Bad Corp", "Corporation"),
+            ]),
+            |config| config.bans.enforce_ownership = true,
+        )
+        .await
+        {
+            Some(app) => app,
+            None => return,
+        };
+        app.add_character(FC, "Some FC", Some("FC")).await;
+        app.add_character(OTHER_FC, "Other FC", Some("FC")).await;
+        app.add_character(LEADER, "Some Leader", Some("Leadership"))
+            .await;
+
+        let mut ids = Vec::new();
+        for (id, category) in [(TARGET, "Character"), (BAD_CORPORATION, "Corporation")].iter() {
+            let response = app
+                .login(app.client.post("/api/v2/bans"), FC)
+                .header(ContentType::JSON)
+                .body(
+                    json!({ "entity": { "id": id, "category": category }, "reason": "x" })
+                        .to_string(),
+                )
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Created);
+            let created: Value = response.json().await.unwrap();
+            ids.push(created["id"].as_i64().unwrap());
+        }
+        let (ban_id, other_id) = (ids[0], ids[1]);
+
+        let response = app
+            .login(
+                app.client.patch(format!("/api/v2/bans/{}", ban_id)),
+                OTHER_FC,
+            )
+            .header(ContentType::JSON)
+            .body(json!({ "reason": "y" }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Forbidden);
+        assert!(response
+            .into_string()
+            .await
+            .unwrap()
+            .contains("belongs to Some FC for another 24 hour(s)"));
+        let response = app
+            .login(
+                app.client.delete(format!("/api/v2/bans/{}", ban_id)),
+                OTHER_FC,
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Forbidden);
+        let response = app
+            .login(app.client.post("/api/v2/bans/revoke"), OTHER_FC)
+            .header(ContentType::JSON)
+            .body(json!({ "ids": [ban_id] }).to_string())
+            .dispatch()
+            .await;
+        let result: Value = response.json().await.unwrap();
+        assert_eq!(result["failed"][0]["id"], ban_id);
+
+        // The issuing FC and bans-admin aren't held back
+        let response = app
+            .login(app.client.patch(format!("/api/v2/bans/{}", ban_id)), FC)
+            .header(ContentType::JSON)
+            .body(json!({ "reason": "y" }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let response = app
+            .login(
+                app.client.delete(format!("/api/v2/bans/{}", other_id)),
+                LEADER,
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NoContent);
+
+        // An edit by someone else doesn't hand the ban over or restart the window
+        let response = app
+            .login(app.client.patch(format!("/api/v2/bans/{}", ban_id)), LEADER)
+            .header(ContentType::JSON)
+            .body(json!({ "reason": "z" }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let response = app
+            .login(
+                app.client.patch(format!("/api/v2/bans/{}", ban_id)),
+                OTHER_FC,
+            )
+            .header(ContentType::JSON)
+            .body(json!({ "reason": "y" }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Forbidden);
+        assert!(response
+            .into_string()
+            .await
+            .unwrap()
+            .contains("belongs to Some FC"));
+
+        // Once the window is over any FC can
+        sqlx::query!("UPDATE ban SET issued_at=1000 WHERE id=$1", ban_id)
+            .execute(app.db())
+            .await
+            .unwrap();
+        let response = app
+            .login(
+                app.client.delete(format!("/api/v2/bans/{}", ban_id)),
+                OTHER_FC,
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NoContent);
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_similar() {
+        let app = match setup().await {
+            Some(app) => app,
+            None => return,
+        };
+
+        for (id, category, reason) in [
+            (TARGET, "Character", "Selling ISK for real money"),
+            (BAD_CORPORATION, "Corporation", "Awoxing fleet members"),
+        ]
+        .iter()
+        {
+            let response = app
+                .login(app.client.post("/api/v2/bans"), FC)
+                .header(ContentType::JSON)
+                .body(
+                    json!({ "entity": { "id": id, "category": category }, "reason": reason })
+                        .to_string(),
+                )
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Created);
+        }
+
+        let similar = |query: &'static str| {
+            app.login(app.client.get(format!("/api/v2/bans/similar{}", query)), FC)
+                .dispatch()
+        };
+
+        let body: Vec<Value> = similar("?reason=selling%20isk%20for%20money")
+            .await
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(body.len(), 1);
+        assert_eq!(body[0]["entity"]["id"], TARGET);
+        assert!(body[0]["duration"].is_null());
+
+        let body: Vec<Value> =
+            similar("?reason=selling%20isk%20for%20money&entity_type=Corporation")
+                .await
+                .json()
+                .await
+                .unwrap();
+        assert!(body.is_empty());
+
+        assert_eq!(similar("?reason=x").await.status(), Status::BadRequest);
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_partial_update() {
+        let app = match setup().await {
+            Some(app) => app,
+            None => return,
+        };
+
+        let response = app
+            .login(app.client.post("/api/v2/bans"), FC)
+            .header(ContentType::JSON)
+            .body(
+                json!({
+                    "entity": { "id": TARGET, "category": "Character" },
+                    "reason": "Awoxing",
+                    "public_reason": "Breaking the rules",
+                    "revoked_at": 4_000_000_000i64,
+                    "tags": ["awox"],
+                })
+                .to_string(),
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Created);
+        let ban = active_bans(&app).await.remove(0);
+        let ban_id = ban["id"].as_i64().unwrap();
+
+        let edit = |body: Value| {
+            app.login(app.client.patch(format!("/api/v2/bans/{}", ban_id)), FC)
+                .header(ContentType::JSON)
+                .body(body.to_string())
+                .dispatch()
+        };
+
+        // Only what's sent changes
+        let response = edit(json!({ "reason": "Awoxing twice" })).await;
+        assert_eq!(response.status(), Status::Ok);
+        let updated: Value = response.json().await.unwrap();
+        assert_eq!(updated["reason"], "Awoxing twice");
+        assert_eq!(updated["public_reason"], "Breaking the rules");
+        assert_eq!(updated["revoked_at"], ban["revoked_at"]);
+        assert_eq!(updated["tags"], json!(["awox"]));
+
+        let response = edit(json!({ "public_reason": "Shooting blues" })).await;
+        let updated: Value = response.json().await.unwrap();
+        assert_eq!(updated["reason"], "Awoxing twice");
+        assert_eq!(updated["public_reason"], "Shooting blues");
+
+        // Null clears it, for the expiry that makes the ban permanent
+        let response = edit(json!({ "public_reason": null, "revoked_at": null })).await;
+        let updated: Value = response.json().await.unwrap();
+        assert_eq!(updated["reason"], "Awoxing twice");
+        assert_eq!(updated["public_reason"], Value::Null);
+        assert_eq!(updated["revoked_at"], Value::Null);
+        assert_eq!(updated["tags"], json!(["awox"]));
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_ended_bans_cannot_be_edited() {
+        let app = match setup().await {
+            Some(app) => app,
+            None => return,
+        };
+
+        let response = app
+            .login(app.client.post("/api/v2/bans"), FC)
+            .header(ContentType::JSON)
+            .body(
+                json!({ "entity": { "id": TARGET, "category": "Character" }, "reason": "x" })
+                    .to_string(),
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Created);
+        let ban_id = active_bans(&app).await[0]["id"].as_i64().unwrap();
+
+        sqlx::query!("UPDATE ban SET revoked_at=1000 WHERE id=$1", ban_id)
+            .execute(app.db())
+            .await
+            .unwrap();
+
+        // A new expiry would otherwise bring the ban back
+        let response = app
+            .login(app.client.patch(format!("/api/v2/bans/{}", ban_id)), FC)
+            .header(ContentType::JSON)
+            .body(json!({ "reason": "x", "revoked_at": 4_000_000_000i64 }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::BadRequest);
+        assert!(response
+            .into_string()
+            .await
+            .unwrap()
+            .contains("already ended"));
+        assert!(active_bans(&app).await.is_empty());
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_fc_note() {
+        let app = match setup().await {
+            Some(app) => app,
+            None => return,
+        };
+
+        let response = app
+            .login(app.client.post("/api/v2/bans"), FC)
+            .header(ContentType::JSON)
+            .body(
+                json!({
+                    "entity": { "id": TARGET, "category": "Character" },
+                    "reason": "Awoxing",
+                    "public_reason": "Breaking the rules",
+                    "fc_note": "Argues in comms",
+                })
+                .to_string(),
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Created);
+        let ban_id = active_bans(&app).await[0]["id"].as_i64().unwrap();
+        assert_eq!(active_bans(&app).await[0]["fc_note"], "Argues in comms");
+
+        // Older clients don't know about the note, an edit from them keeps it
+        let response = app
+            .login(app.client.patch(format!("/api/v2/bans/{}", ban_id)), FC)
+            .header(ContentType::JSON)
+            .body(json!({ "reason": "Awoxing twice" }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let updated: Value = response.json().await.unwrap();
+        assert_eq!(updated["fc_note"], "Argues in comms");
+
+        let response = app
+            .login(app.client.patch(format!("/api/v2/bans/{}", ban_id)), FC)
+            .header(ContentType::JSON)
+            .body(json!({ "reason": "Awoxing twice", "fc_note": "" }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let updated: Value = response.json().await.unwrap();
+        assert_eq!(updated["fc_note"], Value::Null);
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_incomplete() {
+        let app = match setup().await {
+            Some(app) => app,
+            None => return,
+        };
+
+        for (entity, reason) in [
+            (json!({ "id": TARGET, "category": "Character" }), "rmt"),
+            (
+                json!({ "id": BAD_CORPORATION, "category": "Corporation" }),
+                "Awoxed a fleet on 2021-06-01, logs in #evidence",
+            ),
+        ]
+        .iter()
+        {
+            let response = app
+                .login(app.client.post("/api/v2/bans"), FC)
+                .header(ContentType::JSON)
+                .body(json!({ "entity": entity, "reason": reason }).to_string())
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Created);
+        }
+
+        let response = app
+            .login(app.client.get("/api/v2/bans/incomplete"), FC)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let bans: Vec<Value> = response.json().await.unwrap();
+        assert_eq!(bans.len(), 1);
+        assert_eq!(bans[0]["entity"]["id"], TARGET);
+
+        let response = app
+            .login(app.client.get("/api/v2/bans/incomplete?min_length=100"), FC)
+            .dispatch()
+            .await;
+        let bans: Vec<Value> = response.json().await.unwrap();
+        assert_eq!(bans.len(), 2);
+
+        let response = app
+            .login(app.client.get("/api/v2/bans/incomplete"), PILOT)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Unauthorized);
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_create_checks_the_entity() {
+        let app = match setup().await {
+            Some(app) => app,
+            None => return,
+        };
+
+        // Not a character as far as ESI knows
+        let response = app
+            .login(app.client.post("/api/v2/bans"), FC)
+            .header(ContentType::JSON)
+            .body(
+                json!({ "entity": { "id": 3001, "category": "Character" }, "reason": "x" })
+                    .to_string(),
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NotFound);
+
+        // FCs can't be banned
+        let response = app
+            .login(app.client.post("/api/v2/bans"), FC)
+            .header(ContentType::JSON)
+            .body(
+                json!({ "entity": { "id": FC, "category": "Character" }, "reason": "x" })
+                    .to_string(),
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_fcs_cannot_ban_themselves() {
+        let app = match setup().await {
+            Some(app) => app,
+            None => return,
+        };
+
+        let response = app
+            .login(app.client.post("/api/v2/bans"), FC)
+            .header(ContentType::JSON)
+            .body(
+                json!({ "entity": { "id": FC, "category": "Character" }, "reason": "x" })
+                    .to_string(),
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        assert_eq!(
+            response.into_string().await.unwrap(),
+            "You cannot ban yourself"
+        );
+        assert!(active_bans(&app).await.is_empty());
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_malformed_and_rejected_bans() {
+        let app = match setup().await {
+            Some(app) => app,
+            None => return,
+        };
+
+        let create = |body: Value| {
+            app.login(app.client.post("/api/v2/bans"), FC)
+                .header(ContentType::JSON)
+                .body(body.to_string())
+                .dispatch()
+        };
+
+        // Something the client has to fix
+        let response = create(json!({ "reason": "x" })).await;
+        assert_eq!(response.status(), Status::BadRequest);
+
+        // Well formed, but not allowed
+        let yesterday = chrono::Utc::now().timestamp() - 24 * 60 * 60;
+        let response = create(json!({
+            "entity": { "id": TARGET, "category": "Character" },
+            "reason": "x",
+            "revoked_at": yesterday,
+        }))
+        .await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        assert!(active_bans(&app).await.is_empty());
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_export_character() {
+        const LEADER: i64 = 1003;
+
+        let app = match setup().await {
+            Some(app) => app,
+            None => return,
+        };
+        app.add_character(LEADER, "Some Leader", Some("Leadership"))
+            .await;
+        app.add_character(TARGET, "Bad Pilot", None).await;
+        sqlx::query!(
+            "INSERT INTO corporation (id, name, updated_at) VALUES ($1, 'Bad Corp', 0)",
+            BAD_CORPORATION
+        )
+        .execute(app.db())
+        .await
+        .unwrap();
+        sqlx::query!(
+            "UPDATE character SET corporation_id=$1 WHERE id=$2",
+            BAD_CORPORATION,
+            TARGET
+        )
+        .execute(app.db())
+        .await
+        .unwrap();
+
+        for (id, category) in [(TARGET, "Character"), (BAD_CORPORATION, "Corporation")].iter() {
+            let response = app
+                .login(app.client.post("/api/v2/bans"), FC)
+                .header(ContentType::JSON)
+                .body(
+                    json!({
+                        "entity": { "id": id, "category": category },
+                        "reason": "x",
+                        "public_reason": "Breaking the rules",
+                        "fc_note": "Watch his alts",
+                    })
+                    .to_string(),
+                )
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Created);
+        }
+        let ban_id = active_bans(&app)
+            .await
+            .iter()
+            .find(|ban| ban["entity"]["category"] == "Character")
+            .and_then(|ban| ban["id"].as_i64())
+            .unwrap();
+        let response = app
+            .login(app.client.patch(format!("/api/v2/bans/{}", ban_id)), FC)
+            .header(ContentType::JSON)
+            .body(json!({ "reason": "y" }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+
+        let export = |account_id: i64| {
+            app.login(
+                app.client
+                    .get(format!("/api/v2/bans/{}/export.json", TARGET)),
+                account_id,
+            )
+            .dispatch()
+        };
+        assert_eq!(export(FC).await.status(), Status::Unauthorized);
+
+        let response = export(LEADER).await;
+        assert_eq!(response.status(), Status::Ok);
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(body["character_name"], "Bad Pilot");
+        assert_eq!(body["corporation"]["name"], "Bad Corp");
+        // Only what the pilot is told anyway
+        let ban = &body["bans"][0];
+        assert_eq!(ban["public_reason"], "Breaking the rules");
+        assert_eq!(ban["reason"], "");
+        assert!(ban["fc_note"].is_null());
+        assert_eq!(ban["reason_history"], json!([]));
+        assert!(!body.to_string().contains("Watch his alts"));
+        assert_eq!(body["corporation_bans"].as_array().unwrap().len(), 1);
+        assert!(body["alliance"].is_null());
+
+        // The jobs route still gets its own paths
+        let response = app
+            .login(app.client.get("/api/v2/bans/jobs/999999"), LEADER)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NotFound);
+        assert!(response
+            .into_string()
+            .await
+            .unwrap()
+            .contains("Job not found"));
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_reassign() {
+        const LEADER: i64 = 1003;
+
+        let app = match setup().await {
+            Some(app) => app,
+            None => return,
+        };
+        app.add_character(LEADER, "Some Leader", Some("Leadership"))
+            .await;
+
+        let response = app
+            .login(app.client.post("/api/v2/bans"), FC)
+            .header(ContentType::JSON)
+            .body(
+                json!({ "entity": { "id": TARGET, "category": "Character" }, "reason": "x" })
+                    .to_string(),
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Created);
+        let ban_id = active_bans(&app).await[0]["id"].as_i64().unwrap();
+
+        let reassign = |account_id: i64| {
+            app.login(app.client.post("/api/v2/bans/reassign"), account_id)
+                .header(ContentType::JSON)
+                .body(json!({ "from_character_id": FC, "to_character_id": PILOT }).to_string())
+                .dispatch()
+        };
+        assert_eq!(reassign(FC).await.status(), Status::Unauthorized);
+
+        let response = reassign(LEADER).await;
+        assert_eq!(response.status(), Status::Ok);
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(body, json!({ "succeeded": [ban_id], "failed": [] }));
+        assert_eq!(active_bans(&app).await[0]["issued_by"]["id"], PILOT);
+
+        let previous = sqlx::query!(
+            "SELECT issued_by, reassigned_by FROM ban_issuer_history WHERE ban_id=$1",
+            ban_id
+        )
+        .fetch_one(app.db())
+        .await
+        .unwrap();
+        assert_eq!((previous.issued_by, previous.reassigned_by), (FC, LEADER));
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_purge() {
+        const LEADER: i64 = 1003;
+
+        let app = match setup().await {
+            Some(app) => app,
+            None => return,
+        };
+        app.add_character(LEADER, "Some Leader", Some("Leadership"))
+            .await;
+
+        for entity in [
+            json!({ "id": TARGET, "category": "Character" }),
+            json!({ "id": BAD_CORPORATION, "category": "Corporation" }),
+        ]
+        .iter()
+        {
+            let response = app
+                .login(app.client.post("/api/v2/bans"), FC)
+                .header(ContentType::JSON)
+                .body(json!({ "entity": entity, "reason": "x", "tags": ["old"] }).to_string())
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Created);
+        }
+        let ended = sqlx::query!(
+            "UPDATE ban SET revoked_at=1000 WHERE entity_id=$1 RETURNING id",
+            TARGET
+        )
+        .fetch_one(app.db())
+        .await
+        .unwrap()
+        .id;
+
+        async fn preview(app: &TestApp, account_id: i64, older_than_days: i64) -> Value {
+            let response = app
+                .login(
+                    app.client.get(format!(
+                        "/api/v2/bans/purge/preview?older_than_days={}",
+                        older_than_days
+                    )),
+                    account_id,
+                )
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Ok);
+            response.json().await.unwrap()
+        }
+
+        async fn purge(app: &TestApp, older_than_days: i64, token: &str) -> Status {
+            app.login(app.client.delete("/api/v2/bans/purge"), LEADER)
+                .header(ContentType::JSON)
+                .body(json!({ "older_than_days": older_than_days, "token": token }).to_string())
+                .dispatch()
+                .await
+                .status()
+        }
+
+        let response = app
+            .login(
+                app.client
+                    .get("/api/v2/bans/purge/preview?older_than_days=30"),
+                FC,
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Unauthorized);
+
+        let body = preview(&app, LEADER, 30).await;
+        assert_eq!(body["count"], 1);
+        let token = body["token"].as_str().unwrap();
+
+        // The token only confirms the purge that was previewed
+        assert_eq!(purge(&app, 0, token).await, Status::BadRequest);
+        assert_eq!(purge(&app, 30, "nonsense").await, Status::BadRequest);
+
+        assert_eq!(purge(&app, 30, token).await, Status::Ok);
+        assert!(sqlx::query!("SELECT id FROM ban WHERE id=$1", ended)
+            .fetch_optional(app.db())
+            .await
+            .unwrap()
+            .is_none());
+        let tags = sqlx::query!("SELECT ban_id FROM ban_tag")
+            .fetch_all(app.db())
+            .await
+            .unwrap();
+        assert_eq!(tags.len(), 1);
+        assert_eq!(active_bans(&app).await.len(), 1);
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_pilots_cannot_ban() {
+        let app = match setup().await {
+            Some(app) => app,
+            None => return,
+        };
+
+        let response = app
+            .login(app.client.post("/api/v2/bans"), PILOT)
+            .header(ContentType::JSON)
+            .body(
+                json!({ "entity": { "id": TARGET, "category": "Character" }, "reason": "x" })
+                    .to_string(),
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Unauthorized);
+        assert!(active_bans(&app).await.is_empty());
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_bulk_revoke() {
+        let app = match setup().await {
+            Some(app) => app,
+            None => return,
+        };
+
+        let response = app
+            .login(app.client.post("/api/v2/bans"), FC)
+            .header(ContentType::JSON)
+            .body(
+                json!({ "entity": { "id": TARGET, "category": "Character" }, "reason": "x" })
+                    .to_string(),
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Created);
+        let ban_id = active_bans(&app).await[0]["id"].as_i64().unwrap();
+
+        async fn revoke(app: &TestApp, ids: &[i64]) -> Value {
+            let response = app
+                .login(app.client.post("/api/v2/bans/revoke"), FC)
+                .header(ContentType::JSON)
+                .body(json!({ "ids": ids }).to_string())
+                .dispatch()
+                .await;
+            // Even when some of them fail
+            assert_eq!(response.status(), Status::Ok);
+            response.json().await.unwrap()
+        }
+
+        let body = revoke(&app, &[ban_id, 999999, ban_id]).await;
+        assert_eq!(body["succeeded"], json!([ban_id]));
+        assert_eq!(body["failed"][0]["id"], 999999);
+        assert!(body["failed"][0]["error"]
+            .as_str()
+            .unwrap()
+            .contains("Could not find"));
+        assert!(active_bans(&app).await.is_empty());
+
+        // A database error only fails its own ban, the ones before it stay revoked
+        let mut ids = Vec::new();
+        for (id, category) in [(TARGET, "Character"), (BAD_CORPORATION, "Corporation")].iter() {
+            let response = app
+                .login(app.client.post("/api/v2/bans"), FC)
+                .header(ContentType::JSON)
+                .body(
+                    json!({ "entity": { "id": id, "category": category }, "reason": "x" })
+                        .to_string(),
+                )
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Created);
+            let created: Value = response.json().await.unwrap();
+            ids.push(created["id"].as_i64().unwrap());
+        }
+        sqlx::query(
+            "CREATE FUNCTION fail_revoke() RETURNS trigger AS $$
+            BEGIN RAISE EXCEPTION 'revoke failed'; END
+            $$ LANGUAGE plpgsql",
+        )
+        .execute(app.db())
+        .await
+        .unwrap();
+        sqlx::query(&format!(
+            "CREATE TRIGGER fail_revoke BEFORE UPDATE ON ban
+            FOR EACH ROW WHEN (OLD.id = {}) EXECUTE FUNCTION fail_revoke()",
+            ids[1]
+        ))
+        .execute(app.db())
+        .await
+        .unwrap();
+
+        let body = revoke(&app, &ids).await;
+        assert_eq!(body["succeeded"], json!([ids[0]]));
+        assert_eq!(
+            body["failed"],
+            json!([{ "id": ids[1], "error": "Database error, try again" }])
+        );
+        let bans = active_bans(&app).await;
+        assert_eq!(bans.len(), 1);
+        assert_eq!(bans[0]["id"], ids[1]);
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_bulk_tags() {
+        let app = match setup().await {
+            Some(app) => app,
+            None => return,
+        };
+
+        for (id, category) in [(TARGET, "Character"), (BAD_CORPORATION, "Corporation")].iter() {
+            let response = app
+                .login(app.client.post("/api/v2/bans"), FC)
+                .header(ContentType::JSON)
+                .body(
+                    json!({ "entity": { "id": id, "category": category }, "reason": "x" })
+                        .to_string(),
+                )
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Created);
+        }
+        let mut ids: Vec<i64> = active_bans(&app)
+            .await
+            .iter()
+            .map(|ban| ban["id"].as_i64().unwrap())
+            .collect();
+
+        async fn bulk(app: &TestApp, path: &str, ids: &[i64], tag: &str) -> (Status, Value) {
+            let response = app
+                .login(app.client.post(format!("/api/v2/bans/{}", path)), FC)
+                .header(ContentType::JSON)
+                .body(json!({ "ids": ids, "tag": tag }).to_string())
+                .dispatch()
+                .await;
+            let status = response.status();
+            (status, response.json().await.unwrap_or(Value::Null))
+        }
+
+        // IDs that aren't bans fail on their own, tagging twice is fine
+        ids.push(999999);
+        let tagged = json!({
+            "succeeded": [ids[0], ids[1]],
+            "failed": [{ "id": 999999, "error": "Ban not found" }],
+        });
+        let (status, body) = bulk(&app, "tag", &ids, " Coalition-2025 ").await;
+        assert_eq!(status, Status::Ok);
+        assert_eq!(body, tagged);
+        assert_eq!(bulk(&app, "tag", &ids, "coalition-2025").await.1, tagged);
+
+        let tagged: Vec<Value> = app
+            .login(app.client.get("/api/v2/bans?tag=coalition-2025"), FC)
+            .dispatch()
+            .await
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(tagged.len(), 2);
+
+        let (status, body) = bulk(&app, "untag", &ids[..1], "COALITION-2025").await;
+        assert_eq!(status, Status::Ok);
+        assert_eq!(body, json!({ "succeeded": [ids[0]], "failed": [] }));
+
+        // A ban without room for another tag doesn't stop the others
+        for i in 0..9 {
+            sqlx::query!(
+                "INSERT INTO ban_tag (ban_id, tag) VALUES ($1, $2)",
+                ids[1],
+                format!("tag{}", i)
+            )
+            .execute(app.db())
+            .await
+            .unwrap();
+        }
+        let (status, body) = bulk(&app, "tag", &ids[..2], "awox").await;
+        assert_eq!(status, Status::Ok);
+        assert_eq!(
+            body,
+            json!({
+                "succeeded": [ids[0]],
+                "failed": [{ "id": ids[1], "error": "The ban already has 10 tags" }],
+            })
+        );
+
+        assert_eq!(bulk(&app, "tag", &ids, "  ").await.0, Status::BadRequest);
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_announce() {
+        let app = match setup().await {
+            Some(app) => app,
+            None => return,
+        };
+        let response = app
+            .login(app.client.post("/api/v2/bans/1/announce"), FC)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NotFound);
+        assert!(response.into_string().await.unwrap().contains("webhook"));
+        app.destroy().await;
+
+        // Nothing listens there, so every post fails
+        let app = match TestApp::with_config(
+            FakeEsi::new(&[(TARGET, "Bad Pilot", "Character")]),
+            |config| config.discord.ban_webhook = Some("http://127.0.0.1:9".to_string()),
+        )
+        .await
+        {
+            Some(app) => app,
+            None => return,
+        };
+        app.add_character(FC, "Some FC", Some("FC")).await;
+        let response = app
+            .login(app.client.post("/api/v2/bans"), FC)
+            .header(ContentType::JSON)
+            .body(
+                json!({ "entity": { "id": TARGET, "category": "Character" }, "reason": "x" })
+                    .to_string(),
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Created);
+        let ban_id = active_bans(&app).await[0]["id"].as_i64().unwrap();
+
+        // A failed post doesn't count towards the limit
+        for _ in 0..=ANNOUNCE_LIMIT {
+            let response = app
+                .login(
+                    app.client.post(format!("/api/v2/bans/{}/announce", ban_id)),
+                    FC,
+                )
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::InternalServerError);
+        }
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_announce_feature_off() {
+        let app = match TestApp::with_config(
+            FakeEsi::new(&[(TARGET, "Bad Pilot", "Character")]),
+            |config| {
+                config.discord.ban_webhook = Some("http://127.0.0.1:9".to_string());
+                config.features.insert("discord_webhook".to_string(), false);
+            },
+        )
+        .await
+        {
+            Some(app) => app,
+            None => return,
+        };
+        app.add_character(FC, "Some FC", Some("FC")).await;
+        let response = app
+            .login(app.client.post("/api/v2/bans"), FC)
+            .header(ContentType::JSON)
+            .body(
+                json!({ "entity": { "id": TARGET, "category": "Character" }, "reason": "x" })
+                    .to_string(),
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Created);
+        let ban_id = active_bans(&app).await[0]["id"].as_i64().unwrap();
+
+        let response = app
+            .login(
+                app.client.post(format!("/api/v2/bans/{}/announce", ban_id)),
+                FC,
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NotFound);
+        assert!(response.into_string().await.unwrap().contains("turned off"));
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_announce_pending() {
+        let app = match TestApp::with_config(
+            FakeEsi::new(&[(TARGET, "Bad Pilot", "Character")]),
+            |config| {
+                config.discord.ban_webhook = Some("http://127.0.0.1:9".to_string());
+                config.bans.require_approval = true;
+            },
+        )
+        .await
+        {
+            Some(app) => app,
+            None => return,
+        };
+        app.add_character(FC, "Some FC", Some("FC")).await;
+        let response = app
+            .login(app.client.post("/api/v2/bans"), FC)
+            .header(ContentType::JSON)
+            .body(
+                json!({ "entity": { "id": TARGET, "category": "Character" }, "reason": "x" })
+                    .to_string(),
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Created);
+        let created: Value = response.json().await.unwrap();
+
+        let response = app
+            .login(
+                app.client
+                    .post(format!("/api/v2/bans/{}/announce", created["id"])),
+                FC,
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        let body = response.into_string().await.unwrap();
+        assert!(body.contains("once approved"));
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_list_revoked_immediately() {
+        let app = match setup().await {
+            Some(app) => app,
+            None => return,
+        };
+        let response = app
+            .login(app.client.post("/api/v2/bans"), FC)
+            .header(ContentType::JSON)
+            .body(
+                json!({ "entity": { "id": TARGET, "category": "Character" }, "reason": "x" })
+                    .to_string(),
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Created);
+        assert_eq!(active_bans(&app).await[0]["revoked_immediately"], false);
+
+        // Still in effect for another minute, but already taken back by the FC who issued it
+        sqlx::query!("UPDATE ban SET revoked_at=issued_at + 60, revoked_by=issued_by")
+            .execute(app.db())
+            .await
+            .unwrap();
+        let bans = active_bans(&app).await;
+        assert_eq!(bans[0]["revoked_by"]["id"], FC);
+        assert_eq!(bans[0]["revoked_immediately"], true);
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_legacy_bans_after_migrations() {
+        const MIGRATED_FC: i64 = 1003;
+
+        let db = match TestDatabase::legacy().await {
+            Some(db) => db,
+            None => return,
+        };
+        // Bans 1 and 2 are active, 3 was revoked, see the file
+        db.execute_file("./sql/testing/legacy_bans.sql").await;
+        db.migrate().await;
+        let app = TestApp::with_database(
+            db,
+            FakeEsi::new(&[(TARGET, "Bad Pilot", "Character")]),
+            |_config| (),
+        )
+        .await;
+        app.add_character(MIGRATED_FC, "New FC", Some("FC")).await;
+
+        let list = || {
+            app.login(app.client.get("/api/v2/bans"), MIGRATED_FC)
+                .dispatch()
+        };
+        let response = list().await;
+        assert_eq!(response.status(), Status::Ok);
+        let bans: Vec<Value> = response.json().await.unwrap();
+        let mut ids: Vec<i64> = bans.iter().map(|ban| ban["id"].as_i64().unwrap()).collect();
+        ids.sort();
+        assert_eq!(ids, vec![1, 2]);
+        let legacy = bans.iter().find(|ban| ban["id"] == 1).unwrap();
+        assert_eq!(legacy["issued_by"]["name"], "Legacy FC");
+        assert_eq!(legacy["reason"], "Internal reason");
+        assert_eq!(legacy["tags"], json!([]));
+
+        let response = app
+            .login(app.client.post("/api/v2/bans"), MIGRATED_FC)
+            .header(ContentType::JSON)
+            .body(
+                json!({ "entity": { "id": TARGET, "category": "Character" }, "reason": "x" })
+                    .to_string(),
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Created);
+        let bans: Vec<Value> = list().await.json().await.unwrap();
+        assert_eq!(bans.len(), 3);
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_approve() {
+        const OTHER_FC: i64 = 1004;
+
+        let app = match TestApp::with_config(
+            FakeEsi::new(&[(TARGET, "Bad Pilot", "Character")]),
+            |config| config.bans.require_approval = true,
+        )
+        .await
+        {
+            Some(app) => app,
+            None => return,
+        };
+        app.add_character(FC, "Some FC", Some("FC")).await;
+        app.add_character(OTHER_FC, "Other FC", Some("FC")).await;
+
+        let response = app
+            .login(app.client.post("/api/v2/bans"), FC)
+            .header(ContentType::JSON)
+            .body(
+                json!({ "entity": { "id": TARGET, "category": "Character" }, "reason": "x" })
+                    .to_string(),
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Created);
+        let created: Value = response.json().await.unwrap();
+        let ban_id = created["id"].as_i64().unwrap();
+
+        let pending: Vec<Value> = app
+            .login(app.client.get("/api/v2/bans/pending"), OTHER_FC)
+            .dispatch()
+            .await
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0]["id"], ban_id);
+        assert!(active_bans(&app).await.is_empty());
+
+        let approve = |id| {
+            app.login(
+                app.client.post(format!("/api/v2/bans/{}/approve", ban_id)),
+                id,
+            )
+            .dispatch()
+        };
+        let response = approve(FC).await;
+        assert_eq!(response.status(), Status::Forbidden);
+
+        let response = approve(OTHER_FC).await;
+        assert_eq!(response.status(), Status::Ok);
+        let bans = active_bans(&app).await;
+        assert_eq!(bans.len(), 1);
+        assert_eq!(bans[0]["id"], ban_id);
+
+        // Already approved
+        let response = approve(OTHER_FC).await;
+        assert_eq!(response.status(), Status::BadRequest);
+
+        let response = app
+            .login(app.client.post("/api/v2/bans/999999/approve"), OTHER_FC)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NotFound);
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_reasons() {
+        let app = match setup().await {
+            Some(app) => app,
+            None => return,
+        };
+
+        let response = app
+            .login(app.client.post("/api/v2/bans"), FC)
+            .header(ContentType::JSON)
+            .body(
+                json!({ "entity": { "id": TARGET, "category": "Character" }, "reason": "First" })
+                    .to_string(),
+            )
+            .dispatch()
+            .await;
+        let created: Value = response.json().await.unwrap();
+        let ban_id = created["id"].as_i64().unwrap();
+        let response = app
+            .login(app.client.patch(format!("/api/v2/bans/{}", ban_id)), FC)
+            .header(ContentType::JSON)
+            .body(json!({ "reason": "Second" }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+
+        let response = app
+            .login(
+                app.client.get(format!("/api/v2/bans/{}/reasons", ban_id)),
+                FC,
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let reasons: Vec<Value> = response.json().await.unwrap();
+        assert_eq!(reasons.len(), 1);
+        assert_eq!(reasons[0]["reason"], "First");
+
+        // Still served by the jobs route
+        let response = app
+            .login(app.client.get("/api/v2/bans/jobs/999999"), FC)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::NotFound);
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_list_by_tags() {
+        let app = match setup().await {
+            Some(app) => app,
+            None => return,
+        };
+
+        let mut ids = Vec::new();
+        for (id, category, tags) in [
+            (TARGET, "Character", json!(["awox", "spy"])),
+            (BAD_CORPORATION, "Corporation", json!(["awox"])),
+        ]
+        .iter()
+        {
+            let response = app
+                .login(app.client.post("/api/v2/bans"), FC)
+                .header(ContentType::JSON)
+                .body(
+                    json!({
+                        "entity": { "id": id, "category": category },
+                        "reason": "x",
+                        "tags": tags,
+                    })
+                    .to_string(),
+                )
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Created);
+            let created: Value = response.json().await.unwrap();
+            ids.push(created["id"].as_i64().unwrap());
+        }
+
+        let list = |query: &'static str| {
+            app.login(app.client.get(format!("/api/v2/bans?{}", query)), FC)
+                .dispatch()
+        };
+        async fn ids_of(response: rocket::local::asynchronous::LocalResponse<'_>) -> Vec<i64> {
+            assert_eq!(response.status(), Status::Ok);
+            let bans: Vec<Value> = response.json().await.unwrap();
+            let mut ids: Vec<i64> = bans.iter().map(|ban| ban["id"].as_i64().unwrap()).collect();
+            ids.sort();
+            ids
+        }
+
+        assert_eq!(ids_of(list("tag=awox").await).await, ids);
+        // Every tag has to match
+        assert_eq!(ids_of(list("tag=awox&tag=spy").await).await, vec![ids[0]]);
+        assert_eq!(ids_of(list("tag=spy&tag=AWOX").await).await, vec![ids[0]]);
+        assert!(ids_of(list("tag=spy&tag=rmt").await).await.is_empty());
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_reason_can_be_required() {
+        for &required in [false, true].iter() {
+            let app = match TestApp::with_config(
+                FakeEsi::new(&[
+                    (TARGET, "Bad Pilot", "Character"),
+                    (BAD_CORPORATION, "Bad Corp", "Corporation"),
+                ]),
+                |config| config.bans.require_reason = required,
+            )
+            .await
+            {
+                Some(app) => app,
+                None => return,
+            };
+            app.add_character(FC, "Some FC", Some("FC")).await;
+
+            let create = |body: Value| {
+                app.login(app.client.post("/api/v2/bans"), FC)
+                    .header(ContentType::JSON)
+                    .body(body.to_string())
+                    .dispatch()
+            };
+
+            let response = create(json!({
+                "entity": { "id": TARGET, "category": "Character" },
+                "reason": " ",
+                "public_reason": "Breaking the rules",
+            }))
+            .await;
+            if required {
+                assert_eq!(response.status(), Status::BadRequest);
+                assert!(response.into_string().await.unwrap().contains("\"reason\""));
+            } else {
+                assert_eq!(response.status(), Status::Created);
+            }
+
+            let response = create(json!({
+                "entity": { "id": BAD_CORPORATION, "category": "Corporation" },
+                "reason": "Awoxing",
+            }))
+            .await;
+            assert_eq!(response.status(), Status::Created);
+            assert_eq!(active_bans(&app).await.len(), if required { 1 } else { 2 });
+
+            app.destroy().await;
+        }
+    }
+
+    #[rocket::async_test]
+    async fn test_edit_keeps_issuer() {
+        const LEADER: i64 = 1003;
+
+        let app = match setup().await {
+            Some(app) => app,
+            None => return,
+        };
+        app.add_character(LEADER, "Some Leader", Some("Leadership"))
+            .await;
+
+        let response = app
+            .login(app.client.post("/api/v2/bans"), FC)
+            .header(ContentType::JSON)
+            .body(
+                json!({ "entity": { "id": TARGET, "category": "Character" }, "reason": "x" })
+                    .to_string(),
+            )
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Created);
+        let created: Value = response.json().await.unwrap();
+        let ban_id = created["id"].as_i64().unwrap();
+        sqlx::query!("UPDATE ban SET issued_at=1000 WHERE id=$1", ban_id)
+            .execute(app.db())
+            .await
+            .unwrap();
+
+        let response = app
+            .login(app.client.patch(format!("/api/v2/bans/{}", ban_id)), LEADER)
+            .header(ContentType::JSON)
+            .body(json!({ "reason": "y" }).to_string())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let updated: Value = response.json().await.unwrap();
+        assert_eq!(updated["issued_by"]["id"], FC);
+        assert_eq!(updated["issued_at"], 1000);
+
+        let ban = sqlx::query!("SELECT updated_by, updated_at FROM ban WHERE id=$1", ban_id)
+            .fetch_one(app.db())
+            .await
+            .unwrap();
+        assert_eq!(ban.updated_by, Some(LEADER));
+        assert!(ban.updated_at.unwrap() > 1000);
+
+        app.destroy().await;
+    }
+}
@@ -1,8 +1,14 @@
-use std::cmp::Ordering;
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet},
+};
 
 use crate::{
     app::Application,
-    core::auth::{get_access_keys, AuthenticatedAccount},
+    core::{
+        auth::{all_access_levels, get_access_keys, AuthenticatedAccount},
+        esi::{token_status, TokenStatus},
+    },
     util::madness::Madness,
 };
 
@@ -36,6 +42,17 @@ struct Commander {
     granted_at: i64,
 }
 
+// One character of an FC account, never the token itself
+#[derive(Serialize)]
+struct TokenHealth {
+    account_id: i64,
+    character: Character,
+    role: String,
+    status: TokenStatus,
+    refreshed_at: Option<i64>,
+    refresh_failed_at: Option<i64>,
+}
+
 #[derive(Serialize)]
 struct CommanderRank {
     name: String,
@@ -225,6 +242,65 @@ async fn assignable(account: AuthenticatedAccount) -> Result<Json<Vec<&'static s
     Ok(Json(options))
 }
 
+#[get("/api/commanders/roles/access")]
+async fn role_access(
+    account: AuthenticatedAccount,
+) -> Result<Json<&'static BTreeMap<String, BTreeSet<String>>>, Madness> {
+    account.require_access("access-levels-view")?;
+
+    Ok(Json(all_access_levels()))
+}
+
+// The ESI tokens of every FC and their alts, so they can be asked to log in again before a
+// fleet breaks on them
+#[get("/api/commanders/tokens")]
+async fn tokens(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+) -> Result<Json<Vec<TokenHealth>>, Madness> {
+    account.require_access("commanders-manage")?;
+
+    let now = chrono::Utc::now().timestamp();
+    let rows = sqlx::query!(
+        "SELECT
+            admin.character_id AS account_id,
+            admin.role,
+            c.id,
+            c.name,
+            c.esi_token_failed_at,
+            rt.refreshed_at AS \"refreshed_at?\",
+            rt.character_id IS NOT NULL AS \"has_token!\"
+        FROM admin
+        JOIN character c ON c.id = admin.character_id
+            OR c.id IN (SELECT alt_id FROM alt_character WHERE account_id = admin.character_id)
+        LEFT JOIN refresh_token rt ON rt.character_id = c.id
+        ORDER BY admin.character_id, c.id <> admin.character_id, c.name"
+    )
+    .fetch_all(app.get_db())
+    .await?;
+
+    Ok(Json(
+        rows.into_iter()
+            .map(|row| TokenHealth {
+                account_id: row.account_id,
+                character: Character {
+                    id: row.id,
+                    name: row.name,
+                },
+                role: row.role,
+                status: token_status(
+                    row.has_token,
+                    row.refreshed_at,
+                    row.esi_token_failed_at,
+                    now,
+                ),
+                refreshed_at: row.refreshed_at,
+                refresh_failed_at: row.esi_token_failed_at,
+            })
+            .collect(),
+    ))
+}
+
 #[get("/api/commanders/<character_id>")]
 async fn lookup(
     account: AuthenticatedAccount,
@@ -284,11 +360,97 @@ async fn revoke(
 
 pub fn routes() -> Vec<rocket::Route> {
     routes![
-        assign,             // POST     /api/commanders
-        list,               // GET      /api/commanders
-        public_directory,   // GET      /api/commanders/directory
-        assignable,         // GET      /api/commanders/roles
-        lookup,             // GET      /api/commanders/<character_id>
-        revoke              // DELETE   /api/commanders/<character_id>
+        assign,           // POST     /api/commanders
+        list,             // GET      /api/commanders
+        public_directory, // GET      /api/commanders/directory
+        assignable,       // GET      /api/commanders/roles
+        role_access,      // GET      /api/commanders/roles/access
+        tokens,           // GET      /api/commanders/tokens
+        lookup,           // GET      /api/commanders/<character_id>
+        revoke            // DELETE   /api/commanders/<character_id>
     ]
 }
+
+#[cfg(test)]
+mod tests {
+    use rocket::http::Status;
+    use serde_json::Value;
+
+    use crate::util::testapp::{FakeEsi, TestApp};
+
+    const FC: i64 = 1001;
+    const ALT: i64 = 1002;
+    const INSTRUCTOR: i64 = 1003;
+
+    #[rocket::async_test]
+    async fn test_tokens() {
+        let app = match TestApp::new(FakeEsi::new(&[])).await {
+            Some(app) => app,
+            None => return,
+        };
+        app.add_character(FC, "Some FC", Some("FC")).await;
+        app.add_character(ALT, "Some Alt", None).await;
+        app.add_character(INSTRUCTOR, "Some Instructor", Some("Instructor"))
+            .await;
+        let now = chrono::Utc::now().timestamp();
+        sqlx::query!(
+            "INSERT INTO alt_character (account_id, alt_id) VALUES ($1, $2)",
+            FC,
+            ALT
+        )
+        .execute(app.db())
+        .await
+        .unwrap();
+        sqlx::query!(
+            "INSERT INTO refresh_token (character_id, refresh_token, scopes, refreshed_at) VALUES ($1, 'secret', '', $2)",
+            FC,
+            now
+        )
+        .execute(app.db())
+        .await
+        .unwrap();
+        sqlx::query!(
+            "UPDATE character SET esi_token_failed_at=$2 WHERE id=$1",
+            ALT,
+            now
+        )
+        .execute(app.db())
+        .await
+        .unwrap();
+
+        let response = app
+            .login(app.client.get("/api/commanders/tokens"), FC)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Unauthorized);
+
+        let response = app
+            .login(app.client.get("/api/commanders/tokens"), INSTRUCTOR)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let body = response.into_string().await.unwrap();
+        assert!(!body.contains("secret"));
+        let tokens: Vec<Value> = serde_json::from_str(&body).unwrap();
+        let status: Vec<(i64, i64, &str)> = tokens
+            .iter()
+            .map(|token| {
+                (
+                    token["account_id"].as_i64().unwrap(),
+                    token["character"]["id"].as_i64().unwrap(),
+                    token["status"].as_str().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            status,
+            vec![
+                (FC, FC, "valid"),
+                (FC, ALT, "refresh-failed"),
+                (INSTRUCTOR, INSTRUCTOR, "missing"),
+            ]
+        );
+
+        app.destroy().await;
+    }
+}
@@ -0,0 +1,174 @@
+use rocket::request::{FromRequest, Outcome, Request};
+use serde::Deserialize;
+
+use crate::{app::Application, core::discord_roles::SIGNATURE_HEADER, util::madness::Madness};
+
+pub struct Signature(Option<String>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Signature {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(Signature(
+            req.headers().get_one(SIGNATURE_HEADER).map(str::to_string),
+        ))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct LinkRequest {
+    character_id: i64,
+    // Leaving it out unlinks the character
+    discord_id: Option<String>,
+}
+
+// Called by our Discord bot when a pilot links or unlinks their Discord account, signed with
+// discord.role_removal_secret. These links decide whose roles are removed when they're banned.
+#[put("/api/v2/discord/links", data = "<body>")]
+async fn link(
+    app: &rocket::State<Application>,
+    signature: Signature,
+    body: String,
+) -> Result<&'static str, Madness> {
+    if !app.role_removal_client.is_enabled() {
+        return Err(Madness::NotFound("Discord role removal is not configured"));
+    }
+    match &signature.0 {
+        Some(signature) if app.role_removal_client.verify(body.as_bytes(), signature) => (),
+        _ => return Err(Madness::AccessDenied),
+    }
+
+    let request: LinkRequest = serde_json::from_str(&body)
+        .map_err(|e| Madness::BadRequest(format!("Invalid link: {}", e)))?;
+
+    match request.discord_id {
+        Some(discord_id) => {
+            if discord_id.is_empty() || discord_id.len() > 32 {
+                return Err(Madness::BadRequest(
+                    "discord_id must be between 1 and 32 characters".to_string(),
+                ));
+            }
+            if sqlx::query!("SELECT id FROM character WHERE id=$1", request.character_id)
+                .fetch_optional(app.get_db())
+                .await?
+                .is_none()
+            {
+                return Err(Madness::NotFound("Character not found"));
+            }
+
+            sqlx::query!(
+                "INSERT INTO character_discord (character_id, discord_id) VALUES ($1, $2)
+                ON CONFLICT (character_id) DO UPDATE SET discord_id=excluded.discord_id",
+                request.character_id,
+                discord_id
+            )
+            .execute(app.get_db())
+            .await?;
+        }
+        None => {
+            sqlx::query!(
+                "DELETE FROM character_discord WHERE character_id=$1",
+                request.character_id
+            )
+            .execute(app.get_db())
+            .await?;
+        }
+    }
+
+    Ok("Ok")
+}
+
+pub fn routes() -> Vec<rocket::Route> {
+    routes![
+        link // PUT      /api/v2/discord/links
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::http::{Header, Status};
+    use serde_json::json;
+
+    use crate::core::discord_roles::{sign, SIGNATURE_HEADER};
+    use crate::util::testapp::{FakeEsi, TestApp};
+
+    const PILOT: i64 = 1002;
+    const SECRET: &str = "00ff";
+
+    async fn put_link(app: &TestApp, body: &str, secret: &[u8]) -> Status {
+        app.client
+            .put("/api/v2/discord/links")
+            .header(Header::new(
+                SIGNATURE_HEADER,
+                format!("sha256={}", sign(secret, body.as_bytes())),
+            ))
+            .body(body)
+            .dispatch()
+            .await
+            .status()
+    }
+
+    async fn linked(app: &TestApp) -> Option<String> {
+        sqlx::query!(
+            "SELECT discord_id FROM character_discord WHERE character_id=$1",
+            PILOT
+        )
+        .fetch_optional(app.db())
+        .await
+        .unwrap()
+        .map(|row| row.discord_id)
+    }
+
+    #[rocket::async_test]
+    async fn test_link() {
+        let app = match TestApp::with_config(FakeEsi::new(&[]), |config| {
+            config.discord.role_removal_url = Some("http://127.0.0.1:9".to_string());
+            config.discord.role_removal_secret = Some(SECRET.to_string());
+        })
+        .await
+        {
+            Some(app) => app,
+            None => return,
+        };
+        app.add_character(PILOT, "Some Pilot", None).await;
+        let secret = hex::decode(SECRET).unwrap();
+
+        let body = json!({ "character_id": PILOT, "discord_id": "1234" }).to_string();
+        assert_eq!(put_link(&app, &body, b"wrong").await, Status::Unauthorized);
+        assert_eq!(linked(&app).await, None);
+
+        assert_eq!(put_link(&app, &body, &secret).await, Status::Ok);
+        assert_eq!(linked(&app).await.as_deref(), Some("1234"));
+
+        // Linking again replaces the old account
+        let body = json!({ "character_id": PILOT, "discord_id": "5678" }).to_string();
+        assert_eq!(put_link(&app, &body, &secret).await, Status::Ok);
+        assert_eq!(linked(&app).await.as_deref(), Some("5678"));
+
+        let body = json!({ "character_id": 999, "discord_id": "5678" }).to_string();
+        assert_eq!(put_link(&app, &body, &secret).await, Status::NotFound);
+
+        let body = json!({ "character_id": PILOT }).to_string();
+        assert_eq!(put_link(&app, &body, &secret).await, Status::Ok);
+        assert_eq!(linked(&app).await, None);
+
+        app.destroy().await;
+    }
+
+    #[rocket::async_test]
+    async fn test_link_not_configured() {
+        let app = match TestApp::new(FakeEsi::new(&[])).await {
+            Some(app) => app,
+            None => return,
+        };
+
+        let body = json!({ "character_id": PILOT, "discord_id": "1234" }).to_string();
+        assert_eq!(
+            put_link(&app, &body, &hex::decode(SECRET).unwrap()).await,
+            Status::NotFound
+        );
+
+        app.destroy().await;
+    }
+}
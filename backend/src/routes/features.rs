@@ -0,0 +1,74 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    app::Application, config::FEATURES, core::auth::AuthenticatedAccount, util::madness::Madness,
+};
+
+use rocket::serde::json::Json;
+
+// Every known feature and whether it's on for this instance
+#[get("/api/features")]
+fn list(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+) -> Result<Json<BTreeMap<&'static str, bool>>, Madness> {
+    account.require_access("access-levels-view")?;
+
+    Ok(Json(
+        FEATURES
+            .iter()
+            .map(|(name, _)| (*name, app.feature_enabled(name)))
+            .collect(),
+    ))
+}
+
+pub fn routes() -> Vec<rocket::Route> {
+    routes![
+        list // GET      /api/features
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::http::Status;
+    use serde_json::{json, Value};
+
+    use crate::util::testapp::{FakeEsi, ReadJson, TestApp};
+
+    const FC: i64 = 1001;
+    const LEADER: i64 = 1003;
+
+    #[rocket::async_test]
+    async fn test_list() {
+        let app = match TestApp::with_config(FakeEsi::new(&[]), |config| {
+            config.features.insert("discord_webhook".to_string(), false);
+        })
+        .await
+        {
+            Some(app) => app,
+            None => return,
+        };
+        app.add_character(FC, "Some FC", Some("FC")).await;
+        app.add_character(LEADER, "Some Leader", Some("Leadership"))
+            .await;
+
+        let response = app
+            .login(app.client.get("/api/features"), FC)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Unauthorized);
+
+        let response = app
+            .login(app.client.get("/api/features"), LEADER)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(
+            body,
+            json!({ "discord_role_removal": true, "discord_webhook": false })
+        );
+
+        app.destroy().await;
+    }
+}
@@ -9,6 +9,7 @@ use crate::{
     tdf::{
         self,
         fitcheck::{Output, PilotData, PubAnalysis},
+        stats::FitStats,
     },
     util::madness::Madness,
 };
@@ -23,6 +24,7 @@ struct XupRequest {
 pub struct FitResult {
     pub approved: bool,
     pub fit_analysis: Option<PubAnalysis>,
+    pub stats: Option<FitStats>,
     pub dna: String,
 }
 
@@ -40,6 +42,7 @@ async fn fitcheck(
         skills: &skills::load_skills(&app.esi_client, app.get_db(), input.character_id).await?,
         access_keys: account.access,
         id: &input.character_id,
+        skill_gate_override: skills::has_gate_override(app.get_db(), input.character_id).await?,
     };
 
     let badges: Vec<String> = sqlx::query!(
@@ -65,6 +68,7 @@ async fn fitcheck(
         result.push(FitResult {
             approved: fit_checked.approved,
             fit_analysis: fit_checked.analysis,
+            stats: fit_checked.stats,
             dna: fit.to_dna()?,
         });
     }
@@ -0,0 +1,134 @@
+use crate::{
+    app::Application,
+    core::{
+        auth::{authorize_character, AuthenticatedAccount},
+        esi::{ESIError, ESIScope},
+        sse::Event,
+    },
+    data::fleets::FleetInfo,
+    util::{madness::Madness, types::Character},
+};
+
+use rocket::serde::json::Json;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize)]
+struct RegisterRequest {
+    character_id: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct ActiveFleet {
+    id: i64,
+    boss: Character,
+    registered_at: i64,
+}
+
+#[post("/api/v2/fleet/register", data = "<body>")]
+async fn register(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+    body: Json<RegisterRequest>,
+) -> Result<Json<ActiveFleet>, Madness> {
+    account.require_access("fleet-configure")?;
+    authorize_character(app.get_db(), &account, body.character_id, None).await?;
+
+    let basic_info: FleetInfo = match app
+        .esi_client
+        .get(
+            &format!("/v2/characters/{}/fleet", body.character_id),
+            body.character_id,
+            ESIScope::Fleets_ReadFleet_v1,
+        )
+        .await
+    {
+        Ok(info) => info,
+        Err(ESIError::Status(404)) | Err(ESIError::WithMessage(404, _)) => {
+            return Err(Madness::NotFound("You are not in a fleet"))
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    // Invites and fleet tracking are done with the boss' token
+    if basic_info.fleet_boss_id != body.character_id {
+        return Err(Madness::BadRequest(
+            "You must be the fleet boss to register the fleet".to_string(),
+        ));
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    sqlx::query!(
+        "INSERT INTO fleet (id, boss_id, max_size, registered_at) VALUES ($1, $2, 40, $3) ON CONFLICT (id) DO UPDATE
+        SET boss_id = excluded.boss_id,
+        registered_at = excluded.registered_at;",
+        basic_info.fleet_id,
+        basic_info.fleet_boss_id,
+        now
+    )
+    .execute(app.get_db())
+    .await?;
+
+    let boss = sqlx::query!(
+        "SELECT name FROM character WHERE id=$1",
+        basic_info.fleet_boss_id
+    )
+    .fetch_one(app.get_db())
+    .await?;
+
+    app.sse_client
+        .submit(vec![Event::new_json("fleet", "fleets", "registered")])
+        .await?;
+
+    Ok(Json(ActiveFleet {
+        id: basic_info.fleet_id,
+        boss: Character {
+            id: basic_info.fleet_boss_id,
+            name: boss.name,
+            corporation_id: None,
+        },
+        registered_at: now,
+    }))
+}
+
+#[get("/api/v2/fleet/active")]
+async fn active(
+    account: AuthenticatedAccount,
+    app: &rocket::State<Application>,
+) -> Result<Json<ActiveFleet>, Madness> {
+    account.require_access("fleet-view")?;
+
+    // The most recently registered fleet is the active one
+    if let Some(fleet) = sqlx::query!(
+        "SELECT
+            fleet.id,
+            fleet.registered_at,
+            fc.id as boss_id,
+            fc.name as boss_name
+        FROM fleet
+        JOIN character as fc ON fc.id=fleet.boss_id
+        ORDER BY fleet.registered_at DESC
+        LIMIT 1"
+    )
+    .fetch_optional(app.get_db())
+    .await?
+    {
+        return Ok(Json(ActiveFleet {
+            id: fleet.id,
+            boss: Character {
+                id: fleet.boss_id,
+                name: fleet.boss_name,
+                corporation_id: None,
+            },
+            registered_at: fleet.registered_at,
+        }));
+    }
+
+    Err(Madness::NotFound("No fleet has been registered"))
+}
+
+pub fn routes() -> Vec<rocket::Route> {
+    routes![
+        register, // POST   /api/v2/fleet/register
+        active,   // GET    /api/v2/fleet/active
+    ]
+}
@@ -183,7 +183,8 @@ async fn register(
     sqlx::query!(
         "INSERT INTO fleet (id, boss_id, max_size) VALUES ($1, $2, 40) ON CONFLICT (id) DO UPDATE
         SET max_size = excluded.max_size, 
-        boss_id = excluded.boss_id;",
+        boss_id = excluded.boss_id,
+        registered_at = excluded.registered_at;",
         basic_info.fleet_id,
         basic_info.fleet_boss_id
     )
@@ -1,4 +1,5 @@
 mod actions;
+mod active;
 mod configure;
 mod comp;
 mod notify;
@@ -9,6 +10,7 @@ mod historic;
 pub fn routes() -> Vec<rocket::Route> {
     [
         actions::routes(),
+        active::routes(),
         configure::routes(),
         comp::routes(),
         settings::routes(),
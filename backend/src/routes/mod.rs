@@ -4,6 +4,8 @@ mod badges;
 mod bans;
 mod categories;
 mod commanders;
+mod discord;
+mod features;
 mod fitcheck;
 mod fittings;
 mod fleet; // deprecated
@@ -14,9 +16,10 @@ mod implants;
 mod modules;
 mod notes;
 mod pilot;
+mod reports;
 mod search;
+mod server_status;
 mod skillplans;
-mod reports;
 mod skills;
 mod sse;
 mod statistics;
@@ -37,6 +40,7 @@ pub fn routes() -> Vec<rocket::Route> {
         commanders::routes(),
         modules::routes(),
         search::routes(),
+        server_status::routes(),
         categories::routes(),
         fleet::routes(),
         fleets::routes(),
@@ -48,7 +52,9 @@ pub fn routes() -> Vec<rocket::Route> {
         skillplans::routes(),
         fitcheck::routes(),
         fittings::routes(),
-        reports::routes()
+        reports::routes(),
+        features::routes(),
+        discord::routes(),
     ]
     .concat()
 }
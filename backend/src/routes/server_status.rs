@@ -0,0 +1,94 @@
+use std::sync::Mutex;
+
+use rocket::serde::json::Json;
+use serde::{Deserialize, Serialize};
+
+use crate::{app::Application, core::auth::AuthenticatedAccount, util::madness::Madness};
+
+// Used when ESI doesn't send an expires header
+const DEFAULT_CACHE_TIME: i64 = 30;
+
+#[derive(Debug, Clone, Deserialize)]
+struct EsiStatus {
+    players: i64,
+    server_version: String,
+    start_time: String,
+    #[serde(default)]
+    vip: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum ServerState {
+    Online,
+    Unreachable,
+}
+
+#[derive(Debug, Serialize)]
+struct ServerStatusResponse {
+    state: ServerState,
+    players: Option<i64>,
+    server_version: Option<String>,
+    start_time: Option<String>,
+    vip: bool,
+}
+
+lazy_static::lazy_static! {
+    // The last status ESI gave us, and when it expires
+    static ref STATUS_CACHE: Mutex<Option<(EsiStatus, i64)>> = Mutex::new(None);
+}
+
+async fn load_status(app: &Application) -> Option<EsiStatus> {
+    let now = chrono::Utc::now().timestamp();
+    if let Some((status, expires)) = STATUS_CACHE.lock().unwrap().as_ref() {
+        if *expires > now {
+            return Some(status.clone());
+        }
+    }
+
+    match app
+        .esi_client
+        .get_unauthenticated_with_expiry::<EsiStatus>("/latest/status/")
+        .await
+    {
+        Ok((status, expires)) => {
+            let expires = expires.unwrap_or(now + DEFAULT_CACHE_TIME);
+            *STATUS_CACHE.lock().unwrap() = Some((status.clone(), expires));
+            Some(status)
+        }
+        Err(e) => {
+            warn!("Unable to load the server status from ESI: {:#?}", e);
+            None
+        }
+    }
+}
+
+#[get("/api/v2/server-status")]
+async fn server_status(
+    app: &rocket::State<Application>,
+    _account: AuthenticatedAccount,
+) -> Result<Json<ServerStatusResponse>, Madness> {
+    // An unreachable ESI is an answer in itself, so it isn't treated as an error
+    let response = match load_status(app).await {
+        Some(status) => ServerStatusResponse {
+            state: ServerState::Online,
+            players: Some(status.players),
+            server_version: Some(status.server_version),
+            start_time: Some(status.start_time),
+            vip: status.vip,
+        },
+        None => ServerStatusResponse {
+            state: ServerState::Unreachable,
+            players: None,
+            server_version: None,
+            start_time: None,
+            vip: false,
+        },
+    };
+
+    Ok(Json(response))
+}
+
+pub fn routes() -> Vec<rocket::Route> {
+    routes![server_status]
+}
@@ -2,7 +2,7 @@ use std::collections::HashMap;
 
 use eve_data_core::{SkillLevel, TypeID};
 use rocket::serde::json::Json;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     core::auth::{authorize_character, AuthenticatedAccount},
@@ -41,6 +41,50 @@ async fn list_skills(
     }))
 }
 
+#[derive(Deserialize, Debug)]
+struct GateOverrideRequest {
+    character_id: i64,
+}
+
+#[post("/api/skills/gate-override", data = "<input>")]
+async fn add_gate_override(
+    app: &rocket::State<crate::app::Application>,
+    account: AuthenticatedAccount,
+    input: Json<GateOverrideRequest>,
+) -> Result<&'static str, Madness> {
+    account.require_access("waitlist-edit")?;
+
+    let now = chrono::Utc::now().timestamp();
+    sqlx::query!(
+        "INSERT INTO skill_gate_override (character_id, granted_by_id, granted_at) VALUES ($1, $2, $3) ON CONFLICT (character_id) DO NOTHING",
+        input.character_id,
+        account.id,
+        now
+    )
+    .execute(app.get_db())
+    .await?;
+
+    Ok("OK")
+}
+
+#[delete("/api/skills/gate-override?<character_id>")]
+async fn remove_gate_override(
+    app: &rocket::State<crate::app::Application>,
+    account: AuthenticatedAccount,
+    character_id: i64,
+) -> Result<&'static str, Madness> {
+    account.require_access("waitlist-edit")?;
+
+    sqlx::query!(
+        "DELETE FROM skill_gate_override WHERE character_id=$1",
+        character_id
+    )
+    .execute(app.get_db())
+    .await?;
+
+    Ok("OK")
+}
+
 pub fn routes() -> Vec<rocket::Route> {
-    routes![list_skills]
+    routes![list_skills, add_gate_override, remove_gate_override]
 }
@@ -14,6 +14,10 @@ fn stream(app: &rocket::State<crate::app::Application>, account: AuthenticatedAc
         topics.push("fleet".to_string());
     }
 
+    if account.access.contains("bans-manage") {
+        topics.push("bans".to_string());
+    }
+
     Redirect::temporary(app.sse_client.events_url(&topics))
 }
 
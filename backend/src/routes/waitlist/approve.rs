@@ -11,6 +11,9 @@ use crate::{
 #[derive(Debug, Deserialize)]
 struct ApproveRequest {
     id: i64,
+    // When approving from a category queue, make sure the fit is still in it
+    #[serde(default)]
+    category: Option<String>,
 }
 
 #[post("/api/waitlist/approve", data = "<input>")]
@@ -21,13 +24,18 @@ async fn approve_fit(
 ) -> Result<&'static str, Madness> {
     account.require_access("waitlist-manage")?;
 
-    sqlx::query!(
-        "UPDATE waitlist_entry_fit SET state='approved' WHERE id=$1",
-        input.id
+    let updated = sqlx::query!(
+        "UPDATE waitlist_entry_fit SET state='approved' WHERE id=$1 AND ($2::VARCHAR IS NULL OR category=$2)",
+        input.id,
+        input.category
     )
     .execute(app.get_db())
     .await?;
 
+    if input.category.is_some() && updated.rows_affected() == 0 {
+        return Err(Madness::NotFound("Fit not found in this category"));
+    }
+
     super::notify::notify_waitlist_update(app).await?;
 
     Ok("OK")
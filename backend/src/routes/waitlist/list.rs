@@ -19,6 +19,14 @@ struct WaitlistResponse {
     open: bool,
     waitlist: Option<Vec<WaitlistEntry>>,
     categories: Vec<&'static str>,
+    queues: Option<Vec<WaitlistQueue>>,
+}
+
+#[derive(Debug, Serialize)]
+struct WaitlistQueue {
+    id: &'static str,
+    name: &'static str,
+    fits: Vec<i64>,
 }
 
 #[derive(Debug, Serialize)]
@@ -27,6 +35,7 @@ struct WaitlistEntry {
     fits: Vec<WaitlistEntryFit>,
     character: Option<Character>,
     joined_at: i64,
+    age_seconds: i64,
     can_remove: bool,
 }
 
@@ -70,9 +79,11 @@ async fn list(
             open: false,
             waitlist: None,
             categories: waitlist_categories,
+            queues: None,
         }));
     }
 
+    let now = chrono::Utc::now().timestamp();
     let records = sqlx::query!(
         "
             SELECT
@@ -114,8 +125,22 @@ async fn list(
         .collect();
     let hull_names = TypeDB::names_of(&hulls)?;
 
+    // One queue per category, in the order they are defined in categories.yaml
+    let mut queues: Vec<WaitlistQueue> = data::categories::categories()
+        .iter()
+        .map(|cat| WaitlistQueue {
+            id: &cat.id,
+            name: &cat.name,
+            fits: Vec::new(),
+        })
+        .collect();
+
     let mut entries = BTreeMap::new();
     for record in records {
+        if let Some(queue) = queues.iter_mut().find(|q| q.id == record.wef_category) {
+            queue.fits.push(record.wef_id);
+        }
+
         let x_is_ours = record.we_account_id == account.id;
 
         let entry = entries
@@ -133,6 +158,7 @@ async fn list(
                     None
                 },
                 joined_at: record.we_joined_at,
+                age_seconds: now - record.we_joined_at,
                 can_remove: x_is_ours || account.access.contains("waitlist-manage"),
             });
 
@@ -207,6 +233,7 @@ async fn list(
     Ok(Json(WaitlistResponse {
         open: true,
         categories: waitlist_categories,
+        queues: Some(queues),
         waitlist: Some(entries.into_iter().map(|(_id, entry)| entry).collect()),
     }))
 }
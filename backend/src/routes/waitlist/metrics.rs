@@ -0,0 +1,57 @@
+use std::collections::BTreeMap;
+
+use rocket::serde::json::Json;
+use serde::Serialize;
+
+use crate::{app::Application, core::auth::AuthenticatedAccount, util::madness::Madness};
+
+// Default to the last week when no range is given
+const DEFAULT_RANGE: i64 = 7 * 86400;
+
+#[derive(Debug, Serialize)]
+struct MetricsResponse {
+    from: i64,
+    to: i64,
+    // Snapshot time -> category -> number of fits
+    series: BTreeMap<i64, BTreeMap<String, i64>>,
+}
+
+#[get("/api/v2/waitlist/metrics?<from>&<to>")]
+async fn metrics(
+    app: &rocket::State<Application>,
+    account: AuthenticatedAccount,
+    from: Option<i64>,
+    to: Option<i64>,
+) -> Result<Json<MetricsResponse>, Madness> {
+    account.require_access("stats-view")?;
+
+    let to = to.unwrap_or_else(|| chrono::Utc::now().timestamp());
+    let from = from.unwrap_or(to - DEFAULT_RANGE);
+    if from > to {
+        return Err(Madness::BadRequest(
+            "\"from\" must be before \"to\"".to_string(),
+        ));
+    }
+
+    let rows = sqlx::query!(
+        "SELECT recorded_at, category, size FROM waitlist_metrics WHERE recorded_at >= $1 AND recorded_at <= $2 ORDER BY recorded_at",
+        from,
+        to
+    )
+    .fetch_all(app.get_db())
+    .await?;
+
+    let mut series = BTreeMap::new();
+    for row in rows {
+        series
+            .entry(row.recorded_at)
+            .or_insert_with(BTreeMap::new)
+            .insert(row.category, row.size);
+    }
+
+    Ok(Json(MetricsResponse { from, to, series }))
+}
+
+pub fn routes() -> Vec<rocket::Route> {
+    routes![metrics]
+}
@@ -3,6 +3,7 @@ mod empty;
 mod invite;
 mod list;
 mod message;
+mod metrics;
 mod notify;
 mod remove;
 mod xup;
@@ -13,6 +14,7 @@ pub fn routes() -> Vec<rocket::Route> {
         approve::routes(),
         empty::routes(),
         message::routes(),
+        metrics::routes(),
         remove::routes(),
         invite::routes(),
         xup::routes(),
@@ -5,8 +5,11 @@ use serde::Deserialize;
 
 use crate::{
     app::Application,
-    core::auth::{authorize_character, AuthenticatedAccount},
-    data::{implants, skills},
+    core::{
+        auth::{authorize_character, AuthenticatedAccount},
+        idempotency::IdempotencyKey,
+    },
+    data::{self, implants, skills},
     tdf,
     util::madness::Madness,
 };
@@ -26,6 +29,14 @@ struct XupRequest {
 
     #[serde(default)]
     dna: Vec<DnaXup>,
+
+    // Lets the pilot pick a queue instead of the one derived from the fit
+    #[serde(default)]
+    category: Option<String>,
+
+    // Sends a notification if the x-up is removed for inactivity
+    #[serde(default)]
+    notify_on_expiry: bool,
 }
 
 const MAX_X_PER_ACCOUNT: usize = 10;
@@ -94,6 +105,8 @@ async fn xup_multi(
     account: AuthenticatedAccount,
     xups: Vec<(i64, Fitting)>,
     is_alt: bool,
+    category: Option<&str>,
+    notify_on_expiry: bool,
 ) -> Result<(), Madness> {
     // Track the "now" from the start of the operation, to keep things fair
     let now = chrono::Utc::now().timestamp();
@@ -146,12 +159,16 @@ async fn xup_multi(
         let time_in_fleet = get_time_in_fleet(app.get_db(), character_id).await?;
         let implants = implants::get_implants(app, character_id).await?;
         let skills = skills::load_skills(&app.esi_client, app.get_db(), character_id).await?;
+        let gate_override = skills::has_gate_override(app.get_db(), character_id).await?;
 
-        character_info.insert(character_id, (time_in_fleet, implants, skills));
+        character_info.insert(
+            character_id,
+            (time_in_fleet, implants, skills, gate_override),
+        );
     }
 
     let mut pilot_data = HashMap::new();
-    for (character_id, (time_in_fleet, implants, skills)) in character_info.iter() {
+    for (character_id, (time_in_fleet, implants, skills, gate_override)) in character_info.iter() {
         pilot_data.insert(
             character_id,
             tdf::fitcheck::PilotData {
@@ -159,7 +176,8 @@ async fn xup_multi(
                 time_in_fleet: *time_in_fleet,
                 skills,
                 access_keys: account.access,
-                id: character_id
+                id: character_id,
+                skill_gate_override: *gate_override,
             },
         );
     }
@@ -175,12 +193,24 @@ async fn xup_multi(
     .fetch_optional(&mut tx)
     .await?
     {
-        Some(e) => e.id,
+        Some(e) => {
+            // X'ing up again counts as activity, so the entry doesn't expire
+            sqlx::query!(
+                "UPDATE waitlist_entry SET last_activity_at=$2, notify_on_expiry=$3 WHERE id=$1",
+                e.id,
+                now,
+                notify_on_expiry
+            )
+            .execute(&mut tx)
+            .await?;
+            e.id
+        }
         None => {
             let result = match sqlx::query!(
-                "INSERT INTO waitlist_entry (account_id, joined_at) VALUES ($1, $2) returning id",
+                "INSERT INTO waitlist_entry (account_id, joined_at, last_activity_at, notify_on_expiry) VALUES ($1, $2, $2, $3) returning id",
                 account.id,
                 now,
+                notify_on_expiry,
             )
             .fetch_optional(&mut tx)
             .await? 
@@ -237,6 +267,12 @@ async fn xup_multi(
             return Err(Madness::BadRequest(error));
         }
 
+        // Starters always go to the starter queue, otherwise the pilot's choice wins
+        let category = match category {
+            Some(chosen) if fit_checked.category != "starter" => chosen.to_string(),
+            _ => fit_checked.category,
+        };
+
         let tags = fit_checked.tags.join(",");
         let fit_analysis: Option<String> = fit_checked
             .analysis
@@ -246,7 +282,7 @@ async fn xup_multi(
         sqlx::query!("
             INSERT INTO waitlist_entry_fit (character_id, entry_id, fit_id, category, state, tags, implant_set_id, fit_analysis, cached_time_in_fleet, is_alt)
             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
-        ", character_id, entry_id, fit_id, fit_checked.category, match fit_checked.approved {
+        ", character_id, entry_id, fit_id, category, match fit_checked.approved {
             true => "approved",
             false => "pending"
         }, tags, implant_set_id, fit_analysis, this_pilot_data.time_in_fleet, is_alt)
@@ -272,10 +308,40 @@ async fn xup_multi(
 async fn xup(
     app: &rocket::State<Application>,
     account: AuthenticatedAccount,
+    idempotency_key: IdempotencyKey,
     input: Json<XupRequest>,
-) -> Result<&'static str, Madness> {
+) -> Result<String, Madness> {
+    let account_id = account.id;
+    if let Some(response) = idempotency_key
+        .claim(app.get_db(), account_id, "xup", app.clock.now().timestamp())
+        .await?
+    {
+        return Ok(response);
+    }
+
+    let result = xup_fits(app, account, &input).await;
+    idempotency_key
+        .complete(app.get_db(), account_id, "xup", &result)
+        .await?;
+    result
+}
+
+async fn xup_fits(
+    app: &Application,
+    account: AuthenticatedAccount,
+    input: &XupRequest,
+) -> Result<String, Madness> {
     // Character authorization is done by xup_multi!
 
+    if let Some(category) = &input.category {
+        if !data::categories::category_exists(category) {
+            return Err(Madness::BadRequest(format!(
+                "\"{}\" is not a valid waitlist category",
+                category
+            )));
+        }
+    }
+
     // EFT x'es
     let fits = Fitting::from_eft(&input.eft)?;
     let mut xups: Vec<_> = fits
@@ -289,9 +355,17 @@ async fn xup(
         xups.push((dna_xup.character_id, fit));
     }
 
-    xup_multi(app, account, xups, input.is_alt).await?;
+    xup_multi(
+        app,
+        account,
+        xups,
+        input.is_alt,
+        input.category.as_deref(),
+        input.notify_on_expiry,
+    )
+    .await?;
 
-    Ok("OK")
+    Ok("OK".to_string())
 }
 
 pub fn routes() -> Vec<rocket::Route> {
@@ -1,14 +1,23 @@
-use std::{
-    cmp::min,
-    collections::{BTreeMap, BTreeSet},
+use super::{
+    fitmatch, implantmatch,
+    skillgate::{self, GateAction},
+    skills::SkillTier,
+    stats::{self, FitStats},
+};
+use crate::data::{
+    categories,
+    fits::{DoctrineFit, Tolerance},
+    skills::Skills,
 };
-use reqwest::Method;
-use super::{fitmatch, implantmatch, skills::SkillTier};
-use crate::data::{categories, fits::DoctrineFit, skills::Skills};
 use eve_data_core::{FitError, Fitting, TypeDB, TypeID};
-use serde::Serialize;
 use inflector::Inflector;
+use reqwest::Method;
+use serde::Serialize;
 use std::time::Duration;
+use std::{
+    cmp::min,
+    collections::{BTreeMap, BTreeSet},
+};
 
 #[derive(Debug)]
 pub struct Output {
@@ -18,6 +27,7 @@ pub struct Output {
     pub errors: Vec<String>,
 
     pub analysis: Option<PubAnalysis>,
+    pub stats: Option<FitStats>,
 }
 
 #[derive(Debug, Serialize)]
@@ -27,6 +37,7 @@ pub struct PubAnalysis {
     extra: BTreeMap<TypeID, i64>,
     cargo_missing: BTreeMap<TypeID, i64>,
     downgraded: BTreeMap<TypeID, BTreeMap<TypeID, i64>>,
+    tolerance: Tolerance,
 }
 
 pub struct PilotData<'a> {
@@ -34,7 +45,8 @@ pub struct PilotData<'a> {
     pub time_in_fleet: i64,
     pub skills: &'a Skills,
     pub access_keys: &'a BTreeSet<String>,
-    pub id: &'a i64
+    pub id: &'a i64,
+    pub skill_gate_override: bool,
 }
 
 pub struct FitChecker<'a> {
@@ -69,6 +81,7 @@ impl<'a> FitChecker<'a> {
         };
 
         checker.check_skill_reqs()?;
+        checker.check_skill_gate()?;
         checker.check_module_skills()?;
         checker.check_fit();
         checker.check_fit_reqs();
@@ -122,6 +135,36 @@ impl<'a> FitChecker<'a> {
         Ok(())
     }
 
+    fn check_skill_gate(&mut self) -> Result<(), FitError> {
+        // FCs can let individual pilots past the gate
+        if self.pilot.skill_gate_override {
+            return Ok(());
+        }
+
+        if let Some((action, failures)) = skillgate::check(self.fit.hull, self.pilot.skills)? {
+            match action {
+                GateAction::Flag => {
+                    self.tags.insert("SKILL-GATE");
+                }
+                GateAction::Reject => {
+                    let ship_name = TypeDB::name_of(self.fit.hull)?;
+                    for failure in failures {
+                        self.errors.push(format!(
+                            "The {} requires {} {}, you have level {} ({} short)",
+                            ship_name,
+                            failure.skill,
+                            failure.required,
+                            failure.current,
+                            failure.required - failure.current
+                        ));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     fn check_module_skills(&mut self) -> Result<(), FitError> {
         let mut module_ids = vec![self.fit.hull];
         for &module_id in self.fit.modules.keys() {
@@ -159,7 +202,15 @@ impl<'a> FitChecker<'a> {
                 self.tags.insert("ANTIGANK");
             }
 
-            let fit_ok = diff.module_downgraded.is_empty() && diff.module_missing.is_empty();
+            match doctrine_fit.tolerance {
+                // Upgrades are deviations too
+                Tolerance::Exact => diff.module_downgraded.append(&mut diff.module_upgraded),
+                Tolerance::AllowMeta | Tolerance::AllowFactionUpgrade => (),
+            };
+
+            let fit_ok = diff.module_missing.is_empty()
+                && (doctrine_fit.tolerance == Tolerance::AllowMeta
+                    || diff.module_downgraded.is_empty());
 
             if !(diff.cargo_missing.is_empty() && fit_ok) {
                 self.approved = false;
@@ -177,6 +228,7 @@ impl<'a> FitChecker<'a> {
                 extra: diff.module_extra,
                 downgraded: diff.module_downgraded,
                 cargo_missing: diff.cargo_missing,
+                tolerance: doctrine_fit.tolerance,
             });
         } else {
             self.approved = false;
@@ -470,6 +522,8 @@ impl<'a> FitChecker<'a> {
             errors: self.errors,
             category: self.category.expect("Category not assigned"),
             analysis: self.analysis,
+            // Only an estimate, a fit we can't evaluate still gets checked
+            stats: stats::estimate(self.fit).ok(),
         })
     }
 }
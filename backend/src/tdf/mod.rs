@@ -1,4 +1,6 @@
 pub mod fitcheck;
 mod fitmatch;
 mod implantmatch;
+pub mod skillgate;
 pub mod skills;
+pub mod stats;
@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+
+use crate::data::{skills::Skills, yamlhelper};
+use eve_data_core::{SkillLevel, TypeDB, TypeError, TypeID};
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GateAction {
+    Reject,
+    Flag,
+}
+
+struct SkillGate {
+    action: GateAction,
+    skills: HashMap<TypeID, SkillLevel>,
+}
+
+#[derive(Debug)]
+pub struct GateFailure {
+    pub skill: String,
+    pub required: SkillLevel,
+    pub current: SkillLevel,
+}
+
+lazy_static::lazy_static! {
+    static ref SKILL_GATES: HashMap<TypeID, SkillGate> = build_skill_gates().unwrap();
+}
+
+fn build_skill_gates() -> Result<HashMap<TypeID, SkillGate>, TypeError> {
+    #[derive(Deserialize)]
+    struct GateFile {
+        action: GateAction,
+        skills: HashMap<String, SkillLevel>,
+    }
+
+    let file: HashMap<String, GateFile> = yamlhelper::from_file("./data/skillgates.yaml");
+
+    let mut gates = HashMap::new();
+    for (ship_name, gate) in file {
+        let mut skills = HashMap::new();
+        for (skill_name, level) in gate.skills {
+            skills.insert(TypeDB::id_of(&skill_name)?, level);
+        }
+
+        gates.insert(
+            TypeDB::id_of(&ship_name)?,
+            SkillGate {
+                action: gate.action,
+                skills,
+            },
+        );
+    }
+
+    Ok(gates)
+}
+
+pub fn check(
+    hull: TypeID,
+    skills: &Skills,
+) -> Result<Option<(GateAction, Vec<GateFailure>)>, TypeError> {
+    let gate = match SKILL_GATES.get(&hull) {
+        Some(gate) => gate,
+        None => return Ok(None),
+    };
+
+    let mut failures = Vec::new();
+    for (&skill_id, &required) in &gate.skills {
+        let current = skills.get(skill_id);
+        if current < required {
+            failures.push(GateFailure {
+                skill: TypeDB::name_of(skill_id)?,
+                required,
+                current,
+            });
+        }
+    }
+
+    if failures.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some((gate.action, failures)))
+}
@@ -0,0 +1,257 @@
+// Rough DPS and EHP estimates for a fit, to give FCs a quality signal beyond "matches doctrine".
+//
+// This is deliberately not a full dogma engine. Assumptions:
+// - Skills, implants, hull bonuses, boosts and overheating are all ignored, so the numbers
+//   are comparable between fits rather than what the pilot will actually see in game.
+// - Damage is the best-damage charge in cargo that fits the weapon's charge size.
+// - Damage modifiers apply to every weapon and drone on the fit, with stacking penalties.
+// - Low slot resist modules are treated as armor modules, mid slot ones as shield modules.
+// - EHP is against an even (omni) damage profile.
+//
+// Attributes come from the SDE through TypeDB, which caches every type it loads for the
+// lifetime of the process, so repeated checks don't touch the database. shrink-sde.sh has to
+// keep every attribute used here.
+use std::collections::HashMap;
+
+use eve_data_core::{Attribute, Category, Fitting, Type, TypeDB, TypeError, TypeID};
+use serde::Serialize;
+
+const MAX_DRONES: i64 = 5;
+
+const ATTR_STRUCTURE_HP: i32 = 9;
+const ATTR_SHIELD_HP: i32 = 263;
+const ATTR_ARMOR_HP: i32 = 265;
+const ATTR_SHIELD_HP_BONUS: i32 = 72;
+const ATTR_ARMOR_HP_BONUS: i32 = 1159;
+
+const ATTR_RATE_OF_FIRE: i32 = 51;
+const ATTR_DAMAGE_MULTIPLIER: i32 = 64;
+const ATTR_SPEED_MULTIPLIER: i32 = 204;
+const ATTR_CHARGE_SIZE: i32 = 128;
+
+// EM, Thermal, Kinetic, Explosive
+const ATTR_DAMAGE: [i32; 4] = [114, 118, 117, 116];
+const ATTR_RESIST_BONUS: [i32; 4] = [984, 987, 986, 985];
+const ATTR_SHIELD_RESONANCE: [i32; 4] = [271, 274, 273, 272];
+const ATTR_ARMOR_RESONANCE: [i32; 4] = [267, 270, 269, 268];
+const ATTR_STRUCTURE_RESONANCE: [i32; 4] = [113, 110, 109, 111];
+
+#[derive(Debug, Serialize)]
+pub struct FitStats {
+    pub dps: f64,
+    pub ehp: f64,
+}
+
+fn attribute(typ: &Type, id: i32) -> Option<f64> {
+    typ.attributes
+        .get(&Attribute::from_id(id))
+        .map(|value| *value as f64)
+}
+
+fn stacking_penalized(mut multipliers: Vec<f64>) -> f64 {
+    // The strongest modifier is applied in full, each following one is penalized more
+    multipliers.sort_by(|a, b| (b - 1.0).abs().partial_cmp(&(a - 1.0).abs()).unwrap());
+    multipliers
+        .into_iter()
+        .enumerate()
+        .fold(1.0, |total, (i, multiplier)| {
+            let penalty = (-(i as f64 / 2.67).powi(2)).exp();
+            total * (1.0 + (multiplier - 1.0) * penalty)
+        })
+}
+
+fn total_damage(typ: &Type) -> f64 {
+    ATTR_DAMAGE
+        .iter()
+        .map(|id| attribute(typ, *id).unwrap_or(0.0))
+        .sum()
+}
+
+fn layer_ehp(hp: f64, resonances: [f64; 4]) -> f64 {
+    let average = resonances.iter().sum::<f64>() / 4.0;
+    if average <= 0.0 {
+        return hp;
+    }
+    hp / average
+}
+
+pub fn estimate(fit: &Fitting) -> Result<FitStats, TypeError> {
+    let mut ids: Vec<TypeID> = vec![fit.hull];
+    ids.extend(fit.modules.keys());
+    ids.extend(fit.cargo.keys());
+    let types: HashMap<TypeID, _> = TypeDB::load_types(&ids)?
+        .into_iter()
+        .filter_map(|(id, typ)| typ.map(|t| (id, t)))
+        .collect();
+
+    let hull = match types.get(&fit.hull) {
+        Some(hull) => hull,
+        None => return Err(TypeError::NothingMatched),
+    };
+
+    let mut shield_hp = attribute(hull, ATTR_SHIELD_HP).unwrap_or(0.0);
+    let mut armor_hp = attribute(hull, ATTR_ARMOR_HP).unwrap_or(0.0);
+    let structure_hp = attribute(hull, ATTR_STRUCTURE_HP).unwrap_or(0.0);
+
+    let mut shield_resists: [Vec<f64>; 4] = Default::default();
+    let mut armor_resists: [Vec<f64>; 4] = Default::default();
+    let mut damage_multipliers = Vec::new();
+    let mut speed_multipliers = Vec::new();
+    let mut weapons = Vec::new();
+    let mut drones = Vec::new();
+
+    for (type_id, count) in &fit.modules {
+        let module = match types.get(type_id) {
+            Some(module) => module,
+            None => continue,
+        };
+
+        if module.category == Category::Drone {
+            drones.push((module, *count));
+            continue;
+        }
+
+        let slot = module.slot();
+        for _ in 0..*count {
+            match slot {
+                Some("low") => {
+                    armor_hp += attribute(module, ATTR_ARMOR_HP_BONUS).unwrap_or(0.0);
+                    for (i, id) in ATTR_RESIST_BONUS.iter().enumerate() {
+                        if let Some(bonus) = attribute(module, *id) {
+                            armor_resists[i].push(1.0 - bonus.abs() / 100.0);
+                        }
+                    }
+                }
+                Some("med") => {
+                    shield_hp += attribute(module, ATTR_SHIELD_HP_BONUS).unwrap_or(0.0);
+                    for (i, id) in ATTR_RESIST_BONUS.iter().enumerate() {
+                        if let Some(bonus) = attribute(module, *id) {
+                            shield_resists[i].push(1.0 - bonus.abs() / 100.0);
+                        }
+                    }
+                }
+                _ => (),
+            };
+        }
+
+        let rate_of_fire = attribute(module, ATTR_RATE_OF_FIRE);
+        let damage_multiplier = attribute(module, ATTR_DAMAGE_MULTIPLIER);
+        match (rate_of_fire, damage_multiplier) {
+            // Weapons have a rate of fire, damage modifiers only modify it
+            (Some(_), Some(_)) if slot == Some("high") => weapons.push((module, *count)),
+            (None, Some(multiplier)) => {
+                for _ in 0..*count {
+                    damage_multipliers.push(multiplier);
+                    if let Some(speed) = attribute(module, ATTR_SPEED_MULTIPLIER) {
+                        speed_multipliers.push(speed);
+                    }
+                }
+            }
+            _ => (),
+        };
+    }
+
+    let damage_bonus = stacking_penalized(damage_multipliers);
+    let speed_bonus = stacking_penalized(speed_multipliers);
+
+    let mut dps = 0.0;
+    for (weapon, count) in weapons {
+        let charge_size = attribute(weapon, ATTR_CHARGE_SIZE);
+        let best_charge = fit
+            .cargo
+            .keys()
+            .filter_map(|id| types.get(id))
+            .filter(|charge| charge.category == Category::Charge)
+            .filter(|charge| attribute(charge, ATTR_CHARGE_SIZE) == charge_size)
+            .map(|charge| total_damage(charge))
+            .fold(0.0, f64::max);
+
+        let rate_of_fire = attribute(weapon, ATTR_RATE_OF_FIRE).unwrap_or(0.0) / 1000.0;
+        if rate_of_fire > 0.0 {
+            let volley = attribute(weapon, ATTR_DAMAGE_MULTIPLIER).unwrap_or(0.0)
+                * best_charge
+                * damage_bonus;
+            dps += volley / (rate_of_fire * speed_bonus) * count as f64;
+        }
+    }
+
+    let mut drones_left = MAX_DRONES;
+    for (drone, count) in drones {
+        let launched = count.min(drones_left);
+        drones_left -= launched;
+
+        let rate_of_fire = attribute(drone, ATTR_RATE_OF_FIRE).unwrap_or(0.0) / 1000.0;
+        if rate_of_fire > 0.0 {
+            let volley =
+                attribute(drone, ATTR_DAMAGE_MULTIPLIER).unwrap_or(0.0) * total_damage(drone);
+            dps += volley / rate_of_fire * launched as f64;
+        }
+    }
+
+    let resonances = |base: [i32; 4], modules: Option<[Vec<f64>; 4]>| -> [f64; 4] {
+        let mut result = [0.0; 4];
+        for (i, id) in base.iter().enumerate() {
+            result[i] = attribute(hull, *id).unwrap_or(1.0);
+        }
+        if let Some(modules) = modules {
+            for (i, multipliers) in modules.iter().enumerate() {
+                result[i] *= stacking_penalized(multipliers.clone());
+            }
+        }
+        result
+    };
+
+    let ehp = layer_ehp(
+        shield_hp,
+        resonances(ATTR_SHIELD_RESONANCE, Some(shield_resists)),
+    ) + layer_ehp(
+        armor_hp,
+        resonances(ATTR_ARMOR_RESONANCE, Some(armor_resists)),
+    ) + layer_ehp(structure_hp, resonances(ATTR_STRUCTURE_RESONANCE, None));
+
+    Ok(FitStats {
+        dps: dps.round(),
+        ehp: ehp.round(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{estimate, layer_ehp, stacking_penalized};
+    use eve_data_core::Fitting;
+
+    #[test]
+    fn test_stacking_penalty() {
+        assert_eq!(stacking_penalized(vec![]), 1.0);
+        assert!((stacking_penalized(vec![1.1]) - 1.1).abs() < 1e-9);
+
+        // The second module is only ~87% effective
+        let two = stacking_penalized(vec![1.1, 1.1]);
+        assert!(two > 1.19 && two < 1.2, "got {}", two);
+
+        // Order of modules doesn't matter
+        assert_eq!(
+            stacking_penalized(vec![0.8, 0.7]),
+            stacking_penalized(vec![0.7, 0.8])
+        );
+    }
+
+    #[test]
+    fn test_layer_ehp() {
+        assert_eq!(layer_ehp(1000.0, [1.0, 1.0, 1.0, 1.0]), 1000.0);
+        assert_eq!(layer_ehp(1000.0, [0.5, 0.5, 0.5, 0.5]), 2000.0);
+    }
+
+    #[test]
+    fn test_estimate() {
+        // Nightmare with four Mega Pulse Laser II and Conflagration L in cargo
+        let stats = estimate(&Fitting::from_dna("17736:3057;4:12816;2::").unwrap()).unwrap();
+        assert!(stats.dps > 0.0, "got {:?}", stats);
+        assert!(stats.ehp > 0.0, "got {:?}", stats);
+
+        // Lasers without crystals don't shoot, the tank stays the same
+        let unloaded = estimate(&Fitting::from_dna("17736:3057;4::").unwrap()).unwrap();
+        assert_eq!(unloaded.dps, 0.0);
+        assert_eq!(unloaded.ehp, stats.ehp);
+    }
+}
@@ -0,0 +1,63 @@
+// Lets clients pin the shape of API responses, so the backend and the frontend don't have to
+// be deployed in lockstep. Sent as an Accept-Version header or a ?v= query parameter.
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ApiVersion {
+    // Bans as they were first released: id, entity, issued_at, issued_by, public_reason,
+    // reason, revoked_at and revoked_by
+    V2,
+    // Everything, new fields are added here
+    V3,
+}
+
+impl ApiVersion {
+    // Existing clients already read the newer fields, so leaving the version out gets the latest
+    pub const LATEST: ApiVersion = ApiVersion::V3;
+
+    pub fn parse(input: &str) -> Option<ApiVersion> {
+        match input.trim().trim_start_matches('v') {
+            "2" => Some(ApiVersion::V2),
+            "3" => Some(ApiVersion::V3),
+            _ => None,
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ApiVersion {
+    type Error = String;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let requested = match req.query_value::<&str>("v") {
+            Some(Ok(version)) => Some(version),
+            _ => req.headers().get_one("Accept-Version"),
+        };
+
+        match requested {
+            None => Outcome::Success(ApiVersion::LATEST),
+            Some(version) => match ApiVersion::parse(version) {
+                Some(version) => Outcome::Success(version),
+                None => Outcome::Failure((
+                    Status::BadRequest,
+                    format!("Unsupported API version {}", version),
+                )),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ApiVersion;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(ApiVersion::parse("2"), Some(ApiVersion::V2));
+        assert_eq!(ApiVersion::parse("v3"), Some(ApiVersion::V3));
+        assert_eq!(ApiVersion::parse(" 3 "), Some(ApiVersion::V3));
+        assert_eq!(ApiVersion::parse("4"), None);
+        assert_eq!(ApiVersion::parse(""), None);
+    }
+}
@@ -0,0 +1,31 @@
+// The address a request came from, for limits per client. X-Real-IP is only believed when the
+// connection comes from one of app.trusted_proxies, anyone else could just make it up.
+use std::net::IpAddr;
+
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+
+pub struct ClientIp(pub IpAddr);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ClientIp {
+    type Error = &'static str;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let app = req
+            .guard::<&rocket::State<crate::app::Application>>()
+            .await
+            .unwrap();
+
+        let remote = match req.remote() {
+            Some(remote) => remote.ip(),
+            None => return Outcome::Failure((Status::BadRequest, "Unknown client address")),
+        };
+        match req.real_ip() {
+            Some(real_ip) if app.config.app.trusted_proxies.contains(&remote) => {
+                Outcome::Success(ClientIp(real_ip))
+            }
+            _ => Outcome::Success(ClientIp(remote)),
+        }
+    }
+}
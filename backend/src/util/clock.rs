@@ -0,0 +1,33 @@
+use chrono::{DateTime, Utc};
+
+// Where the current time comes from, so time dependent logic can be tested without sleeping
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+// Always reports the same instant
+#[cfg(test)]
+pub struct FixedClock(pub DateTime<Utc>);
+
+#[cfg(test)]
+impl FixedClock {
+    pub fn at(timestamp: i64) -> FixedClock {
+        use chrono::TimeZone;
+        FixedClock(Utc.timestamp(timestamp, 0))
+    }
+}
+
+#[cfg(test)]
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.0
+    }
+}
@@ -0,0 +1,85 @@
+// Conditional GETs. The handler works out an ETag for what it would send, and answers
+// 304 Not Modified instead if the client already has that version.
+use rocket::{
+    http::{Header, Status},
+    request::{FromRequest, Outcome, Request},
+    response::{self, Responder},
+    Response,
+};
+
+// The If-None-Match header, if any
+pub struct IfNoneMatch(Option<String>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for IfNoneMatch {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(IfNoneMatch(
+            req.headers().get_one("If-None-Match").map(str::to_string),
+        ))
+    }
+}
+
+impl IfNoneMatch {
+    // Weak tags are compared like strong ones, which is what If-None-Match asks for
+    pub fn matches(&self, etag: &str) -> bool {
+        match &self.0 {
+            None => false,
+            Some(header) => header
+                .split(',')
+                .map(|tag| tag.trim())
+                .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag),
+        }
+    }
+}
+
+pub struct Tagged<R> {
+    inner: Option<R>,
+    etag: Option<String>,
+}
+
+impl<R> Tagged<R> {
+    // Sent as is, with the ETag if there is one
+    pub fn fresh(inner: R, etag: Option<String>) -> Tagged<R> {
+        Tagged {
+            inner: Some(inner),
+            etag,
+        }
+    }
+
+    pub fn not_modified(etag: String) -> Tagged<R> {
+        Tagged {
+            inner: None,
+            etag: Some(etag),
+        }
+    }
+}
+
+impl<'r, 'o: 'r, R: Responder<'r, 'o>> Responder<'r, 'o> for Tagged<R> {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'o> {
+        let mut response = match self.inner {
+            Some(inner) => inner.respond_to(req)?,
+            None => Response::build().status(Status::NotModified).finalize(),
+        };
+        if let Some(etag) = self.etag {
+            response.set_header(Header::new("ETag", etag));
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IfNoneMatch;
+
+    #[test]
+    fn test_matches() {
+        let etag = "\"abc\"";
+        assert!(!IfNoneMatch(None).matches(etag));
+        assert!(IfNoneMatch(Some("\"abc\"".to_string())).matches(etag));
+        assert!(IfNoneMatch(Some("\"x\", W/\"abc\"".to_string())).matches(etag));
+        assert!(IfNoneMatch(Some("*".to_string())).matches(etag));
+        assert!(!IfNoneMatch(Some("\"abcd\"".to_string())).matches(etag));
+    }
+}
@@ -0,0 +1,167 @@
+// The paging, sorting and search parameters list endpoints share: ?limit=, ?offset=, ?sort=,
+// ?order= and ?search=. Each endpoint picks the columns it can be sorted by, see
+// ListParams::sort_by. Take the guard as Result<ListParams, String> and turn the error into
+// Madness::BadRequest, a failed guard only gets the bare 400 of the catcher.
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+
+use crate::util::madness::Madness;
+
+// Larger limits are clamped down to this
+pub const MAX_LIMIT: i64 = 500;
+
+const SEARCH_MAX_LENGTH: usize = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct ListParams {
+    // Between 1 and MAX_LIMIT, None when the client wants everything
+    pub limit: Option<i64>,
+    // Never negative
+    pub offset: Option<i64>,
+    pub sort: Option<String>,
+    pub order: Option<SortOrder>,
+    // Trimmed, None if blank
+    pub search: Option<String>,
+}
+
+impl ListParams {
+    pub fn parse(
+        limit: Option<&str>,
+        offset: Option<&str>,
+        sort: Option<&str>,
+        order: Option<&str>,
+        search: Option<&str>,
+    ) -> Result<ListParams, String> {
+        fn number(name: &str, input: Option<&str>) -> Result<Option<i64>, String> {
+            match input {
+                None => Ok(None),
+                Some(input) => match input.trim().parse() {
+                    Ok(number) => Ok(Some(number)),
+                    Err(_) => Err(format!("{} has to be a number", name)),
+                },
+            }
+        }
+
+        let order = match order.map(|order| order.trim().to_lowercase()).as_deref() {
+            None => None,
+            Some("asc") => Some(SortOrder::Asc),
+            Some("desc") => Some(SortOrder::Desc),
+            Some(order) => return Err(format!("Unknown order {}, use asc or desc", order)),
+        };
+        let search = search
+            .map(|search| search.trim().to_string())
+            .filter(|search| !search.is_empty());
+        if let Some(search) = &search {
+            if search.chars().count() > SEARCH_MAX_LENGTH {
+                return Err(format!(
+                    "search cannot be longer than {} characters",
+                    SEARCH_MAX_LENGTH
+                ));
+            }
+        }
+
+        Ok(ListParams {
+            limit: number("limit", limit)?.map(|limit| limit.clamp(1, MAX_LIMIT)),
+            offset: number("offset", offset)?.map(|offset| offset.max(0)),
+            sort: sort
+                .map(|sort| sort.trim().to_lowercase())
+                .filter(|sort| !sort.is_empty()),
+            order,
+            search,
+        })
+    }
+
+    // The sort column, if one was asked for and the endpoint supports it
+    pub fn sort_by(&self, allowed: &[&'static str]) -> Result<Option<&'static str>, Madness> {
+        match &self.sort {
+            None => Ok(None),
+            Some(sort) => match allowed.iter().find(|column| **column == sort.as_str()) {
+                Some(column) => Ok(Some(*column)),
+                None => Err(Madness::BadRequest(format!(
+                    "Cannot sort by {}, use one of: {}",
+                    sort,
+                    allowed.join(", ")
+                ))),
+            },
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ListParams {
+    type Error = String;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let value = |name: &str| match req.query_value::<&str>(name) {
+            Some(Ok(value)) => Some(value),
+            _ => None,
+        };
+
+        match ListParams::parse(
+            value("limit"),
+            value("offset"),
+            value("sort"),
+            value("order"),
+            value("search"),
+        ) {
+            Ok(params) => Outcome::Success(params),
+            Err(error) => Outcome::Failure((Status::BadRequest, error)),
+        }
+    }
+}
+
+// Escapes the wildcards of a LIKE pattern, so user input only matches literally
+pub fn escape_like(input: &str) -> String {
+    input
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape_like, ListParams, SortOrder, MAX_LIMIT};
+
+    #[test]
+    fn test_clamping() {
+        let params = ListParams::parse(Some("100000"), Some("-5"), None, None, None).unwrap();
+        assert_eq!(params.limit, Some(MAX_LIMIT));
+        assert_eq!(params.offset, Some(0));
+        let params = ListParams::parse(Some("0"), Some("20"), None, None, None).unwrap();
+        assert_eq!(params.limit, Some(1));
+        assert_eq!(params.offset, Some(20));
+
+        assert_eq!(
+            ListParams::parse(None, None, None, None, None).unwrap(),
+            ListParams::default()
+        );
+        assert!(ListParams::parse(Some("ten"), None, None, None, None).is_err());
+    }
+
+    #[test]
+    fn test_sort_and_order() {
+        let params = ListParams::parse(None, None, Some("Issued_At"), Some("ASC"), None).unwrap();
+        assert_eq!(params.order, Some(SortOrder::Asc));
+        assert_eq!(params.sort_by(&["issued_at"]).unwrap(), Some("issued_at"));
+        assert!(params.sort_by(&["entity_name"]).is_err());
+
+        assert!(ListParams::parse(None, None, None, Some("sideways"), None).is_err());
+        let params = ListParams::parse(None, None, None, None, None).unwrap();
+        assert_eq!(params.sort_by(&["issued_at"]).unwrap(), None);
+    }
+
+    #[test]
+    fn test_search() {
+        let params = ListParams::parse(None, None, None, None, Some("  ")).unwrap();
+        assert_eq!(params.search, None);
+        let long = "x".repeat(101);
+        assert!(ListParams::parse(None, None, None, None, Some(&long)).is_err());
+        assert_eq!(escape_like("100%_a\\b"), "100\\%\\_a\\\\b");
+    }
+}
@@ -6,6 +6,7 @@ use zxcvbn::ZxcvbnError;
 
 use crate::core::esi::ESIError;
 use crate::core::sse::SSEError;
+use crate::core::webhook::WebhookError;
 use crate::{core::auth::AuthorizationError, data::skills::SkillsError};
 
 use eve_data_core::{FitError, TypeError};
@@ -16,6 +17,8 @@ pub enum Madness {
     DatabaseError(#[from] sqlx::Error),
     #[error("SSE error: {0}")]
     SSEError(#[from] SSEError),
+    #[error("webhook error: {0}")]
+    WebhookError(#[from] WebhookError),
     #[error("ESI error: {0}")]
     ESIError(#[from] ESIError),
     #[error("type error: {0}")]
@@ -25,6 +28,9 @@ pub enum Madness {
     #[error("general error: {0}")]
     GeneralError(#[from] ZxcvbnError),
 
+    // The request is well formed, but breaks a rule the client can't fix by correcting its input
+    #[error("{0}")]
+    UnprocessableEntity(String),
     #[error("{0}")]
     BadRequest(String),
     #[error("access denied")]
@@ -33,6 +39,12 @@ pub enum Madness {
     Forbidden(String),
     #[error("{0}")]
     NotFound(&'static str),
+    #[error("{0}")]
+    NotFoundWithDetails(&'static str, serde_json::Value),
+    #[error("{0}")]
+    Conflict(String),
+    #[error("{0}")]
+    TooManyRequests(String),
 }
 
 impl From<AuthorizationError> for Madness {
@@ -62,15 +74,23 @@ impl<'r> rocket::response::Responder<'r, 'static> for Madness {
 
             Self::DatabaseError(_)
             | Self::SSEError(_)
+            | Self::WebhookError(_)
             | Self::GeneralError(_)
             | Self::ESIError(
-                ESIError::HTTPError(_) | ESIError::DatabaseError(_) | ESIError::Status(_),
+                ESIError::HTTPError(_)
+                | ESIError::DatabaseError(_)
+                | ESIError::Status(_)
+                | ESIError::InvalidResponse(_),
             ) => Status::InternalServerError,
 
             Self::ESIError(ESIError::WithMessage(code, _body)) => Status { code: *code },
+            Self::ESIError(ESIError::ErrorLimited(_)) => Status::ServiceUnavailable,
 
-            Self::NotFound(_) => Status::NotFound,
+            Self::NotFound(_) | Self::NotFoundWithDetails(..) => Status::NotFound,
             Self::Forbidden(_) => Status::Forbidden,
+            Self::Conflict(_) => Status::Conflict,
+            Self::UnprocessableEntity(_) => Status::UnprocessableEntity,
+            Self::TooManyRequests(_) => Status::TooManyRequests,
 
             Self::FitError(_) | Self::BadRequest(_) | Self::TypeError(_) => Status::BadRequest,
         };
@@ -80,10 +100,25 @@ impl<'r> rocket::response::Responder<'r, 'static> for Madness {
             error!("Request error: {}: {:#?}", self, self);
         }
 
+        // Some errors carry data the frontend can act on, send those as JSON
+        if let Self::NotFoundWithDetails(error, details) = &self {
+            let body = serde_json::json!({ "error": error, "details": details }).to_string();
+            return Ok(Response::build()
+                .sized_body(body.len(), Cursor::new(body))
+                .header(rocket::http::ContentType::JSON)
+                .status(status)
+                .finalize());
+        }
+
         let error = format!("{}", self);
-        Ok(Response::build()
+        let mut response = Response::build();
+        response
             .sized_body(error.len(), Cursor::new(error))
-            .status(status)
-            .finalize())
+            .status(status);
+        // Tells clients not to retry before ESI lets us make requests again
+        if let Self::ESIError(ESIError::ErrorLimited(Some(reset))) = &self {
+            response.header(rocket::http::Header::new("Retry-After", reset.to_string()));
+        }
+        Ok(response.finalize())
     }
 }
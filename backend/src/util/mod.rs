@@ -1,2 +1,11 @@
+pub mod api_version;
+pub mod client_ip;
+pub mod clock;
+pub mod etag;
+pub mod list_params;
 pub mod madness;
+#[cfg(test)]
+pub mod testapp;
+#[cfg(test)]
+pub mod testdb;
 pub mod types;
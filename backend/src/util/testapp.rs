@@ -0,0 +1,241 @@
+// The routes running against a throwaway database, see util::testdb, and a fake ESI that only
+// knows the entities a test gives it.
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use rocket::http::Cookie;
+use rocket::local::asynchronous::{Client, LocalRequest, LocalResponse};
+use serde::de::DeserializeOwned;
+
+use crate::{
+    app::Application,
+    config::Config,
+    core::{
+        affiliation::AffiliationService,
+        esi::{CharacterAffiliation, ESIError, EsiLookup, ResolvedEntity},
+    },
+    util::testdb::TestDatabase,
+};
+
+// Nothing listens here, so SSE events fail straight away. They're only logged.
+const SSE_URL: &str = "http://127.0.0.1:9";
+
+// Rocket 0.5.0-rc.1's into_json never returns: it holds its channel open while the JSON reader
+// waits for the end of the body. Reading the body as a string first doesn't hit that.
+#[rocket::async_trait]
+pub trait ReadJson {
+    async fn json<T: DeserializeOwned>(self) -> Option<T>;
+}
+
+#[rocket::async_trait]
+impl ReadJson for LocalResponse<'_> {
+    async fn json<T: DeserializeOwned>(self) -> Option<T> {
+        serde_json::from_str(&self.into_string().await?).ok()
+    }
+}
+
+pub struct FakeEsi {
+    // Name and category by ID
+    entities: HashMap<i64, (&'static str, &'static str)>,
+    // Corporation and alliance by character ID
+    affiliations: HashMap<i64, (i64, Option<i64>)>,
+    // Corporations that report a date_dissolved
+    dissolved: HashMap<i64, &'static str>,
+}
+
+impl FakeEsi {
+    pub fn new(entities: &[(i64, &'static str, &'static str)]) -> FakeEsi {
+        FakeEsi {
+            entities: entities
+                .iter()
+                .map(|(id, name, category)| (*id, (*name, *category)))
+                .collect(),
+            affiliations: HashMap::new(),
+            dissolved: HashMap::new(),
+        }
+    }
+
+    pub fn with_affiliation(
+        mut self,
+        character_id: i64,
+        corporation_id: i64,
+        alliance_id: Option<i64>,
+    ) -> FakeEsi {
+        self.affiliations
+            .insert(character_id, (corporation_id, alliance_id));
+        self
+    }
+
+    pub fn with_dissolved(mut self, corporation_id: i64, date_dissolved: &'static str) -> FakeEsi {
+        self.dissolved.insert(corporation_id, date_dissolved);
+        self
+    }
+
+    fn not_found() -> ESIError {
+        ESIError::WithMessage(404, "Not found".to_string())
+    }
+}
+
+#[rocket::async_trait]
+impl EsiLookup for FakeEsi {
+    // Only /latest/{category}s/{id}, with just the name and date_dissolved
+    async fn get_value(&self, path: &str) -> Result<serde_json::Value, ESIError> {
+        let mut parts = path.trim_matches('/').split('/').skip(1);
+        let (category, id) = match (parts.next(), parts.next().and_then(|id| id.parse().ok())) {
+            (Some(category), Some(id)) => (category, id),
+            _ => return Err(Self::not_found()),
+        };
+
+        match self.entities.get(&id) {
+            Some((name, entity_category))
+                if format!("{}s", entity_category.to_lowercase()) == category =>
+            {
+                Ok(match self.dissolved.get(&id) {
+                    Some(date) => serde_json::json!({ "name": name, "date_dissolved": date }),
+                    None => serde_json::json!({ "name": name }),
+                })
+            }
+            _ => Err(Self::not_found()),
+        }
+    }
+
+    // Like ESI, one unknown ID fails the whole request
+    async fn resolve_names(&self, ids: &[i64]) -> Result<Vec<ResolvedEntity>, ESIError> {
+        ids.iter()
+            .map(|id| match self.entities.get(id) {
+                Some((name, category)) => Ok(ResolvedEntity {
+                    id: *id,
+                    name: name.to_string(),
+                    category: *category,
+                }),
+                None => Err(Self::not_found()),
+            })
+            .collect()
+    }
+
+    async fn resolve_ids(&self, names: &[&str]) -> Result<Vec<ResolvedEntity>, ESIError> {
+        Ok(self
+            .entities
+            .iter()
+            .filter(|(_id, (name, _category))| {
+                names.iter().any(|wanted| wanted.eq_ignore_ascii_case(name))
+            })
+            .map(|(id, (name, category))| ResolvedEntity {
+                id: *id,
+                name: name.to_string(),
+                category: *category,
+            })
+            .collect())
+    }
+
+    // Like ESI, one unknown ID fails the whole request
+    async fn character_affiliations(
+        &self,
+        character_ids: &[i64],
+    ) -> Result<Vec<CharacterAffiliation>, ESIError> {
+        character_ids
+            .iter()
+            .map(|id| match self.affiliations.get(id) {
+                Some((corporation_id, alliance_id)) => Ok(CharacterAffiliation {
+                    character_id: *id,
+                    corporation_id: *corporation_id,
+                    alliance_id: *alliance_id,
+                }),
+                None => Err(Self::not_found()),
+            })
+            .collect()
+    }
+}
+
+pub struct TestApp {
+    pub client: Client,
+    db: TestDatabase,
+}
+
+impl TestApp {
+    // None if tests shouldn't touch Postgres
+    pub async fn new(esi: FakeEsi) -> Option<TestApp> {
+        TestApp::with_config(esi, |_config| ()).await
+    }
+
+    // Starts from config.example.toml
+    pub async fn with_config(esi: FakeEsi, configure: impl FnOnce(&mut Config)) -> Option<TestApp> {
+        let db = TestDatabase::fresh().await?;
+        Some(TestApp::with_database(db, esi, configure).await)
+    }
+
+    // On a database the test set up itself, e.g. a migrated legacy one
+    pub async fn with_database(
+        db: TestDatabase,
+        esi: FakeEsi,
+        configure: impl FnOnce(&mut Config),
+    ) -> TestApp {
+        let mut config: Config = toml::from_str(include_str!("../../config.example.toml")).unwrap();
+        config.sse.url = SSE_URL.to_string();
+        configure(&mut config);
+
+        let database = Arc::new(db.pool().clone());
+        let esi: Arc<dyn EsiLookup> = Arc::new(esi);
+        let mut application = crate::app::new(database.clone(), config);
+        application.affiliation_service = AffiliationService::new(database, esi.clone());
+        application.esi_lookup = esi;
+
+        let client = Client::tracked(
+            rocket::build()
+                .mount("/", crate::routes::routes())
+                .manage(application),
+        )
+        .await
+        .expect("Could not start the test app");
+
+        TestApp { client, db }
+    }
+
+    pub fn db(&self) -> &crate::DB {
+        self.db.pool()
+    }
+
+    // A character that can log in, with a waitlist role if one is given
+    pub async fn add_character(&self, id: i64, name: &str, role: Option<&str>) {
+        sqlx::query!("INSERT INTO character (id, name) VALUES ($1, $2)", id, name)
+            .execute(self.db())
+            .await
+            .unwrap();
+
+        if let Some(role) = role {
+            sqlx::query!(
+                "INSERT INTO admin (character_id, role, granted_at, granted_by_id) VALUES ($1, $2, 0, $1)",
+                id,
+                role
+            )
+            .execute(self.db())
+            .await
+            .unwrap();
+        }
+    }
+
+    // Sends the request with the login cookie of the account
+    pub fn login<'c>(&self, request: LocalRequest<'c>, account_id: i64) -> LocalRequest<'c> {
+        let app = self.client.rocket().state::<Application>().unwrap();
+        let token = crate::core::auth::create_cookie(app, account_id).0;
+        request.cookie(Cookie::new("authToken", token))
+    }
+
+    // Like login, as if the account went through SSO at the given time
+    pub fn login_at<'c>(
+        &self,
+        request: LocalRequest<'c>,
+        account_id: i64,
+        logged_in_at: i64,
+    ) -> LocalRequest<'c> {
+        let app = self.client.rocket().state::<Application>().unwrap();
+        let token = crate::core::auth::create_cookie_at(app, account_id, logged_in_at).0;
+        request.cookie(Cookie::new("authToken", token))
+    }
+
+    // Borrows so tests can still hold responses from the client. The app shares the test
+    // database's pool, so closing it releases the app's connections too.
+    pub async fn destroy(&self) {
+        self.db.destroy().await;
+    }
+}
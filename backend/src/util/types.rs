@@ -1,5 +1,9 @@
+use chrono::{SecondsFormat, TimeZone};
+use chrono_tz::Tz;
 use eve_data_core::TypeID;
-use serde::{Deserialize, Serialize};
+use serde::{ser::SerializeStruct, Deserialize, Serialize};
+
+use crate::util::api_version::ApiVersion;
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct Character {
@@ -40,22 +44,455 @@ pub struct Entity {
     pub category: String,
 }
 
+// The kinds of entity that can be banned and looked up on ESI. Name pattern bans aren't
+// entities, see core::ban::NAME_PATTERN.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EntityType {
+    // Account bans use the ID of the main character
+    Account,
+    Character,
+    Corporation,
+    Alliance,
+}
+
+impl EntityType {
+    // Parses the category stored with bans, e.g. "Corporation"
+    pub fn parse(category: &str) -> Option<EntityType> {
+        match category {
+            "Account" => Some(EntityType::Account),
+            "Character" => Some(EntityType::Character),
+            "Corporation" => Some(EntityType::Corporation),
+            "Alliance" => Some(EntityType::Alliance),
+            _ => None,
+        }
+    }
+
+    // Where ESI has the public information of the entity
+    pub fn esi_path(&self, id: i64) -> String {
+        match self {
+            EntityType::Account | EntityType::Character => format!("/latest/characters/{}", id),
+            EntityType::Corporation => format!("/latest/corporations/{}", id),
+            EntityType::Alliance => format!("/latest/alliances/{}", id),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize)]
 pub struct  System {
     pub id: i64,
     pub name: String
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize)]
 pub struct Ban {
     pub id: Option<i64>,
     pub entity: Option<Entity>,
     pub issued_at: Option<i64>,
     pub issued_by: Option<Character>,
+    // The FC an integration created the ban for, issued_by is then the integration's account
+    #[serde(skip_deserializing)]
+    pub on_behalf_of: Option<Character>,
     pub public_reason: Option<String>,
     pub reason: String,
+    // Structured category, e.g. "RMT", falls back to a leading [Tag] in the reason
+    #[serde(default)]
+    pub category: Option<String>,
     pub revoked_at: Option<i64>,
     pub revoked_by: Option<Character>,
+    #[serde(default)]
+    pub silent: bool,
+    // Waiting for a second FC, pending bans aren't enforced
+    #[serde(skip_deserializing)]
+    pub pending_approval: bool,
+    // The entity name is a placeholder, ESI was down when the ban was created
+    #[serde(skip_deserializing)]
+    pub name_pending: bool,
+    // The category ESI reported for the entity when the ban was created
+    #[serde(skip_deserializing)]
+    pub esi_category: Option<String>,
+    // When the banned character confirmed seeing the public reason
+    #[serde(skip_deserializing)]
+    pub acknowledged_at: Option<i64>,
+    // The earlier ban on the same entity this one corrects, usually revoked along with it
+    #[serde(default)]
+    pub supersedes_ban_id: Option<i64>,
+    // Earlier versions of the reason, only loaded for a single ban
+    #[serde(skip_deserializing)]
+    pub reason_history: Vec<ReasonEdit>,
+    // Free-form labels, lowercase and without duplicates, see core::ban::normalize_tags
+    #[serde(default)]
+    pub tags: Vec<String>,
+    // Operational heads-up for other FCs, unlike the reason it isn't used in appeals
+    #[serde(default)]
+    pub fc_note: Option<String>,
+    // A scheduled ban is only enforced from this time on
+    #[serde(default)]
+    pub starts_at: Option<i64>,
+    // The admin who created the ban even though the entity already had an active one
+    #[serde(skip_deserializing)]
+    pub duplicate_override_by: Option<Character>,
+}
+
+// What happened to each item of a bulk request. An item can fail on its own, e.g. a ban that
+// doesn't exist, without failing the others. Only problems with the whole request are HTTP errors.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct BulkResult<T> {
+    pub succeeded: Vec<T>,
+    pub failed: Vec<BulkFailure<T>>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct BulkFailure<T> {
+    pub id: T,
+    pub error: String,
+}
+
+impl<T> Default for BulkResult<T> {
+    fn default() -> Self {
+        BulkResult {
+            succeeded: Vec::new(),
+            failed: Vec::new(),
+        }
+    }
+}
+
+impl<T> BulkResult<T> {
+    pub fn succeed(&mut self, id: T) {
+        self.succeeded.push(id);
+    }
+
+    pub fn fail(&mut self, id: T, error: impl Into<String>) {
+        self.failed.push(BulkFailure {
+            id,
+            error: error.into(),
+        });
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BanStatus {
+    // Waiting for a second FC
+    Pending,
+    // Approved, but starts_at is still to come
+    Scheduled,
+    Active,
+    // Revoked or expired
+    Ended,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ReasonEdit {
+    // The reason as it was before the edit
+    pub reason: String,
+    pub edited_at: i64,
+    pub edited_by: Character,
+}
+
+// A field that differs between two bans, see Ban::differences
+#[derive(Debug, PartialEq, Serialize)]
+pub struct BanDifference {
+    pub field: &'static str,
+    pub a: serde_json::Value,
+    pub b: serde_json::Value,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct BanVisibility {
+    pub reason: bool,
+    pub public_reason: bool,
+    // Only FCs that manage bans, never anyone outside
+    pub fc_note: bool,
+}
+
+// A ban revoked this soon after it was issued, by the same FC, was most likely a mistake
+const REVOKED_IMMEDIATELY_WINDOW: i64 = 60 * 5;
+
+impl Ban {
+    // Blanks out the reasons the viewer isn't allowed to see
+    pub fn redact(mut self, visibility: BanVisibility) -> Ban {
+        if !visibility.reason {
+            self.reason = String::new();
+            self.reason_history = Vec::new();
+            // Usually parsed out of the reason, so it's just as internal
+            self.category = None;
+            self.tags = Vec::new();
+            self.duplicate_override_by = None;
+        }
+        if !visibility.public_reason {
+            self.public_reason = None;
+        }
+        if !visibility.fc_note {
+            self.fc_note = None;
+        }
+        self
+    }
+
+    // The entity isn't the type the FC said it was
+    pub fn type_mismatch(&self) -> bool {
+        match (&self.entity, &self.esi_category) {
+            (Some(entity), Some(esi_category)) => entity.category != *esi_category,
+            _ => false,
+        }
+    }
+
+    // Whether the ban was in effect at the time. Edits don't change issued_at, so it's when
+    // the ban was created. Pending bans only count once approved, and the approval time isn't
+    // kept.
+    pub fn active_at(&self, timestamp: i64) -> bool {
+        !self.pending_approval
+            && self
+                .issued_at
+                .map_or(false, |issued_at| issued_at <= timestamp)
+            && self
+                .starts_at
+                .map_or(true, |starts_at| starts_at <= timestamp)
+            && self
+                .revoked_at
+                .map_or(true, |revoked_at| revoked_at > timestamp)
+    }
+
+    pub fn status(&self, now: i64) -> BanStatus {
+        if self
+            .revoked_at
+            .map_or(false, |revoked_at| revoked_at <= now)
+        {
+            BanStatus::Ended
+        } else if self.pending_approval {
+            BanStatus::Pending
+        } else if self.starts_at.map_or(false, |starts_at| starts_at > now) {
+            BanStatus::Scheduled
+        } else {
+            BanStatus::Active
+        }
+    }
+
+    // Ran out on its own within the last `grace` seconds, revoked bans don't count
+    pub fn recently_expired(&self, now: i64, grace: i64) -> bool {
+        self.revoked_by.is_none()
+            && self.revoked_at.map_or(false, |revoked_at| {
+                revoked_at <= now && revoked_at > now - grace
+            })
+    }
+
+    // The fields appeal reviews compare, the category stands in for a severity. Redact both bans
+    // first, so hidden reasons don't show up as a difference.
+    pub fn differences(&self, other: &Ban) -> Vec<BanDifference> {
+        use serde_json::json;
+
+        vec![
+            ("reason", json!(self.reason), json!(other.reason)),
+            (
+                "public_reason",
+                json!(self.public_reason),
+                json!(other.public_reason),
+            ),
+            ("category", json!(self.category), json!(other.category)),
+            (
+                "revoked_at",
+                json!(self.revoked_at),
+                json!(other.revoked_at),
+            ),
+            ("issued_by", json!(self.issued_by), json!(other.issued_by)),
+        ]
+        .into_iter()
+        .filter(|(_field, a, b)| a != b)
+        .map(|(field, a, b)| BanDifference { field, a, b })
+        .collect()
+    }
+
+    pub fn revoked_immediately(&self) -> bool {
+        match (
+            self.issued_at,
+            self.issued_by.as_ref(),
+            self.revoked_at,
+            self.revoked_by.as_ref(),
+        ) {
+            (Some(issued_at), Some(issued_by), Some(revoked_at), Some(revoked_by)) => {
+                issued_by.id == revoked_by.id
+                    && revoked_at - issued_at <= REVOKED_IMMEDIATELY_WINDOW
+            }
+            _ => false,
+        }
+    }
+}
+
+pub fn iso_timestamp(timestamp: Option<i64>, tz: Tz) -> Option<String> {
+    timestamp.map(|ts| {
+        tz.timestamp(ts, 0)
+            .to_rfc3339_opts(SecondsFormat::Secs, true)
+    })
+}
+
+// "3 days", "1 hour", rounded down to the largest unit
+fn humanize_duration(seconds: i64) -> String {
+    const UNITS: [(i64, &str); 5] = [
+        (60 * 60 * 24 * 365, "year"),
+        (60 * 60 * 24 * 30, "month"),
+        (60 * 60 * 24, "day"),
+        (60 * 60, "hour"),
+        (60, "minute"),
+    ];
+
+    let seconds = seconds.abs();
+    for (size, unit) in UNITS.iter() {
+        let count = seconds / size;
+        if count > 0 {
+            return match count {
+                1 => format!("1 {}", unit),
+                _ => format!("{} {}s", count, unit),
+            };
+        }
+    }
+    "less than a minute".to_string()
+}
+
+fn relative_timestamp(timestamp: Option<i64>, now: i64, future_prefix: &str) -> Option<String> {
+    timestamp.map(|ts| match ts > now {
+        true => format!("{} {}", future_prefix, humanize_duration(ts - now)),
+        false => format!("{} ago", humanize_duration(now - ts)),
+    })
+}
+
+impl Ban {
+    // Written out by hand so the ISO-8601 copies of the timestamps don't have to be stored on the struct.
+    // The status is only added when the time it's at is given, the relative times when a
+    // reference time is.
+    fn serialize_in<S>(
+        &self,
+        tz: Tz,
+        status_at: Option<i64>,
+        now: Option<i64>,
+        version: ApiVersion,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        if version == ApiVersion::V2 {
+            let mut state = serializer.serialize_struct("Ban", 8)?;
+            state.serialize_field("id", &self.id)?;
+            state.serialize_field("entity", &self.entity)?;
+            state.serialize_field("issued_at", &self.issued_at)?;
+            state.serialize_field("issued_by", &self.issued_by)?;
+            state.serialize_field("public_reason", &self.public_reason)?;
+            state.serialize_field("reason", &self.reason)?;
+            state.serialize_field("revoked_at", &self.revoked_at)?;
+            state.serialize_field("revoked_by", &self.revoked_by)?;
+            return state.end();
+        }
+
+        let mut state = serializer.serialize_struct("Ban", 30)?;
+        state.serialize_field("id", &self.id)?;
+        state.serialize_field("entity", &self.entity)?;
+        state.serialize_field("issued_at", &self.issued_at)?;
+        state.serialize_field("issued_at_iso", &iso_timestamp(self.issued_at, tz))?;
+        state.serialize_field("issued_by", &self.issued_by)?;
+        state.serialize_field("on_behalf_of", &self.on_behalf_of)?;
+        state.serialize_field("public_reason", &self.public_reason)?;
+        state.serialize_field("reason", &self.reason)?;
+        state.serialize_field("reason_history", &self.reason_history)?;
+        state.serialize_field("category", &self.category)?;
+        state.serialize_field("tags", &self.tags)?;
+        state.serialize_field("revoked_at", &self.revoked_at)?;
+        state.serialize_field("revoked_at_iso", &iso_timestamp(self.revoked_at, tz))?;
+        state.serialize_field("revoked_by", &self.revoked_by)?;
+        state.serialize_field("revoked_immediately", &self.revoked_immediately())?;
+        state.serialize_field("silent", &self.silent)?;
+        state.serialize_field("pending_approval", &self.pending_approval)?;
+        state.serialize_field("name_pending", &self.name_pending)?;
+        state.serialize_field("esi_category", &self.esi_category)?;
+        state.serialize_field("type_mismatch", &self.type_mismatch())?;
+        state.serialize_field("acknowledged_at", &self.acknowledged_at)?;
+        state.serialize_field(
+            "acknowledged_at_iso",
+            &iso_timestamp(self.acknowledged_at, tz),
+        )?;
+        state.serialize_field("supersedes_ban_id", &self.supersedes_ban_id)?;
+        state.serialize_field("fc_note", &self.fc_note)?;
+        state.serialize_field("starts_at", &self.starts_at)?;
+        state.serialize_field("starts_at_iso", &iso_timestamp(self.starts_at, tz))?;
+        state.serialize_field("duplicate_override_by", &self.duplicate_override_by)?;
+        if let Some(status_at) = status_at {
+            state.serialize_field("status", &self.status(status_at))?;
+        }
+        if let Some(now) = now {
+            state.serialize_field(
+                "issued_at_relative",
+                &relative_timestamp(self.issued_at, now, "in"),
+            )?;
+            state.serialize_field(
+                "revoked_at_relative",
+                &relative_timestamp(self.revoked_at, now, "expires in"),
+            )?;
+        }
+        state.end()
+    }
+
+    // status_at is the time the status is worked out at, from the app clock
+    pub fn in_timezone(self, tz: Tz, status_at: i64) -> LocalBan {
+        LocalBan {
+            ban: self,
+            tz,
+            status_at,
+            now: None,
+            version: ApiVersion::LATEST,
+        }
+    }
+}
+
+impl Serialize for Ban {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.serialize_in(Tz::UTC, None, None, ApiVersion::LATEST, serializer)
+    }
+}
+
+// A ban with its ISO timestamps rendered in the viewer's timezone
+#[derive(Debug)]
+pub struct LocalBan {
+    ban: Ban,
+    tz: Tz,
+    status_at: i64,
+    now: Option<i64>,
+    version: ApiVersion,
+}
+
+impl LocalBan {
+    // Adds "3 days ago" style copies of the timestamps, relative to now
+    pub fn relative_to(mut self, now: Option<i64>) -> LocalBan {
+        self.now = now;
+        self
+    }
+
+    pub fn at_version(mut self, version: ApiVersion) -> LocalBan {
+        self.version = version;
+        self
+    }
+}
+
+impl Serialize for LocalBan {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.ban.serialize_in(
+            self.tz,
+            Some(self.status_at),
+            self.now,
+            self.version,
+            serializer,
+        )
+    }
+}
+
+// Reads an IANA timezone name, anything invalid falls back to UTC
+pub fn parse_timezone(input: Option<&str>) -> Tz {
+    input
+        .and_then(|name| name.parse::<Tz>().ok())
+        .unwrap_or(Tz::UTC)
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -71,3 +508,193 @@ pub struct Corporation {
     pub alliance: Option<Alliance>,
     pub last_updated: Option<i64>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{humanize_duration, relative_timestamp, Ban, BanStatus, BanVisibility, EntityType};
+    use crate::util::api_version::ApiVersion;
+
+    #[test]
+    fn test_esi_path() {
+        assert_eq!(EntityType::Account.esi_path(1), "/latest/characters/1");
+        assert_eq!(EntityType::Character.esi_path(2), "/latest/characters/2");
+        assert_eq!(
+            EntityType::Corporation.esi_path(3),
+            "/latest/corporations/3"
+        );
+        assert_eq!(EntityType::Alliance.esi_path(4), "/latest/alliances/4");
+        assert_eq!(EntityType::parse("Alliance"), Some(EntityType::Alliance));
+        assert_eq!(EntityType::parse("NamePattern"), None);
+    }
+
+    #[test]
+    fn test_active_at() {
+        let ban: Ban = serde_json::from_value(serde_json::json!({
+            "reason": "x",
+            "issued_at": 1000,
+            "revoked_at": 2000,
+        }))
+        .unwrap();
+        assert!(!ban.active_at(999));
+        assert!(ban.active_at(1000));
+        assert!(ban.active_at(1999));
+        assert!(!ban.active_at(2000));
+
+        let permanent = Ban {
+            revoked_at: None,
+            ..ban
+        };
+        assert!(permanent.active_at(1_000_000));
+
+        let scheduled = Ban {
+            starts_at: Some(1500),
+            ..permanent
+        };
+        assert!(!scheduled.active_at(1499));
+        assert!(scheduled.active_at(1500));
+        assert_eq!(scheduled.status(1499), BanStatus::Scheduled);
+        assert_eq!(scheduled.status(1500), BanStatus::Active);
+
+        // The status is as of the time passed in, and left out when there's none
+        let status = |at| {
+            serde_json::to_value(scheduled.clone().in_timezone(chrono_tz::UTC, at)).unwrap()
+                ["status"]
+                .clone()
+        };
+        assert_eq!(status(1499), "scheduled");
+        assert_eq!(status(1500), "active");
+        assert!(serde_json::to_value(&scheduled).unwrap()["status"].is_null());
+    }
+
+    #[test]
+    fn test_recently_expired() {
+        let ban: Ban = serde_json::from_value(serde_json::json!({
+            "reason": "x",
+            "issued_at": 1000,
+            "revoked_at": 2000,
+        }))
+        .unwrap();
+        assert!(!ban.recently_expired(1999, 100));
+        assert!(ban.recently_expired(2000, 100));
+        assert!(ban.recently_expired(2099, 100));
+        assert!(!ban.recently_expired(2100, 100));
+
+        // Revoked by an FC rather than expired
+        let revoked: Ban = serde_json::from_value(serde_json::json!({
+            "reason": "x",
+            "issued_at": 1000,
+            "revoked_at": 2000,
+            "revoked_by": { "id": 1001, "name": "Some FC" },
+        }))
+        .unwrap();
+        assert!(!revoked.recently_expired(2000, 100));
+    }
+
+    #[test]
+    fn test_redact_fc_note() {
+        let ban: Ban = serde_json::from_value(serde_json::json!({
+            "reason": "Awoxing",
+            "public_reason": "Breaking the rules",
+            "fc_note": "Argues in comms",
+        }))
+        .unwrap();
+
+        // Seeing the internal reason isn't enough
+        let redacted = ban.clone().redact(BanVisibility {
+            reason: true,
+            public_reason: true,
+            fc_note: false,
+        });
+        assert_eq!(redacted.reason, "Awoxing");
+        assert_eq!(redacted.fc_note, None);
+
+        let visible = ban.redact(BanVisibility {
+            reason: true,
+            public_reason: true,
+            fc_note: true,
+        });
+        assert_eq!(visible.fc_note.as_deref(), Some("Argues in comms"));
+    }
+
+    #[test]
+    fn test_differences() {
+        let a: Ban = serde_json::from_value(serde_json::json!({
+            "reason": "Awoxing",
+            "public_reason": "Breaking the rules",
+            "revoked_at": 1700000000,
+        }))
+        .unwrap();
+        let mut b = a.clone();
+        assert!(a.differences(&b).is_empty());
+
+        b.reason = "Awoxing again".to_string();
+        b.revoked_at = None;
+        let differences = a.differences(&b);
+        assert_eq!(
+            differences
+                .iter()
+                .map(|difference| difference.field)
+                .collect::<Vec<_>>(),
+            vec!["reason", "revoked_at"]
+        );
+        assert_eq!(differences[1].a, serde_json::json!(1700000000));
+        assert!(differences[1].b.is_null());
+    }
+
+    #[test]
+    fn test_v2_shape() {
+        let ban: Ban = serde_json::from_value(serde_json::json!({
+            "reason": "x",
+            "issued_at": 1000,
+            "tags": ["rmt"],
+        }))
+        .unwrap();
+
+        let v2 = ban
+            .clone()
+            .in_timezone(chrono_tz::UTC, 1000)
+            .at_version(ApiVersion::V2);
+        let v2 = serde_json::to_value(v2).unwrap();
+        let mut keys: Vec<&str> = v2.as_object().unwrap().keys().map(|k| k.as_str()).collect();
+        keys.sort();
+        assert_eq!(
+            keys,
+            vec![
+                "entity",
+                "id",
+                "issued_at",
+                "issued_by",
+                "public_reason",
+                "reason",
+                "revoked_at",
+                "revoked_by"
+            ]
+        );
+
+        let v3 = serde_json::to_value(ban.in_timezone(chrono_tz::UTC, 1000)).unwrap();
+        assert_eq!(v3["tags"], serde_json::json!(["rmt"]));
+    }
+
+    #[test]
+    fn test_humanize_duration() {
+        assert_eq!(humanize_duration(30), "less than a minute");
+        assert_eq!(humanize_duration(60), "1 minute");
+        assert_eq!(humanize_duration(60 * 60 * 5 + 59), "5 hours");
+        assert_eq!(humanize_duration(60 * 60 * 24 * 3), "3 days");
+        assert_eq!(humanize_duration(60 * 60 * 24 * 400), "1 year");
+    }
+
+    #[test]
+    fn test_relative_timestamp() {
+        let now = 1_000_000;
+        assert_eq!(
+            relative_timestamp(Some(now - 60 * 60 * 24 * 3), now, "expires in"),
+            Some("3 days ago".to_string())
+        );
+        assert_eq!(
+            relative_timestamp(Some(now + 60 * 60 * 2), now, "expires in"),
+            Some("expires in 2 hours".to_string())
+        );
+        assert_eq!(relative_timestamp(None, now, "expires in"), None);
+    }
+}